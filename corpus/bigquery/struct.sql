@@ -0,0 +1 @@
+select `project.dataset.table`.id, array_agg(struct(name, value)) as attrs from `project.dataset.table` group by 1;
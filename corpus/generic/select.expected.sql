@@ -0,0 +1,19 @@
+select
+  a.id,
+  a.name,
+  count(*) as total
+from
+  accounts a
+  join orders o on o.account_id = a.id
+where
+  a.active = true
+  and o.created_at > '2024-01-01'
+group by
+  a.id,
+  a.name
+having
+  count(*) > 5
+order by
+  total desc
+limit
+  10;
@@ -0,0 +1 @@
+SELECT a.id, a.name, count(*) AS total FROM accounts a JOIN orders o ON o.account_id = a.id WHERE a.active = TRUE AND o.created_at > '2024-01-01' GROUP BY a.id, a.name HAVING count(*) > 5 ORDER BY total DESC LIMIT 10;
@@ -0,0 +1,15 @@
+DELIMITER //
+create procedure touch()
+begin
+update
+  t
+set
+  n = n + 1;
+end//
+DELIMITER ;
+select
+  `name`
+from
+  `t`
+where
+  id = 0x1F;
@@ -0,0 +1,4 @@
+DELIMITER //
+CREATE PROCEDURE touch() BEGIN UPDATE t SET n = n + 1; END //
+DELIMITER ;
+SELECT `name` FROM `t` WHERE id = 0x1F;
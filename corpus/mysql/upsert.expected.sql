@@ -0,0 +1,6 @@
+insert into
+  counters (name, hits)
+values
+  ('home', 1)
+on duplicate key update
+  hits = hits + values(hits);
@@ -0,0 +1 @@
+INSERT INTO counters (name, hits) VALUES ('home', 1) ON DUPLICATE KEY UPDATE hits = hits + VALUES(hits);
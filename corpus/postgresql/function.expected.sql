@@ -0,0 +1,5 @@
+create function add_one(i integer) returns integer as $$ select i + 1 $$ language sql;
+select
+  e'a\nb',
+  u&'d\0061t',
+  $tag$ kept as written $tag$;
@@ -0,0 +1,2 @@
+insert into users (id, email, updated_at) values (1, 'a@example.com', now()) on conflict (id) do update set email = excluded.email, updated_at = excluded.updated_at returning id;
+insert into tags (name) values ('sql') on conflict do nothing;
@@ -0,0 +1 @@
+select id, row_number() over (partition by account_id order by created_at desc) as rn from events qualify rn = 1;
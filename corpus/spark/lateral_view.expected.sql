@@ -0,0 +1,10 @@
+select
+  id,
+  tag
+from
+  posts
+  lateral view explode(tags) t as tag
+distribute by
+  id
+sort by
+  tag;
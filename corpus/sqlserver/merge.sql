@@ -0,0 +1,2 @@
+merge dbo.Target as t using dbo.Source as s on (t.Id = s.Id) when matched then update set t.Name = s.Name when not matched by target then insert (Id, Name) values (s.Id, s.Name);
+select top 10 [Id], [Name] from dbo.Target;
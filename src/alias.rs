@@ -0,0 +1,244 @@
+//! Column aliases in select lists, per `asKeyword` and `alignAliases`.
+//!
+//! An alias is the last token of a select-list item when it's an identifier
+//! following `AS` or directly following the end of an expression. With
+//! `asKeyword` the optional `AS` is added or removed, and with
+//! `alignAliases` the aliases of runs of items on lines of their own are
+//! lined up at a common column.
+
+use crate::AsKeyword;
+use crate::Configuration;
+use crate::Dialect;
+use crate::keywords::is_keyword;
+use crate::lexer::Token;
+use crate::lexer::TokenKind;
+use crate::lexer::tokenize;
+
+/// Keywords that end a select list when they appear outside of parentheses.
+const TERMINATORS: &[&str] = &[
+    "except",
+    "fetch",
+    "from",
+    "group",
+    "having",
+    "intersect",
+    "into",
+    "limit",
+    "minus",
+    "offset",
+    "order",
+    "qualify",
+    "union",
+    "where",
+    "window",
+];
+
+/// A select-list item, as indexes into the tokens.
+struct Item {
+    /// The first and last significant tokens.
+    first: usize,
+    last: usize,
+    /// The `AS` before the alias, if any.
+    as_keyword: Option<usize>,
+    /// The alias, which is always `last` when present.
+    alias: Option<usize>,
+}
+
+pub(crate) fn layout(formatted: String, config: &Configuration, dialect: Dialect) -> String {
+    let formatted = if config.as_keyword == AsKeyword::Preserve {
+        formatted
+    } else {
+        normalize_as(&formatted, config, dialect)
+    };
+    if config.align_aliases {
+        align(&formatted, dialect)
+    } else {
+        formatted
+    }
+}
+
+fn normalize_as(formatted: &str, config: &Configuration, dialect: Dialect) -> String {
+    let tokens = tokenize(formatted, dialect);
+    let as_text = if config.uppercase { "AS" } else { "as" };
+    let mut result = String::with_capacity(formatted.len());
+    let mut pos = 0;
+    let mut items: Vec<_> = select_lists(&tokens).into_iter().flatten().collect();
+    items.sort_by_key(|item| item.last);
+    for item in items {
+        let Some(alias) = item.alias.map(|i| &tokens[i]) else {
+            continue;
+        };
+        match (config.as_keyword, item.as_keyword) {
+            (AsKeyword::Always, None) => {
+                result.push_str(&formatted[pos..alias.start]);
+                result.push_str(as_text);
+                result.push(' ');
+                pos = alias.start;
+            }
+            // keywords can only be aliases after `AS` in some dialects
+            (AsKeyword::Never, Some(as_keyword))
+                if alias.kind == TokenKind::QuotedIdentifier || !is_keyword(alias.text) =>
+            {
+                result.push_str(&formatted[pos..tokens[as_keyword].start]);
+                pos = alias.start;
+            }
+            _ => {}
+        }
+    }
+    result.push_str(&formatted[pos..]);
+    result
+}
+
+fn align(formatted: &str, dialect: Dialect) -> String {
+    let tokens = tokenize(formatted, dialect);
+    let mut result = String::with_capacity(formatted.len());
+    let mut pos = 0;
+    let lists = select_lists(&tokens);
+    let mut runs: Vec<_> = lists
+        .iter()
+        .flat_map(|items| runs(formatted, &tokens, items))
+        .collect();
+    // nested select lists come after the one they are in
+    runs.sort_by_key(|run| run[0].first);
+    for run in runs {
+        // the column just after the longest expression
+        let alias_column = run
+            .iter()
+            .map(|item| column(formatted, expression_end(&tokens, item)))
+            .max()
+            .unwrap_or_default()
+            + 1;
+        for item in run {
+            let end = expression_end(&tokens, item);
+            let alias_start = tokens[item.as_keyword.or(item.alias).unwrap()].start;
+            result.push_str(&formatted[pos..end]);
+            result.push_str(&" ".repeat(alias_column - column(formatted, end)));
+            pos = alias_start;
+        }
+    }
+    result.push_str(&formatted[pos..]);
+    result
+}
+
+/// The runs of aliased items that each sit on a line of their own, broken by
+/// items spanning several lines or sharing one.
+fn runs<'a>(formatted: &str, tokens: &[Token], items: &'a [Item]) -> Vec<Vec<&'a Item>> {
+    let mut runs = vec![Vec::new()];
+    for item in items {
+        let first = &tokens[item.first];
+        let last = &tokens[item.last];
+        let line_start = formatted[..first.start].rfind('\n').map_or(0, |i| i + 1);
+        let own_line = formatted[line_start..first.start].trim().is_empty()
+            && !formatted[first.start..last.start].contains('\n')
+            && formatted[last.start + last.text.len()..]
+                .split('\n')
+                .next()
+                .is_some_and(|rest| {
+                    let rest = rest.trim_start().strip_prefix(',').unwrap_or(rest).trim();
+                    rest.is_empty() || rest.starts_with("--")
+                });
+        if !own_line {
+            runs.push(Vec::new());
+        } else if item.alias.is_some() {
+            runs.last_mut().unwrap().push(item);
+        }
+    }
+    runs.retain(|run| run.len() > 1);
+    runs
+}
+
+/// The offset just after the expression an item aliases.
+fn expression_end(tokens: &[Token], item: &Item) -> usize {
+    let alias_start = item.as_keyword.or(item.alias).unwrap();
+    let expression = tokens[..alias_start]
+        .iter()
+        .rfind(|t| t.kind != TokenKind::Whitespace)
+        .unwrap();
+    expression.start + expression.text.len()
+}
+
+/// The column, in characters, of `offset` on its line.
+fn column(text: &str, offset: usize) -> usize {
+    let line_start = text[..offset].rfind('\n').map_or(0, |i| i + 1);
+    text[line_start..offset].chars().count()
+}
+
+/// The items of every select list in `tokens`, in order.
+fn select_lists(tokens: &[Token]) -> Vec<Vec<Item>> {
+    let mut lists = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        if token.is_word("select") {
+            lists.push(items(tokens, i + 1));
+        }
+    }
+    lists
+}
+
+fn items(tokens: &[Token], start: usize) -> Vec<Item> {
+    let mut items = Vec::new();
+    let mut significant = Vec::new();
+    let mut depth = 0usize;
+    for (i, token) in tokens.iter().enumerate().skip(start) {
+        match token.kind {
+            TokenKind::OpenParen => depth += 1,
+            TokenKind::CloseParen if depth == 0 => break,
+            TokenKind::CloseParen => depth -= 1,
+            TokenKind::Semicolon => break,
+            TokenKind::Comma if depth == 0 => {
+                items.extend(item(tokens, &significant));
+                significant.clear();
+                continue;
+            }
+            TokenKind::Word if depth == 0 && TERMINATORS.iter().any(|w| token.is_word(w)) => {
+                break;
+            }
+            _ => {}
+        }
+        if !token.is_trivia() {
+            significant.push(i);
+        }
+    }
+    items.extend(item(tokens, &significant));
+    items
+}
+
+/// The item made of the significant tokens at `indexes`.
+fn item(tokens: &[Token], indexes: &[usize]) -> Option<Item> {
+    let (&first, &last) = (indexes.first()?, indexes.last()?);
+    let mut item = Item {
+        first,
+        last,
+        as_keyword: None,
+        alias: None,
+    };
+    let [.., before, _] = indexes else {
+        return Some(item);
+    };
+    let alias = &tokens[last];
+    let before_token = &tokens[*before];
+    let is_identifier = alias.kind == TokenKind::QuotedIdentifier
+        || (alias.kind == TokenKind::Word && !alias.text.starts_with('@'));
+    if !is_identifier {
+        return Some(item);
+    }
+    if before_token.is_word("as") {
+        // `AS` alone is no expression
+        if indexes.len() > 2 {
+            item.as_keyword = Some(*before);
+            item.alias = Some(last);
+        }
+        return Some(item);
+    }
+    let ends_expression = match before_token.kind {
+        TokenKind::Word => !is_keyword(before_token.text) || before_token.is_word("end"),
+        TokenKind::QuotedIdentifier
+        | TokenKind::Number
+        | TokenKind::String
+        | TokenKind::CloseParen => true,
+        _ => false,
+    };
+    if ends_expression && (alias.kind == TokenKind::QuotedIdentifier || !is_keyword(alias.text)) {
+        item.alias = Some(last);
+    }
+    Some(item)
+}
@@ -0,0 +1,122 @@
+//! Indentation of the arguments of function calls broken over several lines,
+//! per `functionArgumentIndent`.
+//!
+//! sqlformat, and the `maxInlineFunctionArguments` layout, put each argument
+//! of a long call on a line of its own, one indent deeper than the call, with
+//! the closing parenthesis on a line of its own. With the aligned style, the
+//! first argument stays after the opening parenthesis, the others line up
+//! under it, and the closing parenthesis follows the last one:
+//!
+//! ```sql
+//! select
+//!   coalesce(a.very_long_column_name_one,
+//!            b.very_long_column_name_two,
+//!            'default') as v
+//! ```
+//!
+//! The alignment is made of spaces after the indentation of the line the call
+//! starts on, so it holds with tabs too. Calls with comments in their
+//! arguments are left as they are.
+
+use crate::Configuration;
+use crate::Dialect;
+use crate::FunctionArgumentIndent;
+use crate::inline_list::is_function_name;
+use crate::lexer::Token;
+use crate::lexer::TokenKind;
+use crate::lexer::matching_paren;
+use crate::lexer::tokenize;
+
+/// A call whose arguments are being aligned.
+struct Call {
+    /// The index of its closing parenthesis.
+    close: usize,
+    /// The indentation of its arguments as sqlformat wrote them.
+    indent: usize,
+    /// The indentation lining them up with the first one.
+    aligned: String,
+}
+
+pub(crate) fn align(formatted: String, config: &Configuration, dialect: Dialect) -> String {
+    if config.function_argument_indent == FunctionArgumentIndent::Continuation {
+        return formatted;
+    }
+    let tokens = tokenize(&formatted, dialect);
+    let mut result = String::with_capacity(formatted.len());
+    // the calls the tokens are in, innermost last
+    let mut calls: Vec<Call> = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = &tokens[i];
+        if let Some((close, indent)) = broken_call(&tokens, i) {
+            result.push('(');
+            let line_start = result.rfind('\n').map_or(0, |i| i + 1);
+            let line = &result[line_start..];
+            let code = line.trim_start();
+            let aligned = format!(
+                "{}{}",
+                &line[..line.len() - code.len()],
+                " ".repeat(code.chars().count())
+            );
+            calls.push(Call {
+                close,
+                indent,
+                aligned,
+            });
+            // the first argument goes on the line of the parenthesis
+            i += 2;
+            continue;
+        }
+        let is_line_break = token.kind == TokenKind::Whitespace && token.text.contains('\n');
+        match calls.last() {
+            // the closing parenthesis goes after the last argument
+            Some(call) if is_line_break && call.close == i + 1 => {}
+            Some(call) if is_line_break => {
+                let (lines, last) = token.text.rsplit_once('\n').unwrap();
+                result.push_str(lines);
+                result.push('\n');
+                match last.get(call.indent..) {
+                    Some(rest) => {
+                        result.push_str(&call.aligned);
+                        result.push_str(rest);
+                    }
+                    None => result.push_str(last),
+                }
+            }
+            Some(call) if call.close == i => {
+                calls.pop();
+                result.push_str(token.text);
+            }
+            _ => result.push_str(token.text),
+        }
+        i += 1;
+    }
+    result
+}
+
+/// The closing parenthesis and argument indentation of the call whose opening
+/// parenthesis is `tokens[open]`, if its arguments start on the line after it
+/// and its closing parenthesis is on a line of its own.
+fn broken_call(tokens: &[Token], open: usize) -> Option<(usize, usize)> {
+    if tokens[open].kind != TokenKind::OpenParen || open == 0 || !is_function_name(tokens, open - 1)
+    {
+        return None;
+    }
+    let close = matching_paren(tokens, open)?;
+    let after_open = tokens.get(open + 1)?;
+    let before_close = &tokens[close - 1];
+    let (_, indent) = after_open
+        .text
+        .rsplit_once('\n')
+        .filter(|_| after_open.kind == TokenKind::Whitespace)?;
+    let arguments = &tokens[open + 1..close];
+    if before_close.kind != TokenKind::Whitespace
+        || !before_close.text.contains('\n')
+        || arguments
+            .iter()
+            .any(|t| matches!(t.kind, TokenKind::LineComment | TokenKind::BlockComment))
+    {
+        return None;
+    }
+    Some((close, indent.len()))
+}
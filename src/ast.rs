@@ -0,0 +1,305 @@
+//! The `ast` engine, which lays out statements from the syntax tree sqlparser
+//! parses them into rather than from the tokens sqlformat reads.
+//!
+//! sqlparser's tree has no place for comments and writes some constructs in
+//! a form of its own, so each statement is checked to keep the tokens and
+//! literals of the input, the way `validateOutput` checks them. Statements
+//! that don't parse, or that would change, are formatted by the token engine
+//! instead.
+
+use std::collections::HashMap;
+
+use sqlparser::ast::Insert;
+use sqlparser::ast::Statement;
+use sqlparser::dialect;
+use sqlparser::keywords::ALL_KEYWORDS;
+use sqlparser::parser::Parser;
+
+use crate::Configuration;
+use crate::Dialect;
+use crate::identifiers;
+use crate::keywords::is_keyword;
+use crate::lexer::TokenKind;
+use crate::lexer::continued_lines;
+use crate::lexer::tokenize;
+use crate::literal_case;
+use crate::literals;
+use crate::statements;
+use crate::validate;
+
+/// The indentation sqlparser writes for each level.
+const INDENT: &str = "  ";
+
+/// Keywords starting the clauses of a query that sqlparser writes on the
+/// line of the clause before.
+const QUERY_CLAUSES: &[&str] = &["FETCH", "LIMIT", "OFFSET", "ORDER"];
+
+/// Clauses whose contents sqlparser doesn't indent.
+const UNINDENTED_CLAUSES: &[&str] = &["DISTRIBUTE BY", "QUALIFY", "WINDOW"];
+
+/// The first words of the clauses that can follow those.
+const FOLLOWING_CLAUSES: &[&str] = &[
+    "CLUSTER",
+    "DISTRIBUTE",
+    "EXCEPT",
+    "FETCH",
+    "HAVING",
+    "INTERSECT",
+    "LIMIT",
+    "MINUS",
+    "OFFSET",
+    "ORDER",
+    "QUALIFY",
+    "SORT",
+    "UNION",
+    "WINDOW",
+];
+
+/// Formats the statements of `text`, using `fallback` for those that can't
+/// be formatted from their syntax tree. Consecutive statements that can't are
+/// given to `fallback` together, since a statement that doesn't parse may be
+/// part of the one before, like the body of a MySQL procedure.
+pub(crate) fn format(
+    text: &str,
+    config: &Configuration,
+    dialect: Dialect,
+    fallback: impl Fn(&str) -> String,
+) -> String {
+    let offset = |statement: &str| statement.as_ptr() as usize - text.as_ptr() as usize;
+    let mut formatted = Vec::new();
+    // the start of the statements left to `fallback` so far
+    let mut unformatted = None;
+    let mut end = 0;
+    for statement in statements::split(text, dialect) {
+        match format_statement(statement.text, config, dialect) {
+            Some(statement) => {
+                if let Some(start) = unformatted.take() {
+                    formatted.push(fallback(&text[start..end]));
+                }
+                formatted.push(statement);
+            }
+            None => {
+                unformatted.get_or_insert(offset(statement.text));
+            }
+        }
+        end = offset(statement.text) + statement.text.len();
+    }
+    if let Some(start) = unformatted {
+        formatted.push(fallback(&text[start..end]));
+    }
+    formatted.join(&"\n".repeat(config.lines_between_queries.max(1).into()))
+}
+
+fn format_statement(text: &str, config: &Configuration, dialect: Dialect) -> Option<String> {
+    let text = identifiers::normalize_quotes(text, config.quote_identifiers, dialect);
+    let text = identifiers::change_case(&text, config, dialect);
+    let parsed = Parser::parse_sql(parser_dialect(dialect).as_ref(), &text).ok()?;
+    let [statement] = parsed.as_slice() else {
+        return None;
+    };
+    // which sqlparser writes on one line
+    if matches!(
+        statement,
+        Statement::Merge(_)
+            | Statement::Grant(_)
+            | Statement::Revoke(_)
+            | Statement::Insert(Insert { on: Some(_), .. })
+    ) {
+        return None;
+    }
+    let mut printed = format!("{statement:#}");
+    let tokens = tokenize(&text, dialect);
+    if tokens
+        .iter()
+        .rev()
+        .find(|t| !t.is_trivia())
+        .is_some_and(|t| t.kind == TokenKind::Semicolon)
+    {
+        printed.push(';');
+    }
+    let printed = break_query_clauses(&printed, dialect);
+    let printed = indent_clause_contents(&printed, dialect);
+    let printed = indent(&recase(&printed, &text, config, dialect), config, dialect);
+    let printed = literal_case::change_case(printed, config, dialect);
+    if validate::compare(&text, &printed, config, dialect).is_err()
+        || literals::verify(&text, &printed, config, dialect).is_err()
+    {
+        return None;
+    }
+    Some(printed)
+}
+
+/// Puts the `ORDER BY`, `LIMIT`, `OFFSET` and `FETCH` of queries on lines of
+/// their own, like the other clauses, with the contents of the first three
+/// indented on the line below.
+fn break_query_clauses(printed: &str, dialect: Dialect) -> String {
+    let tokens = tokenize(printed, dialect);
+    // the indentation of the queries the tokens are in, or `None` inside
+    // parentheses kept on one line
+    let mut bases = vec![Some("")];
+    let mut result = String::with_capacity(printed.len());
+    let mut pos = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        match token.kind {
+            TokenKind::OpenParen => bases.push(
+                tokens
+                    .get(i + 1)
+                    .filter(|t| t.kind == TokenKind::Whitespace)
+                    .and_then(|t| t.text.rsplit_once('\n'))
+                    .map(|(_, indent)| indent),
+            ),
+            TokenKind::CloseParen if bases.len() > 1 => {
+                bases.pop();
+            }
+            TokenKind::Word if QUERY_CLAUSES.contains(&token.text) => {
+                let Some(&Some(base)) = bases.last() else {
+                    continue;
+                };
+                let before = &tokens[i - 1];
+                if before.kind != TokenKind::Whitespace || before.text.contains('\n') {
+                    continue;
+                }
+                let end = if token.text == "ORDER" {
+                    match tokens.get(i + 2) {
+                        Some(by) if by.text == "BY" => i + 2,
+                        _ => continue,
+                    }
+                } else {
+                    i
+                };
+                result.push_str(&printed[pos..before.start]);
+                result.push('\n');
+                result.push_str(base);
+                pos = token.start;
+                let after = tokens.get(end + 1);
+                if token.text != "FETCH"
+                    && let Some(after) = after.filter(|t| t.kind == TokenKind::Whitespace)
+                {
+                    result.push_str(&printed[pos..after.start]);
+                    result.push('\n');
+                    result.push_str(base);
+                    result.push_str(INDENT);
+                    pos = after.start + after.text.len();
+                }
+            }
+            _ => {}
+        }
+    }
+    result.push_str(&printed[pos..]);
+    result
+}
+
+/// Indents the contents of the clauses sqlparser writes at the level of their
+/// keyword.
+fn indent_clause_contents(printed: &str, dialect: Dialect) -> String {
+    let continued = continued_lines(printed, dialect);
+    let mut lines: Vec<_> = printed.split('\n').map(str::to_string).collect();
+    let mut i = 0;
+    while i < lines.len() {
+        let base = indent_len(&lines[i]);
+        i += 1;
+        if !UNINDENTED_CLAUSES.contains(&lines[i - 1].trim()) {
+            continue;
+        }
+        while i < lines.len() {
+            let line = &lines[i];
+            let ends = indent_len(line) < base
+                || (indent_len(line) == base
+                    && (line.trim_start().starts_with(')')
+                        || FOLLOWING_CLAUSES
+                            .iter()
+                            .any(|clause| line.trim_start().split(' ').next() == Some(clause))));
+            if ends && continued.binary_search(&i).is_err() {
+                break;
+            }
+            if continued.binary_search(&i).is_err() {
+                lines[i].insert_str(0, INDENT);
+            }
+            i += 1;
+        }
+    }
+    lines.join("\n")
+}
+
+fn indent_len(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+/// Writes the keywords of `printed`, which sqlparser writes in uppercase, in
+/// the configured case.
+fn recase(printed: &str, input: &str, config: &Configuration, dialect: Dialect) -> String {
+    // the spellings of the words as written, by their lowercase
+    let mut spellings = HashMap::new();
+    for token in tokenize(input, dialect) {
+        if token.kind == TokenKind::Word {
+            spellings
+                .entry(token.text.to_lowercase())
+                .or_insert(token.text);
+        }
+    }
+    let is_exception = |word: &str| {
+        let ignored = config.ignore_case_convert.iter().flatten();
+        config
+            .case_exceptions
+            .iter()
+            .chain(ignored)
+            .any(|w| w.eq_ignore_ascii_case(word))
+    };
+    let mut result = String::with_capacity(printed.len());
+    for token in tokenize(printed, dialect) {
+        let written = spellings.get(&token.text.to_lowercase()).copied();
+        let is_sqlparser_keyword = ALL_KEYWORDS
+            .binary_search(&token.text.to_uppercase().as_str())
+            .is_ok();
+        // words sqlparser changed the case of are keywords it wrote itself,
+        // the others may be identifiers that happen to be keywords
+        let is_keyword = token.kind == TokenKind::Word
+            && token.text == token.text.to_uppercase()
+            && is_sqlparser_keyword
+            && (is_keyword(token.text) || written != Some(token.text));
+        match written {
+            Some(written) if is_keyword && is_exception(token.text) => result.push_str(written),
+            _ if is_keyword && !config.uppercase => result.push_str(&token.text.to_lowercase()),
+            _ => result.push_str(token.text),
+        }
+    }
+    result
+}
+
+/// Replaces the indentation sqlparser writes with the configured one, and
+/// drops the spaces it leaves at the end of some lines.
+fn indent(printed: &str, config: &Configuration, dialect: Dialect) -> String {
+    let indent = config.indent();
+    let mut result = String::with_capacity(printed.len());
+    for token in tokenize(printed, dialect) {
+        let Some((lines, last)) = token
+            .text
+            .rsplit_once('\n')
+            .filter(|_| token.kind == TokenKind::Whitespace)
+        else {
+            result.push_str(token.text);
+            continue;
+        };
+        for line in lines.split('\n') {
+            result.push_str(line.trim_end_matches(' '));
+            result.push('\n');
+        }
+        let code = last.trim_start_matches(' ');
+        let levels = (last.len() - code.len()) / INDENT.len();
+        result.push_str(&indent.repeat(levels));
+        result.push_str(code);
+    }
+    result
+}
+
+fn parser_dialect(dialect: Dialect) -> Box<dyn dialect::Dialect> {
+    match dialect {
+        Dialect::Generic => Box::new(dialect::GenericDialect {}),
+        Dialect::PostgreSql => Box::new(dialect::PostgreSqlDialect {}),
+        Dialect::MySql => Box::new(dialect::MySqlDialect {}),
+        Dialect::SqlServer => Box::new(dialect::MsSqlDialect {}),
+        Dialect::Snowflake => Box::new(dialect::SnowflakeDialect),
+        Dialect::BigQuery => Box::new(dialect::BigQueryDialect),
+        Dialect::Spark => Box::new(dialect::SparkSqlDialect),
+    }
+}
@@ -0,0 +1,173 @@
+//! Layout of `GROUP BY`, `ORDER BY` and `PARTITION BY` lists, per
+//! `maxInlineListItems`.
+//!
+//! sqlformat decides whether to break these lists by their length in
+//! characters. With a maximum item count, lists with at most that many items
+//! are kept on one line and longer ones get one item per line.
+
+use crate::Configuration;
+use crate::Dialect;
+use crate::lexer::Token;
+use crate::lexer::TokenKind;
+use crate::lexer::tokenize;
+use crate::trace;
+
+/// Keywords that end a list when they appear outside of parentheses.
+const TERMINATORS: &[&str] = &[
+    "except",
+    "fetch",
+    "for",
+    "from",
+    "groups",
+    "having",
+    "intersect",
+    "into",
+    "limit",
+    "minus",
+    "offset",
+    "order",
+    "qualify",
+    "range",
+    "returning",
+    "rows",
+    "select",
+    "union",
+    "where",
+    "window",
+    "with",
+];
+
+pub(crate) fn layout(formatted: String, config: &Configuration, dialect: Dialect) -> String {
+    let Some(max_items) = config.max_inline_list_items else {
+        return formatted;
+    };
+    let tokens = tokenize(&formatted, dialect);
+    let indent = config.indent();
+    let mut result = String::with_capacity(formatted.len());
+    let mut pos = 0;
+    let mut i = 0;
+    while i < tokens.len() {
+        let is_list = tokens[i].is_word("by")
+            && previous_significant(&tokens, i).is_some_and(|t| {
+                t.is_word("group") || t.is_word("order") || t.is_word("partition")
+            });
+        if !is_list {
+            i += 1;
+            continue;
+        }
+        let (items, end) = items(&tokens, i + 1, TERMINATORS);
+        if items.is_empty()
+            || items
+                .iter()
+                .flat_map(|item| item.iter())
+                .any(|t| t.kind == TokenKind::LineComment)
+        {
+            i = end;
+            continue;
+        }
+        let texts: Vec<_> = items.iter().map(|item| text(item)).collect();
+        let last = items.last().and_then(|item| item.last()).unwrap();
+        let list_end = last.start + last.text.len();
+        let by = &tokens[i];
+        let before = &tokens[i + 1];
+        i = end;
+
+        let separator = if texts.len() <= max_items {
+            if texts.iter().any(|t| t.contains('\n')) {
+                continue;
+            }
+            trace::decided("by list: inline");
+            ", ".to_string()
+        } else {
+            // breaking a list that has more after it on the same line would
+            // leave that after the last item
+            let rest = &formatted[list_end..];
+            let rest_of_line = rest.split('\n').next().unwrap_or_default();
+            if !rest_of_line.trim().is_empty() && rest_of_line.trim() != ";" {
+                continue;
+            }
+            let line_start = formatted[..by.start].rfind('\n').map_or(0, |i| i + 1);
+            let line = &formatted[line_start..by.start];
+            let base = &line[..line.len() - line.trim_start().len()];
+            trace::decided("by list: one item per line");
+            format!(",\n{base}{indent}")
+        };
+        result.push_str(&formatted[pos..by.start + by.text.len()]);
+        if texts.len() > max_items {
+            result.push_str(&separator[1..]);
+        } else if before.kind == TokenKind::Whitespace && before.text.contains('\n') {
+            // keep the break sqlformat put before the first item
+            result.push_str(before.text);
+        } else {
+            result.push(' ');
+        }
+        result.push_str(&texts.join(&separator));
+        pos = list_end;
+    }
+    result.push_str(&formatted[pos..]);
+    result
+}
+
+/// The comma separated items of the list starting at `tokens[start]`, each
+/// without surrounding whitespace, and the index of the token ending it: a
+/// closing parenthesis, semicolon or one of `terminators`.
+pub(crate) fn items<'a>(
+    tokens: &'a [Token<'a>],
+    start: usize,
+    terminators: &[&str],
+) -> (Vec<&'a [Token<'a>]>, usize) {
+    let mut items = Vec::new();
+    let mut item_start = start;
+    let mut depth = 0usize;
+    let mut i = start;
+    while i < tokens.len() {
+        let token = &tokens[i];
+        match token.kind {
+            TokenKind::OpenParen => depth += 1,
+            TokenKind::CloseParen if depth == 0 => break,
+            TokenKind::CloseParen => depth -= 1,
+            TokenKind::Semicolon => break,
+            TokenKind::Comma if depth == 0 => {
+                items.push(trim(&tokens[item_start..i]));
+                item_start = i + 1;
+            }
+            TokenKind::Word if depth == 0 && terminators.iter().any(|w| token.is_word(w)) => {
+                break;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    let last = trim(&tokens[item_start..i]);
+    if !last.is_empty() {
+        items.push(last);
+    }
+    if items.iter().any(|item| item.is_empty()) {
+        items.clear();
+    }
+    (items, i)
+}
+
+fn trim<'a>(mut tokens: &'a [Token<'a>]) -> &'a [Token<'a>] {
+    while tokens
+        .first()
+        .is_some_and(|t| t.kind == TokenKind::Whitespace)
+    {
+        tokens = &tokens[1..];
+    }
+    while tokens
+        .last()
+        .is_some_and(|t| t.kind == TokenKind::Whitespace)
+    {
+        tokens = &tokens[..tokens.len() - 1];
+    }
+    tokens
+}
+
+pub(crate) fn text(tokens: &[Token]) -> String {
+    tokens.iter().map(|t| t.text).collect()
+}
+
+fn previous_significant<'a>(tokens: &'a [Token<'a>], i: usize) -> Option<&'a Token<'a>> {
+    tokens[..i].iter().rev().find(|t| !t.is_trivia())
+}
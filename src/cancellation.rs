@@ -0,0 +1,25 @@
+//! Stopping a format the host no longer needs, like a format on save the
+//! editor cancelled. The token is checked between statements, which are
+//! otherwise formatted as usual, and the formatted text thrown away.
+
+use dprint_core::plugins::CancellationToken;
+
+/// An error saying the host cancelled formatting before it finished.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CancelledError;
+
+impl std::fmt::Display for CancelledError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Formatting was cancelled.")
+    }
+}
+
+impl std::error::Error for CancelledError {}
+
+pub(crate) fn check(token: &dyn CancellationToken) -> Result<(), CancelledError> {
+    if token.is_cancelled() {
+        Err(CancelledError)
+    } else {
+        Ok(())
+    }
+}
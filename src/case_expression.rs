@@ -0,0 +1,217 @@
+//! Layout of `CASE ... END` expressions, per `caseStyle` and
+//! `caseInlineWidth`.
+//!
+//! sqlformat puts the operand of a simple CASE on its own line and indents
+//! nested CASEs relative to wherever they happen to start. This pass rewrites
+//! its output: short expressions are joined onto one line, and with the
+//! aligned style each `WHEN`/`ELSE` branch gets exactly one line with `END`
+//! lined up under `CASE`, or under the start of the line for a CASE that
+//! doesn't start one.
+
+use crate::CaseStyle;
+use crate::Configuration;
+use crate::Dialect;
+use crate::lexer::Token;
+use crate::lexer::TokenKind;
+use crate::lexer::tokenize;
+use crate::trace;
+
+pub(crate) fn layout(formatted: String, config: &Configuration, dialect: Dialect) -> String {
+    if config.case_style == CaseStyle::Default && config.case_inline_width == 0 {
+        return formatted;
+    }
+    let tokens = tokenize(&formatted, dialect);
+    let mut layout = Layout {
+        tokens: &tokens,
+        config,
+        indent: config.indent(),
+        out: String::with_capacity(formatted.len()),
+    };
+    layout.verbatim(0, tokens.len());
+    layout.out
+}
+
+struct Layout<'a> {
+    tokens: &'a [Token<'a>],
+    config: &'a Configuration,
+    indent: String,
+    out: String,
+}
+
+impl Layout<'_> {
+    /// Writes `tokens[start..end]` as sqlformat laid them out, except for
+    /// CASE expressions.
+    fn verbatim(&mut self, start: usize, end: usize) {
+        let mut i = start;
+        while i < end {
+            match self.case_end(i).filter(|&j| j < end) {
+                Some(j) => {
+                    self.case(i, j);
+                    i = j + 1;
+                }
+                None => {
+                    self.out.push_str(self.tokens[i].text);
+                    i += 1;
+                }
+            }
+        }
+    }
+
+    /// Writes `tokens[start..end]` on a single line, except for CASE
+    /// expressions that don't fit.
+    fn joined(&mut self, start: usize, end: usize) {
+        let mut i = start;
+        while i < end {
+            let token = &self.tokens[i];
+            if let Some(j) = self.case_end(i).filter(|&j| j < end) {
+                self.case(i, j);
+                i = j + 1;
+                continue;
+            }
+            if token.kind == TokenKind::Whitespace && (i == start || i + 1 == end) {
+                // the caller decides what goes around the range
+            } else if token.kind == TokenKind::Whitespace && token.text.contains('\n') {
+                let after_open = self.tokens[i - 1].kind == TokenKind::OpenParen;
+                let before_close = self.tokens[i + 1].kind == TokenKind::CloseParen;
+                if !after_open && !before_close {
+                    self.out.push(' ');
+                }
+            } else {
+                self.out.push_str(token.text);
+            }
+            i += 1;
+        }
+    }
+
+    /// Writes the CASE expression spanning `tokens[start..=end]`.
+    fn case(&mut self, start: usize, end: usize) {
+        let range = &self.tokens[start..=end];
+        // joining lines would comment out the rest of the expression, and
+        // `END CASE` closes a procedural CASE statement rather than an
+        // expression
+        if range.iter().any(|t| t.kind == TokenKind::LineComment) || self.is_statement(end) {
+            trace::decided("case: kept as it is");
+            self.out.push_str(self.tokens[start].text);
+            self.verbatim(start + 1, end + 1);
+            return;
+        }
+
+        let width = self.config.case_inline_width;
+        if width > 0 {
+            let mark = self.out.len();
+            self.out.push_str(self.tokens[start].text);
+            self.out.push(' ');
+            self.joined(start + 1, end + 1);
+            let line = &self.out[self.out.rfind('\n').map(|i| i + 1).unwrap_or(0)..];
+            if line.chars().count() <= width && !self.out[mark..].contains('\n') {
+                trace::decided("case: inline");
+                return;
+            }
+            self.out.truncate(mark);
+        }
+
+        if self.config.case_style == CaseStyle::Default {
+            trace::decided("case: too wide to inline");
+            self.out.push_str(self.tokens[start].text);
+            self.verbatim(start + 1, end + 1);
+            return;
+        }
+
+        let line = &self.out[self.out.rfind('\n').map(|i| i + 1).unwrap_or(0)..];
+        let base = line[..line.len() - line.trim_start().len()].to_string();
+        let mut branches = self.branches(start, end).into_iter().peekable();
+        trace::decided("case: aligned");
+        self.out.push_str(self.tokens[start].text);
+        // the operand of a simple CASE stays on the CASE line
+        let operand_end = branches.peek().map(|&(s, _)| s).unwrap_or(end);
+        if self.significant(start + 1, operand_end) {
+            self.out.push(' ');
+            self.joined(start + 1, operand_end);
+        }
+        for (branch_start, branch_end) in branches {
+            self.out.push('\n');
+            self.out.push_str(&base);
+            self.out.push_str(&self.indent);
+            self.joined(branch_start, branch_end);
+        }
+        self.out.push('\n');
+        self.out.push_str(&base);
+        self.out.push_str(self.tokens[end].text);
+    }
+
+    /// The `WHEN` and `ELSE` branches of the CASE spanning
+    /// `tokens[start..=end]`, with surrounding whitespace excluded.
+    fn branches(&self, start: usize, end: usize) -> Vec<(usize, usize)> {
+        let mut branches: Vec<(usize, usize)> = Vec::new();
+        let mut depth = 0usize;
+        let mut i = start + 1;
+        while i < end {
+            let token = &self.tokens[i];
+            match token.kind {
+                TokenKind::OpenParen => depth += 1,
+                TokenKind::CloseParen => depth = depth.saturating_sub(1),
+                _ if depth > 0 => {}
+                _ if token.is_word("when") || token.is_word("else") => {
+                    if let Some(last) = branches.last_mut() {
+                        last.1 = self.trim_end(last.0, i);
+                    }
+                    branches.push((i, end));
+                }
+                _ => {}
+            }
+            if let Some(j) = self.case_end(i) {
+                i = j;
+            }
+            i += 1;
+        }
+        if let Some(last) = branches.last_mut() {
+            last.1 = self.trim_end(last.0, end);
+        }
+        branches
+    }
+
+    /// The index of the `END` closing the CASE that starts at `tokens[i]`.
+    fn case_end(&self, i: usize) -> Option<usize> {
+        if !self.tokens[i].is_word("case") || self.previous_is_end(i) {
+            return None;
+        }
+        let mut depth = 0usize;
+        for (j, token) in self.tokens.iter().enumerate().skip(i) {
+            if token.is_word("case") && !self.previous_is_end(j) {
+                depth += 1;
+            } else if token.is_word("end") {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(j);
+                }
+            }
+        }
+        None
+    }
+
+    fn previous_is_end(&self, i: usize) -> bool {
+        self.tokens[..i]
+            .iter()
+            .rev()
+            .find(|t| !t.is_trivia())
+            .is_some_and(|t| t.is_word("end"))
+    }
+
+    fn is_statement(&self, end: usize) -> bool {
+        self.tokens[end + 1..]
+            .iter()
+            .find(|t| !t.is_trivia())
+            .is_some_and(|t| t.is_word("case"))
+    }
+
+    fn significant(&self, start: usize, end: usize) -> bool {
+        self.tokens[start..end].iter().any(|t| !t.is_trivia())
+    }
+
+    fn trim_end(&self, start: usize, mut end: usize) -> usize {
+        while end > start && self.tokens[end - 1].kind == TokenKind::Whitespace {
+            end -= 1;
+        }
+        end
+    }
+}
@@ -0,0 +1,212 @@
+//! Clause keywords sqlformat doesn't know.
+//!
+//! Each is formatted as a keyword sqlformat knows that sits in the same place
+//! in a query, so that it starts a clause of its own, and swapped back
+//! afterwards: Snowflake, BigQuery and Spark's `QUALIFY` as `HAVING`, Spark's
+//! `DISTRIBUTE BY`, `SORT BY` and `CLUSTER BY` as `ORDER BY`, its `LATERAL
+//! VIEW` as `CROSS JOIN` and MySQL's `ON DUPLICATE KEY UPDATE` as `SET`. The `VALUES()` function of the latter's
+//! assignments, which sqlformat would take for the `VALUES` clause, is
+//! formatted as a name it doesn't know, and cased like the clause it's in.
+
+use std::borrow::Cow;
+
+use crate::Dialect;
+use crate::lexer::Token;
+use crate::lexer::TokenKind;
+use crate::lexer::tokenize;
+
+/// The keywords formatted in place of the clauses.
+const STAND_INS: &[&[&str]] = &[
+    &["having"],
+    &["order", "by"],
+    &["cross", "join"],
+    &["set"],
+    &["upsert_values"],
+];
+
+/// Where a clause is recognized.
+#[derive(PartialEq)]
+enum Scope {
+    Anywhere,
+    /// The dialects with `QUALIFY`, where it's no name of a column.
+    Qualify,
+    Spark,
+    /// After `ON DUPLICATE KEY UPDATE`, up to the end of the statement.
+    DuplicateKeyUpdate,
+}
+
+struct Clause {
+    words: &'static [&'static str],
+    /// The index of its stand-in in `STAND_INS`.
+    stand_in: usize,
+    scope: Scope,
+}
+
+/// Longer clauses come before the ones they start with.
+const CLAUSES: &[Clause] = &[
+    Clause {
+        words: &["qualify"],
+        stand_in: 0,
+        scope: Scope::Qualify,
+    },
+    Clause {
+        words: &["distribute", "by"],
+        stand_in: 1,
+        scope: Scope::Spark,
+    },
+    Clause {
+        words: &["sort", "by"],
+        stand_in: 1,
+        scope: Scope::Spark,
+    },
+    Clause {
+        words: &["cluster", "by"],
+        stand_in: 1,
+        scope: Scope::Spark,
+    },
+    Clause {
+        words: &["lateral", "view", "outer"],
+        stand_in: 2,
+        scope: Scope::Spark,
+    },
+    Clause {
+        words: &["lateral", "view"],
+        stand_in: 2,
+        scope: Scope::Spark,
+    },
+    Clause {
+        words: &["on", "duplicate", "key", "update"],
+        stand_in: 3,
+        scope: Scope::Anywhere,
+    },
+    Clause {
+        words: &["values"],
+        stand_in: 4,
+        scope: Scope::DuplicateKeyUpdate,
+    },
+];
+
+/// For each stand-in, the clause each of its occurrences replaced, if any.
+pub(crate) struct Hidden(Vec<Vec<Option<usize>>>);
+
+pub(crate) fn hide(text: &str, dialect: Dialect) -> (Cow<'_, str>, Hidden) {
+    let tokens = tokenize(text, dialect);
+    let mut hidden = Hidden(vec![Vec::new(); STAND_INS.len()]);
+    let mut result = String::with_capacity(text.len());
+    let mut any = false;
+    let mut in_duplicate_key_update = false;
+    let mut i = 0;
+    while i < tokens.len() {
+        if tokens[i].kind == TokenKind::Semicolon {
+            in_duplicate_key_update = false;
+        }
+        let clause = CLAUSES.iter().enumerate().find_map(|(index, clause)| {
+            let enabled = match clause.scope {
+                Scope::Anywhere => true,
+                Scope::Qualify => matches!(
+                    dialect,
+                    Dialect::Snowflake | Dialect::BigQuery | Dialect::Spark
+                ),
+                Scope::Spark => dialect == Dialect::Spark,
+                Scope::DuplicateKeyUpdate => in_duplicate_key_update,
+            };
+            enabled
+                .then(|| matches_words(&tokens, i, clause.words))?
+                .map(|end| (index, end))
+        });
+        if let Some((index, end)) = clause {
+            let clause = &CLAUSES[index];
+            hidden.0[clause.stand_in].push(Some(index));
+            result.push_str(&STAND_INS[clause.stand_in].join(" ").to_uppercase());
+            in_duplicate_key_update |= clause.stand_in == 3;
+            any = true;
+            i = end;
+            continue;
+        }
+        let stand_in = STAND_INS
+            .iter()
+            .enumerate()
+            .find_map(|(index, words)| Some((index, matches_words(&tokens, i, words)?)));
+        let end = match stand_in {
+            Some((index, end)) => {
+                hidden.0[index].push(None);
+                end
+            }
+            None => i + 1,
+        };
+        for token in &tokens[i..end] {
+            result.push_str(token.text);
+        }
+        i = end;
+    }
+    if !any {
+        return (Cow::Borrowed(text), hidden);
+    }
+    (Cow::Owned(result), hidden)
+}
+
+pub(crate) fn restore(formatted: String, hidden: &Hidden, dialect: Dialect) -> String {
+    if hidden.0.iter().flatten().all(Option::is_none) {
+        return formatted;
+    }
+    let tokens = tokenize(&formatted, dialect);
+    let mut occurrences: Vec<_> = hidden.0.iter().map(|o| o.iter()).collect();
+    let mut result = String::with_capacity(formatted.len());
+    // whether sqlformat uppercased the last clause, for the functions in it
+    let mut uppercase = false;
+    let mut i = 0;
+    while i < tokens.len() {
+        let stand_in = STAND_INS
+            .iter()
+            .enumerate()
+            .find_map(|(index, words)| Some((index, matches_words(&tokens, i, words)?)));
+        let Some((index, end)) = stand_in else {
+            result.push_str(tokens[i].text);
+            i += 1;
+            continue;
+        };
+        match occurrences[index].next() {
+            Some(Some(clause)) => {
+                let clause = &CLAUSES[*clause];
+                let words = clause.words.join(" ");
+                // in the case sqlformat gave the keyword
+                if clause.scope != Scope::DuplicateKeyUpdate {
+                    uppercase = tokens[i].text.chars().any(|c| c.is_ascii_uppercase());
+                }
+                if uppercase {
+                    result.push_str(&words.to_uppercase());
+                } else {
+                    result.push_str(&words);
+                }
+            }
+            _ => {
+                for token in &tokens[i..end] {
+                    result.push_str(token.text);
+                }
+            }
+        }
+        i = end;
+    }
+    result
+}
+
+/// The end of `words` separated by whitespace starting at `tokens[start]`.
+fn matches_words(tokens: &[Token], start: usize, words: &[&str]) -> Option<usize> {
+    let mut i = start;
+    for (n, word) in words.iter().enumerate() {
+        if n > 0 {
+            if !tokens
+                .get(i)
+                .is_some_and(|t| t.kind == TokenKind::Whitespace)
+            {
+                return None;
+            }
+            i += 1;
+        }
+        if !tokens.get(i).is_some_and(|t| t.is_word(word)) {
+            return None;
+        }
+        i += 1;
+    }
+    Some(i)
+}
@@ -0,0 +1,21 @@
+//! The rows of Postgres `COPY ... FROM stdin` statements, as pg_dump writes
+//! them.
+//!
+//! The rows are tab separated values that run up to a `\.` line, and aren't
+//! SQL at all. The lexer reads them as one token so that no pass looks inside
+//! them, and they are cut out of the text sqlformat sees and written back as
+//! they are.
+
+use crate::Dialect;
+use crate::lexer::TokenKind;
+use crate::lexer::tokenize;
+
+/// Splits `text` at the first rows in it, into the text up to the end of the
+/// `COPY` statement, the rows and the text after them.
+pub(crate) fn split(text: &str, dialect: Dialect) -> Option<(&str, &str, &str)> {
+    let data = tokenize(text, dialect)
+        .into_iter()
+        .find(|t| t.kind == TokenKind::CopyData)?;
+    let end = data.start + data.text.len();
+    Some((&text[..data.start], data.text, &text[end..]))
+}
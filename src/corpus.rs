@@ -0,0 +1,151 @@
+//! A corpus of SQL files and the output they're expected to format to, kept
+//! in a folder per dialect:
+//!
+//! ```text
+//! corpus/
+//!   postgresql/
+//!     upsert.sql
+//!     upsert.expected.sql
+//!   mysql/
+//!     ...
+//! ```
+//!
+//! Each input is formatted with the configuration given to the runner and the
+//! dialect of its folder, and has to format to its expected output, which in
+//! turn has to format to itself.
+
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::Context;
+use anyhow::Result;
+use anyhow::bail;
+
+use crate::Configuration;
+use crate::Dialect;
+use crate::format_text;
+
+/// The outcome of running a corpus.
+#[derive(Debug, Default)]
+pub struct CorpusReport {
+    /// The number of inputs that formatted to their expected output.
+    pub passed: usize,
+    pub failures: Vec<CorpusFailure>,
+}
+
+/// An input that didn't format to its expected output.
+#[derive(Debug)]
+pub struct CorpusFailure {
+    /// The path of the input.
+    pub path: PathBuf,
+    /// The expected output, or `None` if the input doesn't have one yet.
+    pub expected: Option<String>,
+    /// The formatted input, or the error formatting it failed with. When the
+    /// input formatted to its expected output but that didn't format to
+    /// itself, the expected output formatted again.
+    pub actual: Result<String, String>,
+}
+
+impl std::fmt::Display for CorpusFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", self.path.display())?;
+        match &self.expected {
+            Some(expected) => writeln!(f, "  expected:\n{expected}")?,
+            None => writeln!(f, "  expected: (missing)")?,
+        }
+        match &self.actual {
+            Ok(actual) => write!(f, "  actual:\n{actual}"),
+            Err(error) => write!(f, "  error: {error}"),
+        }
+    }
+}
+
+/// Formats the inputs of the corpus at `path` and compares them to their
+/// expected output.
+pub fn run_corpus(path: &Path, config: &Configuration) -> Result<CorpusReport> {
+    let mut report = CorpusReport::default();
+    for (dialect, input) in inputs(path)? {
+        let config = Configuration {
+            dialect,
+            ..config.clone()
+        };
+        let text = std::fs::read_to_string(&input)
+            .with_context(|| format!("Reading {}", input.display()))?;
+        let expected = std::fs::read_to_string(expected_path(&input)).ok();
+        let actual = format(&text, &config).and_then(|formatted| {
+            // the expected output has to be formatted already
+            if expected.as_ref() == Some(&formatted) {
+                format(&formatted, &config)
+            } else {
+                Ok(formatted)
+            }
+        });
+        if actual.as_ref().ok() == expected.as_ref() {
+            report.passed += 1;
+        } else {
+            report.failures.push(CorpusFailure {
+                path: input,
+                expected,
+                actual,
+            });
+        }
+    }
+    Ok(report)
+}
+
+/// Runs the corpus at `path` and writes the output of each failing input as
+/// its expected output, returning the failures from before they were fixed.
+/// Inputs that fail to format are left as they are.
+pub fn fix_corpus(path: &Path, config: &Configuration) -> Result<CorpusReport> {
+    let report = run_corpus(path, config)?;
+    for failure in &report.failures {
+        if let Ok(actual) = &failure.actual {
+            let expected = expected_path(&failure.path);
+            std::fs::write(&expected, actual)
+                .with_context(|| format!("Writing {}", expected.display()))?;
+        }
+    }
+    Ok(report)
+}
+
+fn format(text: &str, config: &Configuration) -> Result<String, String> {
+    match format_text(text, config) {
+        Ok(formatted) => Ok(formatted.unwrap_or_else(|| text.to_string())),
+        Err(error) => Err(error.to_string()),
+    }
+}
+
+/// The inputs of the corpus with the dialect of their folder, in the order
+/// of their paths.
+fn inputs(path: &Path) -> Result<Vec<(Dialect, PathBuf)>> {
+    let mut inputs = Vec::new();
+    for folder in sorted_entries(path)? {
+        if !folder.is_dir() {
+            continue;
+        }
+        let name = folder.file_name().unwrap_or_default().to_string_lossy();
+        let Ok(dialect) = name.parse::<Dialect>() else {
+            bail!("{} isn't named after a dialect", folder.display());
+        };
+        for file in sorted_entries(&folder)? {
+            let name = file.file_name().unwrap_or_default().to_string_lossy();
+            if name.ends_with(".sql") && !name.ends_with(".expected.sql") {
+                inputs.push((dialect, file));
+            }
+        }
+    }
+    Ok(inputs)
+}
+
+fn sorted_entries(path: &Path) -> Result<Vec<PathBuf>> {
+    let mut entries = std::fs::read_dir(path)
+        .with_context(|| format!("Reading {}", path.display()))?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<Result<Vec<_>>>()?;
+    entries.sort();
+    Ok(entries)
+}
+
+fn expected_path(input: &Path) -> PathBuf {
+    input.with_extension("expected.sql")
+}
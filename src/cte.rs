@@ -0,0 +1,169 @@
+//! Common table expressions (`WITH name AS (...)`).
+//!
+//! sqlformat lays out each CTE body relative to whatever precedes it, so the
+//! list of CTEs is laid out here instead: every body is formatted on its own
+//! and each CTE gets its own block, optionally separated by blank lines.
+
+use crate::Configuration;
+use crate::Dialect;
+use crate::keywords::is_keyword;
+use crate::lexer::Token;
+use crate::lexer::TokenKind;
+use crate::lexer::matching_paren;
+use crate::lexer::tokenize;
+use crate::protect::Protected;
+
+struct Cte<'a> {
+    /// The name, column list and `AS [NOT] MATERIALIZED` before the body.
+    header: &'a [Token<'a>],
+    body: &'a str,
+}
+
+/// Replaces the CTE list following every `WITH` in `text` with a placeholder.
+pub(crate) fn protect(
+    text: &str,
+    config: &Configuration,
+    dialect: Dialect,
+    protected: &mut Protected,
+) -> String {
+    let tokens = tokenize(text, dialect);
+    let mut result = String::with_capacity(text.len());
+    let mut pos = 0;
+    let mut i = 0;
+    while i < tokens.len() {
+        if !tokens[i].is_word("with") {
+            i += 1;
+            continue;
+        }
+        let mut start = next_significant(&tokens, i + 1);
+        if tokens.get(start).is_some_and(|t| t.is_word("recursive")) {
+            start = next_significant(&tokens, start + 1);
+        }
+        let Some((ctes, end)) = parse_list(text, &tokens, start) else {
+            i += 1;
+            continue;
+        };
+        result.push_str(&text[pos..tokens[start].start]);
+        result.push_str(&protected.insert_block(render(&ctes, config, dialect), dialect));
+        pos = tokens[end].start + tokens[end].text.len();
+        i = end + 1;
+    }
+    result.push_str(&text[pos..]);
+    result
+}
+
+/// Parses the CTEs starting at `tokens[start]`, returning them along with the
+/// index of the closing parenthesis of the last one.
+fn parse_list<'a>(
+    text: &'a str,
+    tokens: &'a [Token<'a>],
+    start: usize,
+) -> Option<(Vec<Cte<'a>>, usize)> {
+    let mut ctes = Vec::new();
+    let mut i = start;
+    loop {
+        let name = tokens.get(i)?;
+        match name.kind {
+            TokenKind::Word if !is_keyword(name.text) => {}
+            TokenKind::QuotedIdentifier => {}
+            _ => return None,
+        }
+        let mut j = next_significant(tokens, i + 1);
+        if tokens.get(j)?.kind == TokenKind::OpenParen {
+            j = next_significant(tokens, matching_paren(tokens, j)? + 1);
+        }
+        if !tokens.get(j)?.is_word("as") {
+            return None;
+        }
+        j = next_significant(tokens, j + 1);
+        if tokens.get(j)?.is_word("not") {
+            j = next_significant(tokens, j + 1);
+        }
+        if tokens.get(j)?.is_word("materialized") {
+            j = next_significant(tokens, j + 1);
+        }
+        let open = tokens.get(j).filter(|t| t.kind == TokenKind::OpenParen)?;
+        let close = matching_paren(tokens, j)?;
+        let header = &tokens[i..j];
+        // the header is joined onto one line
+        if header.iter().any(|t| t.kind == TokenKind::LineComment) {
+            return None;
+        }
+        ctes.push(Cte {
+            header,
+            body: &text[open.start + 1..tokens[close].start],
+        });
+
+        let next = next_significant(tokens, close + 1);
+        if tokens.get(next).is_some_and(|t| t.kind == TokenKind::Comma) {
+            i = next_significant(tokens, next + 1);
+        } else {
+            return Some((ctes, close));
+        }
+    }
+}
+
+fn render(ctes: &[Cte], config: &Configuration, dialect: Dialect) -> String {
+    let indent = if config.indent_cte_body {
+        config.indent()
+    } else {
+        String::new()
+    };
+    let separator = format!(",\n{}", "\n".repeat(config.lines_between_ctes.into()));
+    let blocks: Vec<_> = ctes
+        .iter()
+        .map(|cte| {
+            let body = crate::format_sql(cte.body, config);
+            let mut block = header(cte.header, config);
+            block.push_str(" (\n");
+            let mut protected = Protected::default();
+            let placeholder = protected.insert_block(body, dialect);
+            block.push_str(&protected.restore(&format!("{indent}{placeholder}")));
+            block.push_str("\n)");
+            block
+        })
+        .collect();
+    blocks.join(&separator)
+}
+
+/// Writes the tokens before a CTE body on one line, cased like sqlformat
+/// would case them.
+fn header(tokens: &[Token], config: &Configuration) -> String {
+    let mut header = String::new();
+    for (i, token) in tokens.iter().enumerate() {
+        match token.kind {
+            // no space inside the column list's parentheses or around commas
+            TokenKind::Whitespace => {
+                let after = i.checked_sub(1).map(|i| tokens[i].kind);
+                let before = tokens.get(i + 1).map(|t| t.kind);
+                if !matches!(after, Some(TokenKind::OpenParen | TokenKind::Comma))
+                    && !matches!(
+                        before,
+                        Some(TokenKind::OpenParen | TokenKind::CloseParen | TokenKind::Comma)
+                    )
+                {
+                    header.push(' ');
+                }
+            }
+            TokenKind::Word
+                if token.is_word("as") || token.is_word("not") || token.is_word("materialized") =>
+            {
+                if config.uppercase {
+                    header.push_str(&token.text.to_uppercase());
+                } else {
+                    header.push_str(&token.text.to_lowercase());
+                }
+            }
+            TokenKind::Comma => header.push_str(", "),
+            _ => header.push_str(token.text),
+        }
+    }
+    header.trim_end().to_string()
+}
+
+fn next_significant(tokens: &[Token], from: usize) -> usize {
+    tokens[from.min(tokens.len())..]
+        .iter()
+        .position(|t| !t.is_trivia())
+        .map_or(tokens.len(), |i| from + i)
+}
@@ -0,0 +1,157 @@
+//! Handling for the MySQL client's `DELIMITER` directive.
+//!
+//! The directive is not SQL, so sqlformat mangles it, and it splits stored
+//! routine bodies at every inner `;`. Instead the input is split into the
+//! directives themselves and runs of statements terminated by whichever
+//! delimiter is active at that point.
+//!
+//! Only MySQL and generic SQL have the directive, and only a line starting
+//! with it where a statement could start is one, so a column or option named
+//! `delimiter` is left alone.
+
+use crate::Dialect;
+use crate::lexer::Token;
+use crate::lexer::TokenKind;
+use crate::lexer::tokenize;
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Chunk<'a> {
+    /// A `DELIMITER` directive line, trimmed.
+    Directive(&'a str),
+    /// Statements formatted as a whole, because `;` is the active delimiter.
+    Statements(&'a str),
+    /// A single statement terminated by a custom delimiter. `delimiter` is
+    /// `None` for a trailing statement without one.
+    Statement {
+        text: &'a str,
+        delimiter: Option<&'a str>,
+    },
+}
+
+pub(crate) fn has_directives(text: &str, dialect: Dialect) -> bool {
+    split(text, dialect).is_some()
+}
+
+/// Splits `text` at its `DELIMITER` directives. Returns `None` if there are
+/// none, in which case the text should be formatted as usual.
+pub(crate) fn split(text: &str, dialect: Dialect) -> Option<Vec<Chunk<'_>>> {
+    if !matches!(dialect, Dialect::MySql | Dialect::Generic) {
+        return None;
+    }
+    let tokens = tokenize(text, Dialect::MySql);
+    let literals: Vec<_> = tokens
+        .iter()
+        .filter(|t| {
+            matches!(
+                t.kind,
+                TokenKind::String
+                    | TokenKind::QuotedIdentifier
+                    | TokenKind::LineComment
+                    | TokenKind::BlockComment
+            )
+        })
+        .map(|t| t.start..t.start + t.text.len())
+        .collect();
+    let in_literal = |offset: usize| literals.iter().any(|r| r.start < offset && offset < r.end);
+
+    let directives = find_directives(text, &tokens, &in_literal);
+    if directives.is_empty() {
+        return None;
+    }
+
+    let mut chunks = Vec::new();
+    let mut delimiter = ";";
+    let mut pos = 0;
+    for (range, next_delimiter) in directives {
+        push_statements(&mut chunks, text, pos..range.start, delimiter, &in_literal);
+        chunks.push(Chunk::Directive(text[range.clone()].trim()));
+        delimiter = next_delimiter;
+        pos = range.end;
+    }
+    push_statements(&mut chunks, text, pos..text.len(), delimiter, &in_literal);
+    Some(chunks)
+}
+
+fn find_directives<'a>(
+    text: &'a str,
+    tokens: &[Token],
+    in_literal: &impl Fn(usize) -> bool,
+) -> Vec<(std::ops::Range<usize>, &'a str)> {
+    let mut directives = Vec::new();
+    let mut delimiter = ";";
+    // where the statements after the last directive start
+    let mut statements_start = 0;
+    let mut line_start = 0;
+    for line in text.split_inclusive('\n') {
+        let start = line_start;
+        line_start += line.len();
+        if in_literal(start) || line.starts_with(char::is_whitespace) {
+            continue;
+        }
+        let mut words = line.split_whitespace();
+        let is_directive = words
+            .next()
+            .is_some_and(|w| w.eq_ignore_ascii_case("delimiter"));
+        let Some(next_delimiter) = words.next().filter(|_| is_directive) else {
+            continue;
+        };
+        let before = || {
+            tokens
+                .iter()
+                .take_while(|t| t.start < start)
+                .filter(|t| !t.is_trivia())
+        };
+        let depth = before().fold(0usize, |depth, t| match t.kind {
+            TokenKind::OpenParen => depth + 1,
+            TokenKind::CloseParen => depth.saturating_sub(1),
+            _ => depth,
+        });
+        // the statement before it, if any, has to be terminated
+        let at_boundary = before()
+            .filter(|t| t.start >= statements_start)
+            .last()
+            .is_none_or(|t| text[..t.start + t.text.len()].ends_with(delimiter));
+        if depth == 0 && at_boundary {
+            directives.push((start..line_start, next_delimiter));
+            delimiter = next_delimiter;
+            statements_start = line_start;
+        }
+    }
+    directives
+}
+
+fn push_statements<'a>(
+    chunks: &mut Vec<Chunk<'a>>,
+    text: &'a str,
+    range: std::ops::Range<usize>,
+    delimiter: &'a str,
+    in_literal: &impl Fn(usize) -> bool,
+) {
+    if delimiter == ";" {
+        if !text[range.clone()].trim().is_empty() {
+            chunks.push(Chunk::Statements(&text[range]));
+        }
+        return;
+    }
+
+    let mut start = range.start;
+    let mut search_from = range.start;
+    while let Some(index) = text[search_from..range.end].find(delimiter) {
+        let at = search_from + index;
+        search_from = at + delimiter.len();
+        if in_literal(at) || in_literal(at + 1) {
+            continue;
+        }
+        chunks.push(Chunk::Statement {
+            text: &text[start..at],
+            delimiter: Some(delimiter),
+        });
+        start = search_from;
+    }
+    if !text[start..range.end].trim().is_empty() {
+        chunks.push(Chunk::Statement {
+            text: &text[start..range.end],
+            delimiter: None,
+        });
+    }
+}
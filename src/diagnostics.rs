@@ -0,0 +1,199 @@
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::AlteredLiteralError;
+use crate::ChangedTokensError;
+use crate::Dialect;
+use crate::LintError;
+use crate::lexer::Token;
+use crate::lexer::TokenKind;
+use crate::lexer::tokenize;
+
+/// An error describing SQL that could not be parsed in strict mode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub message: String,
+    /// 1-based line number.
+    pub line: usize,
+    /// 1-based column number, counted in characters.
+    pub column: usize,
+    pub token: String,
+}
+
+impl ParseError {
+    fn at(text: &str, token: &Token, message: &str) -> Self {
+        let (line, column) = line_column(text, token.start);
+        ParseError {
+            message: message.to_string(),
+            line,
+            column,
+            token: excerpt(token.text, 20),
+        }
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} at line {}, column {}: `{}`",
+            self.message, self.line, self.column, self.token
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// An error formatting a file, with the file and where in it formatting
+/// failed, for runs over many files.
+#[derive(Debug)]
+pub struct FileError {
+    pub path: PathBuf,
+    /// Where the error is, if it says.
+    pub location: Option<ErrorLocation>,
+    pub error: anyhow::Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorLocation {
+    /// Byte offset in the text of the file, after decoding it.
+    pub offset: usize,
+    /// 1-based line number.
+    pub line: usize,
+    /// 1-based column number, counted in characters.
+    pub column: usize,
+    /// The start of the statement the error is in.
+    pub statement: String,
+}
+
+impl FileError {
+    /// Wraps `error` from formatting `text`, the contents of the file at
+    /// `path`, in `dialect`. Hosts can use it for the diagnostic of
+    /// `format_text_with_validation` too.
+    pub fn new(path: &Path, text: Option<&str>, dialect: Dialect, error: anyhow::Error) -> Self {
+        let location = text.and_then(|text| location(text, dialect, &error));
+        FileError {
+            path: path.to_path_buf(),
+            location,
+            error,
+        }
+    }
+}
+
+impl std::fmt::Display for FileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let path = self.path.display();
+        match &self.location {
+            Some(location) => write!(
+                f,
+                "{path}:{}:{}: {}\n  in statement: {}",
+                location.line, location.column, self.error, location.statement
+            ),
+            None => write!(f, "{path}: {}", self.error),
+        }
+    }
+}
+
+impl std::error::Error for FileError {}
+
+/// Where in `text` the error is, for the errors that say.
+fn location(text: &str, dialect: Dialect, error: &anyhow::Error) -> Option<ErrorLocation> {
+    let (line, column) = if let Some(error) = error.downcast_ref::<ParseError>() {
+        (error.line, error.column)
+    } else if let Some(error) = error.downcast_ref::<AlteredLiteralError>() {
+        (error.line, error.column)
+    } else if let Some(error) = error.downcast_ref::<ChangedTokensError>() {
+        (error.line, error.column)
+    } else if let Some(error) = error.downcast_ref::<LintError>() {
+        let first = error.0.first()?;
+        (first.line, first.column)
+    } else {
+        return None;
+    };
+    let offset = offset(text, line, column);
+    let spans = crate::statements::spans(text, dialect);
+    let span = spans
+        .iter()
+        .find(|span| span.range.contains(&offset))
+        .or(spans.last())?;
+    // the first line of the statement, after the comments before it
+    let statement = &text[span.range.clone()];
+    let start = tokenize(statement, dialect)
+        .iter()
+        .find(|t| !t.is_trivia())
+        .map_or(0, |t| t.start);
+    Some(ErrorLocation {
+        offset,
+        line,
+        column,
+        statement: excerpt(statement[start..].trim_end(), 60),
+    })
+}
+
+/// The byte offset of the 1-based `line` and `column` in `text`.
+fn offset(text: &str, line: usize, column: usize) -> usize {
+    let line_start = text
+        .match_indices('\n')
+        .nth(line.saturating_sub(2))
+        .filter(|_| line > 1)
+        .map_or(0, |(i, _)| i + 1);
+    text[line_start..]
+        .char_indices()
+        .nth(column.saturating_sub(1))
+        .map_or(text.len(), |(i, _)| line_start + i)
+}
+
+pub(crate) fn line_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    (line, before[line_start..].chars().count() + 1)
+}
+
+/// The first line of `text`, cut short at `max_chars`.
+fn excerpt(text: &str, max_chars: usize) -> String {
+    let first_line = text.lines().next().unwrap_or(text);
+    if first_line.chars().count() > max_chars || first_line.len() != text.len() {
+        let mut excerpt: String = first_line.chars().take(max_chars).collect();
+        excerpt.push_str("...");
+        excerpt
+    } else {
+        first_line.to_string()
+    }
+}
+
+/// Checks for the structural problems sqlformat silently papers over:
+/// unterminated literals and comments, and unbalanced parentheses.
+pub(crate) fn check_syntax(text: &str, dialect: Dialect) -> Result<(), ParseError> {
+    let mut open_parens = Vec::new();
+    for token in tokenize(text, dialect) {
+        match token.kind {
+            TokenKind::String if !token.terminated => {
+                return Err(ParseError::at(text, &token, "Unterminated string literal"));
+            }
+            TokenKind::QuotedIdentifier if !token.terminated => {
+                return Err(ParseError::at(
+                    text,
+                    &token,
+                    "Unterminated quoted identifier",
+                ));
+            }
+            TokenKind::BlockComment if !token.terminated => {
+                return Err(ParseError::at(text, &token, "Unterminated block comment"));
+            }
+            TokenKind::OpenParen => open_parens.push(token),
+            TokenKind::CloseParen if open_parens.pop().is_none() => {
+                return Err(ParseError::at(
+                    text,
+                    &token,
+                    "Unexpected closing parenthesis",
+                ));
+            }
+            _ => {}
+        }
+    }
+    match open_parens.pop() {
+        Some(token) => Err(ParseError::at(text, &token, "Unclosed parenthesis")),
+        None => Ok(()),
+    }
+}
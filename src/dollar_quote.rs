@@ -0,0 +1,96 @@
+//! PostgreSQL dollar-quoted strings (`$$ ... $$`, `$tag$ ... $tag$`).
+//!
+//! sqlformat tokenizes their contents as SQL, which re-cases and re-indents
+//! function bodies. They are kept verbatim, or with `formatFunctionBodies`
+//! the bodies of `sql` routines are formatted on their own, and those of
+//! `plpgsql` routines and DO blocks by `plpgsql`.
+
+use crate::Configuration;
+use crate::Dialect;
+use crate::lexer::Token;
+use crate::lexer::TokenKind;
+use crate::lexer::tokenize;
+use crate::plpgsql;
+use crate::protect::Protected;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Language {
+    Sql,
+    Plpgsql,
+}
+
+/// Replaces every dollar-quoted string in `text` with a placeholder.
+pub(crate) fn protect(
+    text: &str,
+    config: &Configuration,
+    dialect: Dialect,
+    protected: &mut Protected,
+) -> String {
+    let tokens = tokenize(text, dialect);
+    let mut result = String::with_capacity(text.len());
+    let mut pos = 0;
+    for statement in tokens.split(|t| t.kind == TokenKind::Semicolon) {
+        let language = if config.format_function_bodies {
+            language(statement)
+        } else {
+            None
+        };
+        for token in statement {
+            if token.kind != TokenKind::String || !token.text.starts_with('$') {
+                continue;
+            }
+            result.push_str(&text[pos..token.start]);
+            let formatted = split_tag(token).and_then(|(tag, body)| {
+                let body = match language? {
+                    Language::Sql => crate::format_sql(body, config),
+                    Language::Plpgsql => plpgsql::format(body, config, dialect)?,
+                };
+                Some(format!("{tag}\n{body}\n{tag}"))
+            });
+            let region = formatted.unwrap_or_else(|| token.text.to_string());
+            result.push_str(&protected.insert(region));
+            pos = token.start + token.text.len();
+        }
+    }
+    result.push_str(&text[pos..]);
+    result
+}
+
+fn split_tag<'a>(token: &Token<'a>) -> Option<(&'a str, &'a str)> {
+    let tag_len = token.text[1..].find('$')? + 2;
+    if !token.terminated || token.text.len() < tag_len * 2 {
+        return None;
+    }
+    let body = &token.text[tag_len..token.text.len() - tag_len];
+    Some((&token.text[..tag_len], body))
+}
+
+/// The language of the routine or DO block `statement`, if it's one whose
+/// body can be formatted.
+fn language(statement: &[Token]) -> Option<Language> {
+    let mut words = statement.iter().filter(|t| !t.is_trivia());
+    // DO blocks default to plpgsql, functions must name their language
+    let mut language = match words.next() {
+        Some(t) if t.is_word("do") => Some("plpgsql"),
+        Some(t)
+            if t.is_word("create")
+                && words
+                    .clone()
+                    .take_while(|t| t.kind != TokenKind::OpenParen)
+                    .any(|t| t.is_word("function") || t.is_word("procedure")) =>
+        {
+            None
+        }
+        _ => return None,
+    };
+    while let Some(token) = words.next() {
+        if token.is_word("language") {
+            language = words.next().map(|t| t.text.trim_matches('\''));
+        }
+    }
+    match language?.to_ascii_lowercase().as_str() {
+        "sql" => Some(Language::Sql),
+        "plpgsql" => Some(Language::Plpgsql),
+        _ => None,
+    }
+}
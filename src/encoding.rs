@@ -0,0 +1,114 @@
+//! Decoding file bytes into text and back, for files that aren't plain UTF-8.
+
+use anyhow::Result;
+use anyhow::bail;
+
+use crate::Encoding;
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+/// Text decoded from a file along with how to write it back.
+pub(crate) struct Decoded {
+    pub text: String,
+    pub encoding: Encoding,
+    pub bom: bool,
+}
+
+pub(crate) fn decode(bytes: &[u8]) -> Result<Decoded> {
+    let (encoding, bom) = if bytes.starts_with(UTF8_BOM) {
+        (Encoding::Utf8, true)
+    } else if bytes.starts_with(UTF16LE_BOM) {
+        (Encoding::Utf16Le, true)
+    } else if bytes.starts_with(UTF16BE_BOM) {
+        (Encoding::Utf16Be, true)
+    } else {
+        (detect(bytes), false)
+    };
+    let content = if bom {
+        &bytes[bom_for(encoding).len()..]
+    } else {
+        bytes
+    };
+
+    let text = match encoding {
+        Encoding::Utf8 | Encoding::Auto => String::from_utf8(content.to_vec())?,
+        Encoding::Utf16Le | Encoding::Utf16Be => {
+            if !content.len().is_multiple_of(2) {
+                bail!("Invalid UTF-16: odd number of bytes");
+            }
+            let units = content.chunks_exact(2).map(|pair| match encoding {
+                Encoding::Utf16Le => u16::from_le_bytes([pair[0], pair[1]]),
+                _ => u16::from_be_bytes([pair[0], pair[1]]),
+            });
+            char::decode_utf16(units).collect::<Result<_, _>>()?
+        }
+        Encoding::Latin1 => content.iter().map(|&b| char::from(b)).collect(),
+    };
+    Ok(Decoded {
+        text,
+        encoding,
+        bom,
+    })
+}
+
+/// Guesses the encoding of text without a byte order mark. UTF-16 is
+/// recognized by the zero high bytes of ASCII characters, which are also
+/// valid UTF-8, and anything else that isn't UTF-8 is read as latin-1, which
+/// accepts every byte.
+fn detect(bytes: &[u8]) -> Encoding {
+    if bytes.len() >= 2 && bytes.len().is_multiple_of(2) {
+        let zeros_at = |parity| {
+            bytes
+                .iter()
+                .skip(parity)
+                .step_by(2)
+                .filter(|&&b| b == 0)
+                .count()
+        };
+        let pairs = bytes.len() / 2;
+        // most SQL is ASCII, so nearly every other byte is zero
+        if zeros_at(1) * 2 > pairs {
+            return Encoding::Utf16Le;
+        }
+        if zeros_at(0) * 2 > pairs {
+            return Encoding::Utf16Be;
+        }
+    }
+    if std::str::from_utf8(bytes).is_ok() {
+        Encoding::Utf8
+    } else {
+        Encoding::Latin1
+    }
+}
+
+pub(crate) fn encode(text: &str, encoding: Encoding, bom: bool) -> Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len() + 3);
+    if bom {
+        bytes.extend_from_slice(bom_for(encoding));
+    }
+    match encoding {
+        Encoding::Utf8 | Encoding::Auto => bytes.extend_from_slice(text.as_bytes()),
+        Encoding::Utf16Le => bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes)),
+        Encoding::Utf16Be => bytes.extend(text.encode_utf16().flat_map(u16::to_be_bytes)),
+        Encoding::Latin1 => {
+            for c in text.chars() {
+                match u8::try_from(c) {
+                    Ok(b) => bytes.push(b),
+                    Err(_) => bail!("Cannot encode {c:?} as latin-1"),
+                }
+            }
+        }
+    }
+    Ok(bytes)
+}
+
+fn bom_for(encoding: Encoding) -> &'static [u8] {
+    match encoding {
+        Encoding::Utf8 | Encoding::Auto => UTF8_BOM,
+        Encoding::Utf16Le => UTF16LE_BOM,
+        Encoding::Utf16Be => UTF16BE_BOM,
+        Encoding::Latin1 => &[],
+    }
+}
@@ -0,0 +1,42 @@
+//! Matching file paths against the patterns of `overrides`.
+//!
+//! `*` matches within a path component, `?` matches one character of one and
+//! `**` matches any number of whole components. Patterns without a `/` match
+//! the file name and other patterns match the end of the path, so that they
+//! work no matter where the configuration file is.
+
+use std::path::Path;
+
+pub(crate) fn matches(pattern: &str, path: &Path) -> bool {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let pattern: Vec<_> = pattern.trim_start_matches("./").chars().collect();
+    let path: Vec<_> = path.chars().collect();
+    if let Some(rooted) = pattern.strip_prefix(&['/']) {
+        return path.first() == Some(&'/') && matches_at(rooted, &path[1..]);
+    }
+    if !pattern.contains(&'/') {
+        let name = path.iter().rposition(|&c| c == '/').map_or(0, |i| i + 1);
+        return matches_at(&pattern, &path[name..]);
+    }
+    (0..path.len())
+        .filter(|&i| i == 0 || path[i - 1] == '/')
+        .any(|i| matches_at(&pattern, &path[i..]))
+}
+
+fn matches_at(pattern: &[char], path: &[char]) -> bool {
+    match pattern {
+        [] => path.is_empty(),
+        ['*', '*'] => true,
+        ['*', '*', '/', rest @ ..] => {
+            matches_at(rest, path)
+                || (0..path.len())
+                    .filter(|&i| path[i] == '/')
+                    .any(|i| matches_at(rest, &path[i + 1..]))
+        }
+        ['*', rest @ ..] => (0..=path.len())
+            .take_while(|&i| i == 0 || path[i - 1] != '/')
+            .any(|i| matches_at(rest, &path[i..])),
+        ['?', rest @ ..] => path.first().is_some_and(|&c| c != '/') && matches_at(rest, &path[1..]),
+        [c, rest @ ..] => path.first() == Some(c) && matches_at(rest, &path[1..]),
+    }
+}
@@ -0,0 +1,362 @@
+//! Layout of `GRANT`, `REVOKE` and `ALTER DEFAULT PRIVILEGES` statements.
+//!
+//! sqlformat reads the privileges of a grant as the start of queries, so it
+//! breaks lines after `SELECT` and `INSERT` and around their commas. These
+//! statements are laid out here instead, with the privileges and grantees one
+//! per line and the object on the line after `ON`:
+//!
+//! ```sql
+//! grant
+//!   select,
+//!   insert
+//! on
+//!   all tables in schema public
+//! to
+//!   app_user,
+//!   report_user
+//! with grant option
+//! ```
+//!
+//! Grants that fit in `grantInlineWidth` are kept on one line. Statements
+//! with comments are left to sqlformat.
+
+use crate::Configuration;
+use crate::Dialect;
+use crate::by_list;
+use crate::lexer::Token;
+use crate::lexer::TokenKind;
+use crate::lexer::tokenize;
+use crate::protect::Protected;
+
+/// Words of the `ALTER DEFAULT PRIVILEGES` before its grant.
+const HEADER_WORDS: &[&str] = &[
+    "alter",
+    "default",
+    "for",
+    "in",
+    "privileges",
+    "role",
+    "schema",
+    "user",
+];
+
+/// Words of the object after `ON` that say what kind of object it is.
+const OBJECT_WORDS: &[&str] = &[
+    "all",
+    "data",
+    "database",
+    "domain",
+    "foreign",
+    "function",
+    "functions",
+    "in",
+    "language",
+    "large",
+    "object",
+    "procedure",
+    "procedures",
+    "routine",
+    "routines",
+    "schema",
+    "sequence",
+    "sequences",
+    "server",
+    "table",
+    "tables",
+    "tablespace",
+    "type",
+    "types",
+    "wrapper",
+];
+
+/// Words of the clauses after the grantees.
+const TRAILING_WORDS: &[&str] = &[
+    "admin", "by", "cascade", "grant", "granted", "option", "restrict", "with",
+];
+
+/// Keywords that end the list of grantees.
+const GRANTEES_END: &[&str] = &["cascade", "granted", "restrict", "with"];
+
+struct Grant<'a> {
+    /// `ALTER DEFAULT PRIVILEGES` and what follows it up to the grant.
+    header: &'a [Token<'a>],
+    /// `GRANT` or `REVOKE`, with a `GRANT OPTION FOR` after the latter.
+    keyword: &'a [Token<'a>],
+    privileges: Vec<&'a [Token<'a>]>,
+    object: Option<&'a [Token<'a>]>,
+    /// `TO` or `FROM`.
+    direction: &'a Token<'a>,
+    grantees: Vec<&'a [Token<'a>]>,
+    trailing: &'a [Token<'a>],
+}
+
+/// Replaces every grant in `text` with a placeholder.
+pub(crate) fn protect(
+    text: &str,
+    config: &Configuration,
+    dialect: Dialect,
+    protected: &mut Protected,
+) -> String {
+    let tokens = tokenize(text, dialect);
+    let mut result = String::with_capacity(text.len());
+    let mut pos = 0;
+    let mut at_statement_start = true;
+    let mut depth = 0usize;
+    for (i, token) in tokens.iter().enumerate() {
+        if token.is_trivia() || token.start < pos {
+            continue;
+        }
+        match token.kind {
+            TokenKind::OpenParen => depth += 1,
+            TokenKind::CloseParen => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        let starts_grant = at_statement_start
+            && (token.is_word("grant") || token.is_word("revoke") || token.is_word("alter"));
+        at_statement_start = depth == 0 && token.kind == TokenKind::Semicolon;
+        if !starts_grant {
+            continue;
+        }
+        let end = statement_end(&tokens, i);
+        let Some(grant) = parse(&tokens[i..end]) else {
+            continue;
+        };
+        let last = &tokens[end - 1];
+        result.push_str(&text[pos..token.start]);
+        result.push_str(&protected.insert_block(render(&grant, config), dialect));
+        pos = last.start + last.text.len();
+    }
+    result.push_str(&text[pos..]);
+    result
+}
+
+/// The index just after the last significant token of the statement starting
+/// at `tokens[start]`.
+fn statement_end(tokens: &[Token], start: usize) -> usize {
+    let mut depth = 0usize;
+    let mut end = start;
+    for (i, token) in tokens.iter().enumerate().skip(start) {
+        match token.kind {
+            TokenKind::OpenParen => depth += 1,
+            TokenKind::CloseParen => depth = depth.saturating_sub(1),
+            TokenKind::Semicolon if depth == 0 => break,
+            _ => {}
+        }
+        if !token.is_trivia() {
+            end = i + 1;
+        }
+    }
+    end
+}
+
+fn parse<'a>(tokens: &'a [Token<'a>]) -> Option<Grant<'a>> {
+    if tokens
+        .iter()
+        .any(|t| matches!(t.kind, TokenKind::LineComment | TokenKind::BlockComment))
+    {
+        return None;
+    }
+    let mut start = 0;
+    if tokens[0].is_word("alter") {
+        let default = next_significant(tokens, 1);
+        let privileges = next_significant(tokens, default + 1);
+        if !tokens.get(default)?.is_word("default")
+            || !tokens.get(privileges)?.is_word("privileges")
+        {
+            return None;
+        }
+        start = tokens
+            .iter()
+            .position(|t| t.is_word("grant") || t.is_word("revoke"))?;
+    }
+    let mut i = next_significant(tokens, start + 1);
+    // `REVOKE GRANT OPTION FOR` and `REVOKE ADMIN OPTION FOR`
+    if tokens[start].is_word("revoke")
+        && let Some(option) = tokens.get(next_significant(tokens, i + 1))
+        && option.is_word("option")
+        && (tokens[i].is_word("grant") || tokens[i].is_word("admin"))
+    {
+        let option_for = next_significant(tokens, next_significant(tokens, i + 1) + 1);
+        if !tokens.get(option_for)?.is_word("for") {
+            return None;
+        }
+        i = next_significant(tokens, option_for + 1);
+    }
+    let keyword = &tokens[start..i];
+    let (privileges, mut i) = by_list::items(tokens, i, &["from", "on", "to"]);
+    let mut object = None;
+    if tokens.get(i)?.is_word("on") {
+        let object_start = i + 1;
+        i = (object_start..tokens.len())
+            .find(|&j| tokens[j].is_word("to") || tokens[j].is_word("from"))?;
+        object = Some(trim(&tokens[object_start..i]));
+    }
+    let direction = tokens.get(i)?;
+    if !(direction.is_word("to") || direction.is_word("from")) {
+        return None;
+    }
+    let (grantees, end) = by_list::items(tokens, i + 1, GRANTEES_END);
+    let grant = Grant {
+        header: trim(&tokens[..start]),
+        keyword,
+        privileges,
+        object,
+        direction,
+        grantees,
+        trailing: trim(&tokens[end..]),
+    };
+    if grant.privileges.is_empty()
+        || grant.grantees.is_empty()
+        || grant.object.is_some_and(<[Token]>::is_empty)
+        || grant
+            .trailing
+            .iter()
+            .any(|t| !t.is_trivia() && t.kind != TokenKind::Word)
+    {
+        return None;
+    }
+    Some(grant)
+}
+
+fn render(grant: &Grant, config: &Configuration) -> String {
+    let indent = config.indent();
+    let keyword = |word: &str| {
+        if config.uppercase {
+            word.to_uppercase()
+        } else {
+            word.to_lowercase()
+        }
+    };
+    let cased = |tokens: &[Token], words: &[&str]| {
+        line(tokens, |token| {
+            if words.iter().any(|w| token.is_word(w)) {
+                keyword(token.text)
+            } else {
+                token.text.to_string()
+            }
+        })
+    };
+    // privileges are keywords, but the roles granted without `ON` are names
+    let privileges: Vec<_> = grant
+        .privileges
+        .iter()
+        .map(|privilege| {
+            if grant.object.is_some() {
+                line(privilege, |token| keyword(token.text))
+            } else {
+                line(privilege, |token| token.text.to_string())
+            }
+        })
+        .collect();
+    let grantees: Vec<_> = grant
+        .grantees
+        .iter()
+        .map(|grantee| line(grantee, |token| token.text.to_string()))
+        .collect();
+    let header = cased(grant.header, HEADER_WORDS);
+    let keyword_line = line(grant.keyword, |token| keyword(token.text));
+    let object = grant.object.map(|object| cased(object, OBJECT_WORDS));
+    let direction = keyword(grant.direction.text);
+    let trailing = cased(grant.trailing, TRAILING_WORDS);
+
+    let mut inline = vec![header.as_str(), &keyword_line];
+    let privilege_list = privileges.join(", ");
+    inline.push(&privilege_list);
+    let on = keyword("on");
+    if let Some(object) = &object {
+        inline.push(&on);
+        inline.push(object);
+    }
+    let grantee_list = grantees.join(", ");
+    inline.extend([direction.as_str(), &grantee_list, &trailing]);
+    let inline = inline
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let width = config.grant_inline_width;
+    if width > 0 && inline.chars().count() <= width {
+        return inline;
+    }
+
+    let mut lines = Vec::new();
+    if !header.is_empty() {
+        lines.push(header);
+    }
+    lines.push(keyword_line);
+    lines.extend(list(&privileges, &indent));
+    if let Some(object) = object {
+        lines.push(on);
+        lines.push(format!("{indent}{object}"));
+    }
+    lines.push(direction);
+    lines.extend(list(&grantees, &indent));
+    if !trailing.is_empty() {
+        lines.push(trailing);
+    }
+    lines.join("\n")
+}
+
+/// The lines of the items of a list, one per line.
+fn list(items: &[String], indent: &str) -> Vec<String> {
+    let last = items.len() - 1;
+    items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let comma = if i < last { "," } else { "" };
+            format!("{indent}{item}{comma}")
+        })
+        .collect()
+}
+
+/// Writes `tokens` on one line with single spaces between them, writing the
+/// words outside of parentheses with `word`.
+fn line(tokens: &[Token], word: impl Fn(&Token) -> String) -> String {
+    let mut line = String::new();
+    let mut depth = 0usize;
+    for (i, token) in tokens.iter().enumerate() {
+        match token.kind {
+            TokenKind::Whitespace => {
+                let after = i.checked_sub(1).map(|i| tokens[i].kind);
+                let before = tokens.get(i + 1).map(|t| t.kind);
+                if !matches!(after, Some(TokenKind::OpenParen | TokenKind::Comma))
+                    && !matches!(before, Some(TokenKind::CloseParen | TokenKind::Comma))
+                {
+                    line.push(' ');
+                }
+            }
+            TokenKind::OpenParen => {
+                depth += 1;
+                line.push('(');
+            }
+            TokenKind::CloseParen => {
+                depth = depth.saturating_sub(1);
+                line.push(')');
+            }
+            TokenKind::Comma => line.push_str(", "),
+            TokenKind::Word if depth == 0 => line.push_str(&word(token)),
+            _ => line.push_str(token.text),
+        }
+    }
+    line.trim().to_string()
+}
+
+fn trim<'a>(tokens: &'a [Token<'a>]) -> &'a [Token<'a>] {
+    let start = tokens
+        .iter()
+        .position(|t| !t.is_trivia())
+        .unwrap_or(tokens.len());
+    let end = tokens
+        .iter()
+        .rposition(|t| !t.is_trivia())
+        .map_or(start, |i| i + 1);
+    &tokens[start..end]
+}
+
+fn next_significant(tokens: &[Token], from: usize) -> usize {
+    tokens[from.min(tokens.len())..]
+        .iter()
+        .position(|t| !t.is_trivia())
+        .map_or(tokens.len(), |i| from + i)
+}
@@ -0,0 +1,158 @@
+//! Optimizer hints, MySQL conditional comments and the pragma comments of
+//! linters, which have to stay where they are written.
+//!
+//! sqlformat puts block comments on lines of their own, which moves a hint
+//! like `SELECT /*+ INDEX(t idx) */` away from the keyword it applies to, and
+//! moves comments after a `;` to the line after it, where a `-- noqa` would
+//! apply to the next statement. These comments are taken out before sqlformat
+//! runs and put back after the token they followed, found by how many times
+//! that token came before them.
+
+use std::collections::HashMap;
+
+use crate::Dialect;
+use crate::lexer::Token;
+use crate::lexer::TokenKind;
+use crate::lexer::tokenize;
+
+/// The starts of the line comments of tools that apply to their line.
+const PRAGMAS: &[&str] = &[
+    "dprint-ignore",
+    "noqa",
+    "nolint",
+    "nosec",
+    "sqlfluff:",
+    "squawk-ignore",
+];
+
+/// A comment taken out of the text, and the token it follows.
+pub(crate) struct Attached {
+    /// The token it follows, lowercased.
+    anchor: String,
+    /// How many of those tokens come before that one.
+    occurrence: usize,
+    comment: String,
+}
+
+/// Takes the comments that have to stay after the token before them out of
+/// `text`, along with the whitespace before them.
+pub(crate) fn detach(text: &str, dialect: Dialect) -> (String, Vec<Attached>) {
+    let tokens = tokenize(text, dialect);
+    let mut result = String::with_capacity(text.len());
+    let mut attached = Vec::new();
+    let mut occurrences = HashMap::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = &tokens[i];
+        let Some(key) = anchor_key(token) else {
+            result.push_str(token.text);
+            i += 1;
+            continue;
+        };
+        let occurrence = next_occurrence(&mut occurrences, &key);
+        result.push_str(token.text);
+        let space = tokens
+            .get(i + 1)
+            .filter(|t| t.kind == TokenKind::Whitespace && !t.text.contains('\n'));
+        let comment_index = i + 1 + usize::from(space.is_some());
+        match tokens.get(comment_index) {
+            Some(comment) if is_attached(token, comment) => {
+                attached.push(Attached {
+                    anchor: key,
+                    occurrence,
+                    comment: comment.text.to_string(),
+                });
+                i = comment_index + 1;
+            }
+            _ => i += 1,
+        }
+    }
+    (result, attached)
+}
+
+/// Puts the comments taken out by `detach` back after their tokens.
+pub(crate) fn attach(formatted: String, attached: &[Attached], dialect: Dialect) -> String {
+    let tokens = tokenize(&formatted, dialect);
+    let has_conditional = tokens.iter().any(is_conditional);
+    if attached.is_empty() && !has_conditional {
+        return formatted;
+    }
+    let mut result = String::with_capacity(formatted.len());
+    let mut occurrences = HashMap::new();
+    for (i, token) in tokens.iter().enumerate() {
+        // conditional comments run as statements, whose `;` sqlformat moves
+        // to the next line
+        let before_semicolon = tokens
+            .get(i + 1)
+            .is_some_and(|t| t.kind == TokenKind::Semicolon);
+        if token.kind == TokenKind::Whitespace
+            && before_semicolon
+            && i > 0
+            && is_conditional(&tokens[i - 1])
+        {
+            continue;
+        }
+        result.push_str(token.text);
+        let Some(key) = anchor_key(token) else {
+            continue;
+        };
+        let occurrence = next_occurrence(&mut occurrences, &key);
+        let comment = attached
+            .iter()
+            .find(|a| a.anchor == key && a.occurrence == occurrence);
+        let Some(comment) = comment else {
+            continue;
+        };
+        result.push(' ');
+        result.push_str(&comment.comment);
+        // a line comment would comment out the rest of the line
+        let ends_line = tokens
+            .get(i + 1)
+            .is_none_or(|t| t.kind == TokenKind::Whitespace && t.text.starts_with(['\r', '\n']));
+        if comment.comment.starts_with("--") && !ends_line {
+            let line_start = result.rfind('\n').map_or(0, |i| i + 1);
+            let line = &result[line_start..];
+            let indent = line[..line.len() - line.trim_start().len()].to_string();
+            result.push('\n');
+            result.push_str(&indent);
+        }
+    }
+    result
+}
+
+/// What identifies `token` as one a comment can follow.
+fn anchor_key(token: &Token) -> Option<String> {
+    match token.kind {
+        TokenKind::Word => Some(token.text.to_lowercase()),
+        TokenKind::Semicolon => Some(";".to_string()),
+        _ => None,
+    }
+}
+
+/// How many times `key` came before, counting this one for the next.
+fn next_occurrence(occurrences: &mut HashMap<String, usize>, key: &str) -> usize {
+    let count = occurrences.entry(key.to_string()).or_default();
+    *count += 1;
+    *count - 1
+}
+
+/// Whether `comment` has to stay after `anchor`, on the same line.
+fn is_attached(anchor: &Token, comment: &Token) -> bool {
+    match comment.kind {
+        TokenKind::BlockComment => {
+            anchor.kind == TokenKind::Word
+                && (comment.text.starts_with("/*+") || is_conditional(comment))
+        }
+        TokenKind::LineComment => {
+            let text = comment.text.trim_start_matches(['-', '#', ' ']);
+            anchor.kind == TokenKind::Semicolon
+                && PRAGMAS.iter().any(|pragma| text.starts_with(pragma))
+        }
+        _ => false,
+    }
+}
+
+/// Whether `token` is a MySQL conditional comment, which MySQL runs.
+fn is_conditional(token: &Token) -> bool {
+    token.kind == TokenKind::BlockComment && token.text.starts_with("/*!")
+}
@@ -0,0 +1,408 @@
+//! Normalization of how identifiers are quoted and of their case, per
+//! `quoteIdentifiers` and `identifierCase`.
+
+use std::borrow::Cow;
+
+use crate::Configuration;
+use crate::Dialect;
+use crate::IdentifierCase;
+use crate::QuoteIdentifiers;
+use crate::keywords::is_keyword;
+use crate::lexer::Token;
+use crate::lexer::TokenKind;
+use crate::lexer::tokenize;
+
+/// Keywords followed by the name of a relation.
+const RELATION_KEYWORDS: &[&str] = &[
+    "copy",
+    "exists",
+    "from",
+    "into",
+    "join",
+    "recursive",
+    "table",
+    "update",
+    "with",
+];
+
+/// The relation keywords whose relation can be followed by its columns.
+const COLUMN_KEYWORDS: &[&str] = &["copy", "into", "recursive", "table", "with"];
+
+/// Keywords followed by an expression, or a list of them.
+const EXPRESSION_STARTS: &[&str] = &[
+    "and",
+    "by",
+    "distinct",
+    "else",
+    "having",
+    "not",
+    "on",
+    "or",
+    "returning",
+    "select",
+    "set",
+    "then",
+    "when",
+    "where",
+];
+
+/// Keywords that can follow a name in an expression.
+const AFTER_OPERAND: &[&str] = &[
+    "and",
+    "as",
+    "asc",
+    "between",
+    "cross",
+    "desc",
+    "else",
+    "end",
+    "except",
+    "fetch",
+    "for",
+    "from",
+    "full",
+    "group",
+    "having",
+    "ilike",
+    "in",
+    "inner",
+    "intersect",
+    "is",
+    "join",
+    "left",
+    "like",
+    "limit",
+    "natural",
+    "not",
+    "nulls",
+    "offset",
+    "on",
+    "or",
+    "order",
+    "outer",
+    "qualify",
+    "returning",
+    "right",
+    "then",
+    "union",
+    "using",
+    "when",
+    "where",
+    "window",
+];
+
+pub(crate) fn normalize_quotes(
+    text: &str,
+    mode: QuoteIdentifiers,
+    dialect: Dialect,
+) -> Cow<'_, str> {
+    if mode == QuoteIdentifiers::Preserve {
+        return Cow::Borrowed(text);
+    }
+
+    let tokens = tokenize(text, dialect);
+    let names = if mode == QuoteIdentifiers::Always {
+        name_positions(&tokens)
+    } else {
+        Vec::new()
+    };
+    let mut result = String::with_capacity(text.len());
+    for (i, token) in tokens.iter().enumerate() {
+        let normalized = match token.kind {
+            TokenKind::QuotedIdentifier => unquote(token, dialect).map(|name| {
+                if mode == QuoteIdentifiers::OnlyWhenNeeded && can_be_unquoted(&name, dialect) {
+                    name
+                } else {
+                    quote(&name, dialect)
+                }
+            }),
+            TokenKind::Word if mode == QuoteIdentifiers::Always && names[i] => {
+                // quoting disables case folding, so fold the way the server would
+                Some(match dialect {
+                    Dialect::PostgreSql => quote(&token.text.to_lowercase(), dialect),
+                    Dialect::Snowflake => quote(&token.text.to_uppercase(), dialect),
+                    _ => quote(token.text, dialect),
+                })
+            }
+            _ => None,
+        };
+        result.push_str(normalized.as_deref().unwrap_or(token.text));
+    }
+    Cow::Owned(result)
+}
+
+/// Writes unquoted identifiers in the configured case, except the words of
+/// `caseExceptions`.
+pub(crate) fn change_case<'a>(
+    text: &'a str,
+    config: &Configuration,
+    dialect: Dialect,
+) -> Cow<'a, str> {
+    // table names can be case sensitive, depending on the server or its
+    // file system
+    let folds = !matches!(dialect, Dialect::MySql | Dialect::BigQuery);
+    if config.identifier_case == IdentifierCase::Preserve || !folds {
+        return Cow::Borrowed(text);
+    }
+    let tokens = tokenize(text, dialect);
+    let mut result = String::with_capacity(text.len());
+    for (i, token) in tokens.iter().enumerate() {
+        let is_exception = config.case_exceptions.iter().any(|w| token.is_word(w));
+        if token.kind == TokenKind::Word && !is_exception && is_identifier(&tokens, i) {
+            result.push_str(&match config.identifier_case {
+                IdentifierCase::Lower => token.text.to_lowercase(),
+                _ => token.text.to_uppercase(),
+            });
+        } else {
+            result.push_str(token.text);
+        }
+    }
+    Cow::Owned(result)
+}
+
+/// Returns the name inside a quoted identifier, or `None` if the token isn't
+/// an identifier in `dialect` (ex. MySQL double-quoted strings).
+fn unquote(token: &Token, dialect: Dialect) -> Option<String> {
+    let quote = token.text.chars().next()?;
+    let is_identifier_quote = match dialect {
+        Dialect::Generic => quote == '"' || quote == '`',
+        Dialect::PostgreSql => quote == '"',
+        Dialect::MySql => quote == '`',
+        Dialect::SqlServer => quote == '"' || quote == '[',
+        Dialect::Snowflake => quote == '"',
+        Dialect::BigQuery | Dialect::Spark => quote == '`',
+    };
+    if !is_identifier_quote || !token.terminated || token.text.len() < 2 {
+        return None;
+    }
+    let close = if quote == '[' { ']' } else { quote };
+    let inner = &token.text[1..token.text.len() - 1];
+    Some(inner.replace(&format!("{close}{close}"), &close.to_string()))
+}
+
+fn quote(name: &str, dialect: Dialect) -> String {
+    let (open, close) = match dialect {
+        Dialect::MySql | Dialect::BigQuery | Dialect::Spark => ('`', '`'),
+        Dialect::SqlServer => ('[', ']'),
+        Dialect::Generic | Dialect::PostgreSql | Dialect::Snowflake => ('"', '"'),
+    };
+    format!(
+        "{open}{}{close}",
+        name.replace(close, &format!("{close}{close}"))
+    )
+}
+
+fn can_be_unquoted(name: &str, dialect: Dialect) -> bool {
+    let mut chars = name.chars();
+    let is_simple = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    // unquoted names fold to lowercase in PostgreSQL and to uppercase in
+    // Snowflake, so only names in that case keep their meaning; the others
+    // compare case-insensitively
+    let keeps_case = match dialect {
+        Dialect::Generic | Dialect::PostgreSql => !name.chars().any(|c| c.is_ascii_uppercase()),
+        Dialect::Snowflake => !name.chars().any(|c| c.is_ascii_lowercase()),
+        Dialect::MySql | Dialect::SqlServer | Dialect::BigQuery | Dialect::Spark => true,
+    };
+    is_simple && keeps_case && !is_keyword(name)
+}
+
+/// Whether `tokens[index]` is the name of a named placeholder such as `:name`.
+fn is_placeholder(tokens: &[Token], index: usize) -> bool {
+    index > 0 && tokens[index - 1].kind == TokenKind::Operator && tokens[index - 1].text == ":"
+}
+
+/// What a parenthesis holds, as far as the names in it go.
+#[derive(Clone, Copy, PartialEq)]
+enum Parens {
+    /// The columns of the relation before it.
+    Columns,
+    /// The arguments of `CAST`, whose `AS` is followed by a type.
+    Cast,
+    /// The arguments of a type, like the length of `varchar(10)`.
+    Type,
+    Other,
+}
+
+/// For each of `tokens`, whether it's a word in a place only a name can be:
+/// the relation after `FROM`, `JOIN`, `INTO` and the like and its alias, a
+/// column of the list after it, a part of a qualified name, or an operand
+/// between keywords and operators. Words anywhere else may be keywords a
+/// dialect has, like `SKIP LOCKED` or the unit of an interval, and the words
+/// of types are left alone too.
+fn name_positions(tokens: &[Token]) -> Vec<bool> {
+    let mut names = vec![false; tokens.len()];
+    // the names that are relations, or parts of their qualified names
+    let mut relations = vec![false; tokens.len()];
+    // the relations followed by a list of their columns
+    let mut has_columns = vec![false; tokens.len()];
+    // the names in a list of columns, which can be followed by their type
+    let mut columns = vec![false; tokens.len()];
+    // the words of types, like `MAP<STRING, STRING>`
+    let mut types = vec![false; tokens.len()];
+    // how deep in the `<...>` arguments of a type
+    let mut angles = 0usize;
+    let significant: Vec<usize> = (0..tokens.len())
+        .filter(|&i| !tokens[i].is_trivia())
+        .collect();
+    let mut parens = Vec::new();
+    // the first word of the statement
+    let mut statement: Option<usize> = None;
+    for (n, &i) in significant.iter().enumerate() {
+        let token = &tokens[i];
+        let back = |k: usize| n.checked_sub(k).map(|m| significant[m]);
+        let prev = back(1).map(|p| &tokens[p]);
+        let next = significant.get(n + 1).map(|&j| &tokens[j]);
+        match token.kind {
+            TokenKind::OpenParen => {
+                parens.push(match back(1) {
+                    Some(p) if relations[p] => Parens::Columns,
+                    Some(p) if types[p] => Parens::Type,
+                    Some(p)
+                        if ["cast", "try_cast", "safe_cast"]
+                            .iter()
+                            .any(|w| tokens[p].is_word(w)) =>
+                    {
+                        Parens::Cast
+                    }
+                    _ => Parens::Other,
+                });
+                continue;
+            }
+            TokenKind::CloseParen => {
+                parens.pop();
+                continue;
+            }
+            TokenKind::Semicolon => {
+                statement = None;
+                continue;
+            }
+            TokenKind::Operator if token.text.chars().all(|c| c == '<' || c == '>') => {
+                if token.text.starts_with('<') && (angles > 0 || back(1).is_some_and(|p| types[p]))
+                {
+                    angles += token.text.len();
+                } else {
+                    angles = angles.saturating_sub(token.text.len());
+                }
+                continue;
+            }
+            _ => {}
+        }
+        let top = parens.last().copied().unwrap_or(Parens::Other);
+        let is_type = angles > 0
+            || top == Parens::Type
+            || (top == Parens::Cast && prev.is_some_and(|t| t.is_word("as")))
+            || back(1).is_some_and(|p| columns[p]);
+        if token.kind == TokenKind::Word && is_type {
+            types[i] = true;
+            continue;
+        }
+        let is_name = token.kind == TokenKind::QuotedIdentifier
+            || (token.kind == TokenKind::Word && !is_keyword(token.text));
+        columns[i] = is_name
+            && top == Parens::Columns
+            && prev.is_some_and(|t| matches!(t.kind, TokenKind::OpenParen | TokenKind::Comma));
+        if statement.is_none() && token.kind == TokenKind::Word {
+            statement = Some(i);
+        }
+        let is_copy = statement.is_some_and(|s| tokens[s].is_word("copy"));
+        let after_relation_keyword = back(1).is_some_and(|p| {
+            let keyword = &tokens[p];
+            let is_relation_keyword = RELATION_KEYWORDS.iter().any(|w| keyword.is_word(w));
+            // `UPDATE` starts a statement, unlike in `FOR UPDATE`
+            let is_update = keyword.is_word("update");
+            is_relation_keyword
+                && !(is_update && statement != Some(p))
+                // the file or stream of a COPY
+                && !(is_copy && keyword.is_word("from"))
+        });
+        let after_dot = prev.is_some_and(|t| t.kind == TokenKind::Dot);
+        // the relation, or the part of its name before the dot, if any
+        let relation = if after_dot {
+            back(2).filter(|&p| relations[p])
+        } else {
+            back(1).filter(|_| after_relation_keyword)
+        };
+        if let Some(relation) = relation {
+            relations[i] = true;
+            // a name before parentheses is a table function, except after
+            // the keywords of relations with columns, like `INTO`
+            has_columns[i] = if after_dot {
+                has_columns[relation]
+            } else {
+                COLUMN_KEYWORDS.iter().any(|w| tokens[relation].is_word(w))
+            };
+        }
+        if token.kind != TokenKind::Word
+            || token.text.starts_with(['@', '$'])
+            || is_keyword(token.text)
+            || is_placeholder(tokens, i)
+        {
+            continue;
+        }
+        let is_call = next.is_some_and(|t| t.kind == TokenKind::OpenParen);
+        if is_call && !has_columns[i] {
+            relations[i] = false;
+            continue;
+        }
+        if after_dot || relation.is_some() {
+            names[i] = true;
+            continue;
+        }
+        let Some(prev) = prev else {
+            continue;
+        };
+        let after_relation = back(1).is_some_and(|p| relations[p]);
+        let is_alias = prev.is_word("as") && top != Parens::Cast;
+        // `ON DUPLICATE KEY UPDATE` is followed by assignments
+        let after_duplicate_key_update =
+            prev.is_word("update") && back(2).is_some_and(|p| tokens[p].is_word("key"));
+        let starts_operand = EXPRESSION_STARTS.iter().any(|w| prev.is_word(w))
+            || after_duplicate_key_update
+            || matches!(
+                prev.kind,
+                TokenKind::OpenParen | TokenKind::Comma | TokenKind::Operator
+            );
+        let ends = |t: Option<&Token>| {
+            t.is_none_or(|t| {
+                matches!(
+                    t.kind,
+                    TokenKind::Comma
+                        | TokenKind::CloseParen
+                        | TokenKind::Semicolon
+                        | TokenKind::Dot
+                        | TokenKind::Operator
+                ) || AFTER_OPERAND.iter().any(|w| t.is_word(w))
+            })
+        };
+        // an alias without `AS`, like `b` in `SELECT a b`
+        let is_bare_alias = |t: Option<&Token>, after: Option<&Token>| {
+            t.is_some_and(|t| t.kind == TokenKind::Word && !is_keyword(t.text)) && ends(after)
+        };
+        let after_name = significant.get(n + 2).map(|&j| &tokens[j]);
+        let ends_operand = ends(next) || is_bare_alias(next, after_name);
+        let after_operand = back(1).is_some_and(|p| names[p]) && ends(next);
+        if after_relation
+            || is_alias
+            || columns[i]
+            || after_operand
+            || (starts_operand && ends_operand)
+        {
+            names[i] = true;
+        }
+    }
+    names
+}
+
+fn is_identifier(tokens: &[Token], index: usize) -> bool {
+    let token = &tokens[index];
+    if token.text.starts_with(['@', '$']) || is_keyword(token.text) || is_placeholder(tokens, index)
+    {
+        return false;
+    }
+    // function calls
+    let next = tokens[index + 1..].iter().find(|t| !t.is_trivia());
+    !next.is_some_and(|t| t.kind == TokenKind::OpenParen)
+}
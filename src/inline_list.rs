@@ -0,0 +1,319 @@
+//! Layout of select lists, `IN` lists, function arguments and `VALUES`
+//! tuples, per `maxInlineSelectList`, `maxInlineInList`,
+//! `maxInlineFunctionArguments` and `maxInlineValues`.
+//!
+//! sqlformat breaks all of these by the same `maxInlineArguments` and
+//! `maxInlineBlock` lengths. With a width set for one kind of list, lists of
+//! that kind whose items fit in that many characters are kept on one line and
+//! longer ones get one item per line.
+
+use crate::Configuration;
+use crate::Dialect;
+use crate::by_list;
+use crate::lexer::Token;
+use crate::lexer::TokenKind;
+use crate::lexer::joined;
+use crate::lexer::matching_paren;
+use crate::lexer::tokenize;
+use crate::trace;
+
+/// Keywords that end a select list when they appear outside of parentheses.
+const SELECT_TERMINATORS: &[&str] = &[
+    "except",
+    "fetch",
+    "from",
+    "group",
+    "having",
+    "intersect",
+    "into",
+    "limit",
+    "minus",
+    "offset",
+    "order",
+    "qualify",
+    "union",
+    "where",
+    "window",
+];
+
+/// Words directly followed by a parenthesis that isn't a function's
+/// arguments.
+const NOT_FUNCTIONS: &[&str] = &[
+    "all", "and", "any", "as", "exists", "filter", "in", "not", "on", "or", "over", "some",
+    "using", "values", "within",
+];
+
+/// Words before a name whose parenthesis lists columns or parameters rather
+/// than arguments.
+const DEFINITIONS: &[&str] = &[
+    "exists",
+    "function",
+    "index",
+    "into",
+    "on",
+    "procedure",
+    "references",
+    "table",
+    "type",
+    "view",
+];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ListKind {
+    InList,
+    FunctionArguments,
+    Values,
+}
+
+pub(crate) fn layout(formatted: String, config: &Configuration, dialect: Dialect) -> String {
+    if config.max_inline_select_list.is_none()
+        && config.max_inline_in_list.is_none()
+        && config.max_inline_function_arguments.is_none()
+        && config.max_inline_values.is_none()
+    {
+        return formatted;
+    }
+    // a pass leaves the lists inside the ones it rearranges for the next, so
+    // this ends after as many passes as lists are nested
+    let mut formatted = formatted;
+    loop {
+        let next = Pass {
+            text: &formatted,
+            config,
+            indent: config.indent(),
+        }
+        .run(dialect);
+        if next == formatted {
+            return next;
+        }
+        formatted = next;
+    }
+}
+
+struct Pass<'a> {
+    text: &'a str,
+    config: &'a Configuration,
+    indent: String,
+}
+
+impl Pass<'_> {
+    fn run(&self, dialect: Dialect) -> String {
+        let tokens = tokenize(self.text, dialect);
+        let kinds = list_kinds(&tokens);
+        let mut result = String::with_capacity(self.text.len());
+        let mut pos = 0;
+        let mut i = 0;
+        while i < tokens.len() {
+            let token = &tokens[i];
+            let rearranged = if token.is_word("select") {
+                self.select_list(&tokens, i)
+            } else if token.kind == TokenKind::OpenParen {
+                kinds
+                    .binary_search_by_key(&i, |&(open, _)| open)
+                    .ok()
+                    .and_then(|k| self.parenthesized(&tokens, i, kinds[k].1))
+            } else {
+                None
+            };
+            match rearranged {
+                Some((start, end, text, decision)) if text != self.text[start..end] => {
+                    trace::decided(decision);
+                    result.push_str(&self.text[pos..start]);
+                    result.push_str(&text);
+                    pos = end;
+                    i = tokens.partition_point(|t| t.start < end);
+                }
+                _ => i += 1,
+            }
+        }
+        result.push_str(&self.text[pos..]);
+        result
+    }
+
+    /// The span of the select list after `tokens[select]`, its new text and
+    /// the decision that made it.
+    fn select_list(
+        &self,
+        tokens: &[Token],
+        select: usize,
+    ) -> Option<(usize, usize, String, &'static str)> {
+        let max_width = self.config.max_inline_select_list?;
+        let mut start = select + 1;
+        let first = next_significant(tokens, start)?;
+        if tokens[first].is_word("distinct") || tokens[first].is_word("all") {
+            start = first + 1;
+        }
+        let first = next_significant(tokens, start)?;
+        if tokens[first].is_word("on") || tokens[first].is_word("top") {
+            return None;
+        }
+        let (items, _) = by_list::items(tokens, start, SELECT_TERMINATORS);
+        if items.is_empty() || has_line_comment(items.iter().flat_map(|item| item.iter())) {
+            return None;
+        }
+        let last = items.last().and_then(|item| item.last()).unwrap();
+        let list_end = last.start + last.text.len();
+        let keyword = &tokens[start - 1];
+        let keyword_end = keyword.start + keyword.text.len();
+        let before = &tokens[start];
+        let broken_before = before.kind == TokenKind::Whitespace && before.text.contains('\n');
+
+        let inline: Vec<_> = items.iter().map(|item| joined(item)).collect();
+        let inline = inline.join(", ");
+        if inline.chars().count() <= max_width {
+            let before = if broken_before { before.text } else { " " };
+            return Some((
+                keyword_end,
+                list_end,
+                format!("{before}{inline}"),
+                "select list: inline",
+            ));
+        }
+        let texts: Vec<_> = items.iter().map(|item| by_list::text(item)).collect();
+        // items spanning several lines are indented for the line they start
+        // on, and what comes after the list on its last line would be left
+        // after the last item
+        let rest_of_line = self.text[list_end..].split('\n').next().unwrap_or_default();
+        if (!broken_before && texts.iter().any(|t| t.contains('\n')))
+            || !(rest_of_line.trim().is_empty() || rest_of_line.trim() == ";")
+        {
+            return None;
+        }
+        let base = self.base_indent(tokens[select].start);
+        let separator = format!("\n{base}{}", self.indent);
+        Some((
+            keyword_end,
+            list_end,
+            format!("{separator}{}", texts.join(&format!(",{separator}"))),
+            "select list: one item per line",
+        ))
+    }
+
+    /// The span of the parenthesized list at `tokens[open]`, its new text and
+    /// the decision that made it.
+    fn parenthesized(
+        &self,
+        tokens: &[Token],
+        open: usize,
+        kind: ListKind,
+    ) -> Option<(usize, usize, String, &'static str)> {
+        let max_width = match kind {
+            ListKind::InList => self.config.max_inline_in_list,
+            ListKind::FunctionArguments => self.config.max_inline_function_arguments,
+            ListKind::Values => self.config.max_inline_values,
+        }?;
+        let close = matching_paren(tokens, open)?;
+        let inner = &tokens[open + 1..close];
+        let starts_query = next_significant(tokens, open + 1)
+            .is_some_and(|i| tokens[i].is_word("select") || tokens[i].is_word("with"));
+        if starts_query || has_line_comment(inner.iter()) {
+            return None;
+        }
+        let (items, end) = by_list::items(tokens, open + 1, &[]);
+        if items.is_empty() || end != close {
+            return None;
+        }
+        let start = tokens[open].start;
+        let end = tokens[close].start + 1;
+
+        let inline = joined(inner);
+        if inline.chars().count() <= max_width {
+            return Some((start, end, format!("({inline})"), "list: inline"));
+        }
+        let broken_before = inner
+            .first()
+            .is_some_and(|t| t.kind == TokenKind::Whitespace && t.text.contains('\n'));
+        let texts: Vec<_> = items.iter().map(|item| by_list::text(item)).collect();
+        if !broken_before && texts.iter().any(|t| t.contains('\n')) {
+            return None;
+        }
+        let base = self.base_indent(start);
+        let separator = format!("\n{base}{}", self.indent);
+        Some((
+            start,
+            end,
+            format!(
+                "({separator}{}\n{base})",
+                texts.join(&format!(",{separator}"))
+            ),
+            "list: one item per line",
+        ))
+    }
+
+    /// The indentation of the line `offset` is on.
+    fn base_indent(&self, offset: usize) -> &str {
+        let line_start = self.text[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line = &self.text[line_start..offset];
+        &line[..line.len() - line.trim_start().len()]
+    }
+}
+
+/// The opening parentheses of the lists in `tokens`, with their kinds.
+fn list_kinds(tokens: &[Token]) -> Vec<(usize, ListKind)> {
+    let mut kinds = Vec::new();
+    let mut depth = 0usize;
+    // the depth of the `VALUES` list being read, whose tuples come one after
+    // the other separated by commas
+    let mut values_depth = None;
+    let mut previous: Option<usize> = None;
+    for (i, token) in tokens.iter().enumerate() {
+        if token.is_trivia() {
+            continue;
+        }
+        match token.kind {
+            TokenKind::OpenParen => {
+                let previous_token = previous.map(|p| &tokens[p]);
+                let kind = if previous_token.is_some_and(|t| t.is_word("in")) {
+                    Some(ListKind::InList)
+                } else if previous_token.is_some_and(|t| t.is_word("values"))
+                    || (values_depth == Some(depth)
+                        && previous_token.is_some_and(|t| t.kind == TokenKind::Comma))
+                {
+                    Some(ListKind::Values)
+                } else if i > 0 && is_function_name(tokens, i - 1) {
+                    Some(ListKind::FunctionArguments)
+                } else {
+                    None
+                };
+                kinds.extend(kind.map(|kind| (i, kind)));
+                depth += 1;
+            }
+            TokenKind::CloseParen => depth = depth.saturating_sub(1),
+            TokenKind::Comma => {}
+            TokenKind::Word if token.is_word("values") => values_depth = Some(depth),
+            _ if values_depth == Some(depth) => values_depth = None,
+            _ => {}
+        }
+        previous = Some(i);
+    }
+    kinds
+}
+
+/// Whether `tokens[i]` is the name of a function called right after it.
+pub(crate) fn is_function_name(tokens: &[Token], i: usize) -> bool {
+    let name = &tokens[i];
+    if name.kind != TokenKind::Word || NOT_FUNCTIONS.iter().any(|w| name.is_word(w)) {
+        return false;
+    }
+    // the word before the whole, possibly qualified, name
+    let mut start = i;
+    while start >= 2
+        && tokens[start - 1].kind == TokenKind::Dot
+        && matches!(
+            tokens[start - 2].kind,
+            TokenKind::Word | TokenKind::QuotedIdentifier
+        )
+    {
+        start -= 2;
+    }
+    let before = tokens[..start].iter().rev().find(|t| !t.is_trivia());
+    !before.is_some_and(|t| DEFINITIONS.iter().any(|w| t.is_word(w)))
+}
+
+fn next_significant(tokens: &[Token], start: usize) -> Option<usize> {
+    (start..tokens.len()).find(|&i| !tokens[i].is_trivia())
+}
+
+fn has_line_comment<'a>(mut tokens: impl Iterator<Item = &'a Token<'a>>) -> bool {
+    tokens.any(|t| t.kind == TokenKind::LineComment)
+}
@@ -0,0 +1,256 @@
+//! Layout of join conditions, per `joinConditionPosition` and
+//! `inlineJoinUsing`.
+//!
+//! sqlformat keeps `ON` after the joined table and starts the lines of a
+//! longer condition at the indentation of the join itself, which makes them
+//! look like the next join. This pass indents those lines under the join or
+//! moves `ON` to a line of its own, and joins the column list of `USING`
+//! onto one line.
+
+use std::collections::HashMap;
+
+use crate::Configuration;
+use crate::Dialect;
+use crate::JoinConditionPosition;
+use crate::lexer::Token;
+use crate::lexer::TokenKind;
+use crate::lexer::joined;
+use crate::lexer::matching_paren;
+use crate::lexer::tokenize;
+use crate::trace;
+
+/// Words that may come before `JOIN` as part of the join.
+const JOIN_MODIFIERS: &[&str] = &[
+    "cross", "full", "inner", "left", "natural", "outer", "right",
+];
+
+/// Keywords that end a join condition when they appear outside of
+/// parentheses.
+const TERMINATORS: &[&str] = &[
+    "except",
+    "fetch",
+    "for",
+    "from",
+    "group",
+    "having",
+    "intersect",
+    "limit",
+    "minus",
+    "offset",
+    "order",
+    "qualify",
+    "returning",
+    "select",
+    "set",
+    "union",
+    "where",
+    "window",
+];
+
+pub(crate) fn layout(formatted: String, config: &Configuration, dialect: Dialect) -> String {
+    let position = config.join_condition_position;
+    if position == JoinConditionPosition::Default && !config.inline_join_using {
+        return formatted;
+    }
+    let tokens = tokenize(&formatted, dialect);
+    // whitespace to write instead of a token, and the number of indentation
+    // levels to add to the line starting in whitespace
+    let mut replacements = HashMap::new();
+    let mut extra = vec![0; tokens.len()];
+    // `USING` lists to write on one line, by their opening parenthesis
+    let mut usings = HashMap::new();
+
+    for join in 0..tokens.len() {
+        if !(tokens[join].is_word("join") || tokens[join].is_word("straight_join")) {
+            continue;
+        }
+        let Some(keyword) = condition_keyword(&tokens, join + 1) else {
+            continue;
+        };
+        if tokens[keyword].is_word("using") {
+            let open = next_significant(&tokens, keyword + 1);
+            let close = open
+                .filter(|&open| tokens[open].kind == TokenKind::OpenParen)
+                .and_then(|open| matching_paren(&tokens, open));
+            if let (true, Some(open), Some(close)) = (config.inline_join_using, open, close) {
+                // joining lines would comment out the rest of the list
+                if !tokens[open..close]
+                    .iter()
+                    .any(|t| t.kind == TokenKind::LineComment)
+                {
+                    trace::decided("join: using list inline");
+                    usings.insert(open, close);
+                }
+            }
+            continue;
+        }
+
+        let end = condition_end(&tokens, keyword + 1);
+        let base = line_indentation(&formatted, tokens[keyword].start);
+        match position {
+            JoinConditionPosition::Default => {}
+            JoinConditionPosition::SameLine => {
+                trace::decided("join: condition indented under the join");
+                let mut depth = 0usize;
+                for i in keyword + 1..end {
+                    match tokens[i].kind {
+                        TokenKind::OpenParen => depth += 1,
+                        TokenKind::CloseParen => depth = depth.saturating_sub(1),
+                        TokenKind::Whitespace
+                            if depth == 0
+                                && i + 1 < end
+                                && indentation(tokens[i].text) == Some(base) =>
+                        {
+                            extra[i] += 1;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            JoinConditionPosition::NextLine => {
+                // moving the condition away from what follows it on the same
+                // line would leave that after the condition
+                let ends_line = end == tokens.len()
+                    || tokens[end].kind == TokenKind::Semicolon
+                    || tokens[end - 1].kind == TokenKind::Whitespace
+                        && tokens[end - 1].text.contains('\n');
+                let before = &tokens[keyword - 1];
+                if !ends_line || !starts_line(&tokens, join) || before.kind != TokenKind::Whitespace
+                {
+                    continue;
+                }
+                trace::decided("join: condition on the next line");
+                if !before.text.contains('\n') {
+                    replacements.insert(keyword - 1, format!("\n{base}"));
+                }
+                extra[keyword - 1] += 1;
+                for i in keyword + 1..end {
+                    if tokens[i].kind == TokenKind::Whitespace && i + 1 < end {
+                        extra[i] += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    let indent = config.indent();
+    let mut result = String::with_capacity(formatted.len());
+    let mut i = 0;
+    while i < tokens.len() {
+        if let Some(&close) = usings.get(&i) {
+            result.push('(');
+            result.push_str(&joined(&tokens[i + 1..close]));
+            result.push(')');
+            i = close + 1;
+            continue;
+        }
+        let text = replacements
+            .get(&i)
+            .map(String::as_str)
+            .unwrap_or(tokens[i].text);
+        match text.rfind('\n') {
+            Some(newline) if extra[i] > 0 => {
+                result.push_str(&text[..=newline]);
+                result.push_str(&indent.repeat(extra[i]));
+                result.push_str(&text[newline + 1..]);
+            }
+            _ => result.push_str(text),
+        }
+        i += 1;
+    }
+    result
+}
+
+/// The index of the `ON` or `USING` of the join whose table starts at
+/// `tokens[start]`.
+fn condition_keyword(tokens: &[Token], start: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    for i in start..tokens.len() {
+        let token = &tokens[i];
+        match token.kind {
+            TokenKind::OpenParen => depth += 1,
+            TokenKind::CloseParen if depth == 0 => return None,
+            TokenKind::CloseParen => depth -= 1,
+            _ if depth > 0 => {}
+            TokenKind::Word if token.is_word("on") || token.is_word("using") => return Some(i),
+            _ if ends_condition(tokens, i) => return None,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The index of the token after the condition starting at `tokens[start]`,
+/// or of the whitespace before it.
+fn condition_end(tokens: &[Token], start: usize) -> usize {
+    let mut depth = 0usize;
+    let mut i = start;
+    while i < tokens.len() {
+        match tokens[i].kind {
+            TokenKind::OpenParen => depth += 1,
+            TokenKind::CloseParen if depth == 0 => break,
+            TokenKind::CloseParen => depth -= 1,
+            _ if depth == 0 && ends_condition(tokens, i) => break,
+            _ => {}
+        }
+        i += 1;
+    }
+    while i > start && tokens[i - 1].kind == TokenKind::Whitespace {
+        i -= 1;
+    }
+    // keep the whitespace before what follows so that the caller can tell
+    // whether the condition ends its line
+    if i < tokens.len() && tokens[i].kind == TokenKind::Whitespace {
+        i + 1
+    } else {
+        i
+    }
+}
+
+fn ends_condition(tokens: &[Token], i: usize) -> bool {
+    let token = &tokens[i];
+    match token.kind {
+        TokenKind::Comma | TokenKind::Semicolon | TokenKind::CloseParen => true,
+        TokenKind::Word => TERMINATORS.iter().any(|w| token.is_word(w)) || starts_join(tokens, i),
+        _ => false,
+    }
+}
+
+fn starts_join(tokens: &[Token], start: usize) -> bool {
+    for token in tokens[start..].iter().filter(|t| !t.is_trivia()) {
+        if token.is_word("join") || token.is_word("straight_join") || token.is_word("apply") {
+            return true;
+        }
+        if !JOIN_MODIFIERS.iter().any(|w| token.is_word(w)) {
+            return false;
+        }
+    }
+    false
+}
+
+/// Whether the join ending in `tokens[join]` starts its line.
+fn starts_line(tokens: &[Token], join: usize) -> bool {
+    let mut i = join;
+    while let Some(previous) = tokens[..i].iter().rposition(|t| !t.is_trivia())
+        && JOIN_MODIFIERS.iter().any(|w| tokens[previous].is_word(w))
+    {
+        i = previous;
+    }
+    i > 0 && tokens[i - 1].kind == TokenKind::Whitespace && tokens[i - 1].text.contains('\n')
+}
+
+fn next_significant(tokens: &[Token], start: usize) -> Option<usize> {
+    (start..tokens.len()).find(|&i| !tokens[i].is_trivia())
+}
+
+/// The indentation of the line `position` is on.
+fn line_indentation(text: &str, position: usize) -> &str {
+    let line_start = text[..position].rfind('\n').map_or(0, |i| i + 1);
+    let line = &text[line_start..];
+    &line[..line.len() - line.trim_start().len()]
+}
+
+/// The indentation of the line started by whitespace, if it starts one.
+fn indentation(whitespace: &str) -> Option<&str> {
+    whitespace.rfind('\n').map(|i| &whitespace[i + 1..])
+}
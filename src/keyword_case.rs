@@ -0,0 +1,52 @@
+//! Uppercases the keywords sqlformat compares against their uppercase
+//! spelling, and finds the spellings of `caseExceptions`.
+//!
+//! sqlformat matches keywords in any case but then tells some of them apart
+//! by the text as written, so a lowercase `USING` starts a clause instead of
+//! continuing a join and a lowercase `CREATE TABLE` gets its name on a line of
+//! its own. Since sqlformat writes keywords in lowercase by default, a second
+//! run would lay out its own output differently.
+
+use std::borrow::Cow;
+
+use crate::Dialect;
+use crate::lexer::tokenize;
+
+const COMPARED: &[&str] = &["create", "using"];
+
+pub(crate) fn uppercase_compared(text: &str, dialect: Dialect) -> Cow<'_, str> {
+    let tokens = tokenize(text, dialect);
+    let needs_uppercase = |i: usize| {
+        let token = &tokens[i];
+        COMPARED.iter().any(|w| token.is_word(w)) && token.text != token.text.to_uppercase()
+    };
+    if !(0..tokens.len()).any(needs_uppercase) {
+        return Cow::Borrowed(text);
+    }
+    let mut result = String::with_capacity(text.len());
+    for (i, token) in tokens.iter().enumerate() {
+        if needs_uppercase(i) {
+            result.push_str(&token.text.to_uppercase());
+        } else {
+            result.push_str(token.text);
+        }
+    }
+    Cow::Owned(result)
+}
+
+/// The spellings in `text` of the words in `exceptions`, which sqlformat only
+/// leaves alone when they match exactly.
+pub(crate) fn exceptions(text: &str, exceptions: &[String], dialect: Dialect) -> Vec<String> {
+    if exceptions.is_empty() {
+        return Vec::new();
+    }
+    let mut spellings: Vec<String> = Vec::new();
+    for token in tokenize(text, dialect) {
+        if exceptions.iter().any(|word| token.is_word(word))
+            && !spellings.iter().any(|spelling| spelling == token.text)
+        {
+            spellings.push(token.text.to_string());
+        }
+    }
+    spellings
+}
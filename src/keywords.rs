@@ -0,0 +1,294 @@
+//! Words treated as SQL keywords when deciding whether an identifier can be
+//! written unquoted. The union of common reserved words across dialects,
+//! including all of PostgreSQL's, plus built-in type names, kept sorted for
+//! binary search.
+
+const KEYWORDS: &[&str] = &[
+    "ABORT",
+    "ABSOLUTE",
+    "ACTION",
+    "ADD",
+    "AFTER",
+    "ALL",
+    "ALTER",
+    "ALWAYS",
+    "ANALYSE",
+    "ANALYZE",
+    "AND",
+    "ANY",
+    "APPLY",
+    "ARRAY",
+    "AS",
+    "ASC",
+    "ASOF",
+    "ASYMMETRIC",
+    "AT",
+    "ATOMIC",
+    "AUTHORIZATION",
+    "AUTO_INCREMENT",
+    "BEFORE",
+    "BEGIN",
+    "BETWEEN",
+    "BIGINT",
+    "BIGSERIAL",
+    "BINARY",
+    "BIT",
+    "BLOB",
+    "BOOLEAN",
+    "BOTH",
+    "BREAK",
+    "BY",
+    "BYTEA",
+    "CALL",
+    "CASCADE",
+    "CASE",
+    "CAST",
+    "CHAR",
+    "CHARACTER",
+    "CHECK",
+    "CLOB",
+    "CLOSE",
+    "CLUSTER",
+    "COALESCE",
+    "COLLATE",
+    "COLLATION",
+    "COLUMN",
+    "COMMENT",
+    "COMMIT",
+    "CONCURRENTLY",
+    "CONFLICT",
+    "CONSTRAINT",
+    "CONTINUE",
+    "CONVERT",
+    "COPY",
+    "CREATE",
+    "CROSS",
+    "CUBE",
+    "CURRENT",
+    "CURRENT_CATALOG",
+    "CURRENT_DATE",
+    "CURRENT_ROLE",
+    "CURRENT_SCHEMA",
+    "CURRENT_TIME",
+    "CURRENT_TIMESTAMP",
+    "CURRENT_USER",
+    "CURSOR",
+    "DATABASE",
+    "DATE",
+    "DATETIME",
+    "DEALLOCATE",
+    "DEC",
+    "DECIMAL",
+    "DECLARE",
+    "DEFAULT",
+    "DEFERRABLE",
+    "DEFERRED",
+    "DELETE",
+    "DELIMITER",
+    "DENY",
+    "DESC",
+    "DESCRIBE",
+    "DISTINCT",
+    "DISTRIBUTE",
+    "DIV",
+    "DO",
+    "DOUBLE",
+    "DROP",
+    "EACH",
+    "ELSE",
+    "ELSEIF",
+    "ELSIF",
+    "END",
+    "ENUM",
+    "ESCAPE",
+    "EXCEPT",
+    "EXCLUDE",
+    "EXEC",
+    "EXECUTE",
+    "EXISTS",
+    "EXPLAIN",
+    "EXTENSION",
+    "FALSE",
+    "FETCH",
+    "FILTER",
+    "FIRST",
+    "FLOAT",
+    "FOLLOWING",
+    "FOR",
+    "FOREIGN",
+    "FREEZE",
+    "FROM",
+    "FULL",
+    "FUNCTION",
+    "GENERATED",
+    "GLOBAL",
+    "GO",
+    "GRANT",
+    "GROUP",
+    "GROUPING",
+    "HANDLER",
+    "HAVING",
+    "IDENTITY",
+    "IF",
+    "ILIKE",
+    "IMMEDIATE",
+    "IN",
+    "INDEX",
+    "INHERITS",
+    "INITIALLY",
+    "INNER",
+    "INOUT",
+    "INSERT",
+    "INT",
+    "INTEGER",
+    "INTERSECT",
+    "INTERVAL",
+    "INTO",
+    "IS",
+    "ISNULL",
+    "JOIN",
+    "JSON",
+    "JSONB",
+    "KEY",
+    "LANGUAGE",
+    "LAST",
+    "LATERAL",
+    "LEADING",
+    "LEAVE",
+    "LEFT",
+    "LIKE",
+    "LIMIT",
+    "LOCAL",
+    "LOCALTIME",
+    "LOCALTIMESTAMP",
+    "LOCK",
+    "LOOP",
+    "MATCHED",
+    "MATERIALIZED",
+    "MERGE",
+    "MINUS",
+    "NATURAL",
+    "NCHAR",
+    "NO",
+    "NOT",
+    "NOTHING",
+    "NOTNULL",
+    "NULL",
+    "NULLS",
+    "NUMERIC",
+    "NVARCHAR",
+    "OF",
+    "OFFSET",
+    "ON",
+    "ONLY",
+    "OPEN",
+    "OR",
+    "ORDER",
+    "OUT",
+    "OUTER",
+    "OVER",
+    "OVERLAPS",
+    "OWNER",
+    "PARTITION",
+    "PERFORM",
+    "PIVOT",
+    "PLACING",
+    "PRECEDING",
+    "PRECISION",
+    "PREPARE",
+    "PRIMARY",
+    "PRIVILEGES",
+    "PROCEDURE",
+    "QUALIFY",
+    "RAISE",
+    "RANGE",
+    "REAL",
+    "RECURSIVE",
+    "REFERENCES",
+    "RELEASE",
+    "RENAME",
+    "REPEAT",
+    "REPLACE",
+    "RESTRICT",
+    "RETURN",
+    "RETURNING",
+    "RETURNS",
+    "REVOKE",
+    "RIGHT",
+    "ROLLBACK",
+    "ROLLUP",
+    "ROW",
+    "ROWS",
+    "SAVEPOINT",
+    "SCHEMA",
+    "SELECT",
+    "SEQUENCE",
+    "SERIAL",
+    "SESSION_USER",
+    "SET",
+    "SETS",
+    "SIMILAR",
+    "SMALLINT",
+    "SOME",
+    "SYMMETRIC",
+    "SYSTEM_USER",
+    "TABLE",
+    "TABLESAMPLE",
+    "TEMP",
+    "TEMPORARY",
+    "TEXT",
+    "THEN",
+    "TIES",
+    "TIME",
+    "TIMESTAMP",
+    "TIMESTAMPTZ",
+    "TINYINT",
+    "TO",
+    "TOP",
+    "TRAILING",
+    "TRANSACTION",
+    "TRIGGER",
+    "TRUE",
+    "TRUNCATE",
+    "TYPE",
+    "UNBOUNDED",
+    "UNION",
+    "UNIQUE",
+    "UNKNOWN",
+    "UNNEST",
+    "UNPIVOT",
+    "UNTIL",
+    "UPDATE",
+    "USER",
+    "USING",
+    "UUID",
+    "VACUUM",
+    "VALUES",
+    "VARBINARY",
+    "VARCHAR",
+    "VARIADIC",
+    "VARYING",
+    "VERBOSE",
+    "VIEW",
+    "WHEN",
+    "WHERE",
+    "WHILE",
+    "WINDOW",
+    "WITH",
+    "WITHIN",
+    "WITHOUT",
+    "XOR",
+    "ZONE",
+];
+
+pub(crate) fn is_keyword(word: &str) -> bool {
+    KEYWORDS
+        .binary_search_by(|k| cmp_ignore_ascii_case(k, word))
+        .is_ok()
+}
+
+fn cmp_ignore_ascii_case(a: &str, b: &str) -> std::cmp::Ordering {
+    a.bytes()
+        .map(|c| c.to_ascii_uppercase())
+        .cmp(b.bytes().map(|c| c.to_ascii_uppercase()))
+}
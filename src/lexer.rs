@@ -0,0 +1,468 @@
+//! A small span-preserving SQL scanner.
+//!
+//! sqlformat keeps its tokenizer private, so the passes that run before and
+//! after it (strict checks, statement splitting, etc.) use this instead. The
+//! quoting and comment rules intentionally mirror sqlformat's so both agree on
+//! where literals and comments start and end.
+
+use crate::Dialect;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum TokenKind {
+    Whitespace,
+    LineComment,
+    BlockComment,
+    String,
+    QuotedIdentifier,
+    Number,
+    Word,
+    OpenParen,
+    CloseParen,
+    Comma,
+    Semicolon,
+    Dot,
+    Operator,
+    /// The rows after a `COPY ... FROM stdin;` statement, up to and including
+    /// the `\.` line ending them.
+    CopyData,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Token<'a> {
+    pub kind: TokenKind,
+    pub text: &'a str,
+    pub start: usize,
+    /// False for strings, quoted identifiers and block comments that run to
+    /// the end of the input without their closing delimiter.
+    pub terminated: bool,
+}
+
+impl Token<'_> {
+    pub fn is_trivia(&self) -> bool {
+        matches!(
+            self.kind,
+            TokenKind::Whitespace | TokenKind::LineComment | TokenKind::BlockComment
+        )
+    }
+
+    pub fn is_word(&self, word: &str) -> bool {
+        self.kind == TokenKind::Word && self.text.eq_ignore_ascii_case(word)
+    }
+
+    /// The delimiter that would close this token if it runs to the end of the
+    /// input without it, like the `$body$` of a dollar-quoted string.
+    pub fn missing_delimiter(&self) -> Option<&str> {
+        if self.terminated {
+            return None;
+        }
+        match self.kind {
+            TokenKind::BlockComment => Some("*/"),
+            TokenKind::String if self.text.starts_with('$') => {
+                let tag_end = self.text[1..].find('$')? + 2;
+                Some(&self.text[..tag_end])
+            }
+            TokenKind::String | TokenKind::QuotedIdentifier => {
+                let quote = self.text.find(['\'', '"', '`', '['])?;
+                match &self.text[quote..=quote] {
+                    "[" => Some("]"),
+                    quote => Some(quote),
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The index of the parenthesis closing the one at `tokens[open]`.
+pub(crate) fn matching_paren(tokens: &[Token], open: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    for (i, token) in tokens.iter().enumerate().skip(open) {
+        match token.kind {
+            TokenKind::OpenParen => depth += 1,
+            TokenKind::CloseParen => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Whether `tokens[i]` ends a statement: a semicolon, or the rows after one
+/// that ends a `COPY ... FROM stdin`.
+pub(crate) fn ends_statement(tokens: &[Token], i: usize) -> bool {
+    match tokens[i].kind {
+        TokenKind::Semicolon => tokens
+            .get(i + 2)
+            .is_none_or(|t| t.kind != TokenKind::CopyData),
+        TokenKind::CopyData => true,
+        _ => false,
+    }
+}
+
+/// Whether the statement made of `tokens` is a `COPY ... FROM stdin` whose
+/// rows follow it.
+pub(crate) fn reads_copy_data(tokens: &[Token]) -> bool {
+    let mut copy = CopyFromStdin::default();
+    tokens.iter().any(|token| copy.next(token))
+}
+
+/// Writes `tokens` on one line, without the line breaks and indentation
+/// sqlformat put between them.
+pub(crate) fn joined(tokens: &[Token]) -> String {
+    let mut joined = String::new();
+    for (i, token) in tokens.iter().enumerate() {
+        if token.kind != TokenKind::Whitespace {
+            joined.push_str(token.text);
+            continue;
+        }
+        let at_edge = i == 0 || i + 1 == tokens.len();
+        if at_edge {
+            continue;
+        }
+        if !token.text.contains('\n') {
+            joined.push_str(token.text);
+        } else if tokens[i - 1].kind != TokenKind::OpenParen
+            && tokens[i + 1].kind != TokenKind::CloseParen
+        {
+            joined.push(' ');
+        }
+    }
+    joined
+}
+
+/// The indexes of the lines of `text` that start inside a token.
+pub(crate) fn continued_lines(text: &str, dialect: Dialect) -> Vec<usize> {
+    let mut lines = Vec::new();
+    let mut line = 0;
+    for token in tokenize(text, dialect) {
+        let breaks = token.text.matches('\n').count();
+        if token.kind != TokenKind::Whitespace {
+            lines.extend(line + 1..=line + breaks);
+        }
+        line += breaks;
+    }
+    lines
+}
+
+pub(crate) fn tokenize(text: &str, dialect: Dialect) -> Vec<Token<'_>> {
+    let mut lexer = Lexer {
+        text,
+        pos: 0,
+        dialect,
+    };
+    let mut tokens = Vec::new();
+    let mut copy = CopyFromStdin::default();
+    while lexer.pos < text.len() {
+        let token = lexer.next_token();
+        let data_follows = copy.next(&token);
+        tokens.push(token);
+        if data_follows {
+            tokens.extend(lexer.copy_data());
+        }
+    }
+    tokens
+}
+
+/// Follows the statements being tokenized to tell when one is a `COPY` that
+/// reads rows from the lines after it, like those pg_dump writes.
+#[derive(Default)]
+struct CopyFromStdin {
+    /// Whether the statement has had a significant token yet.
+    started: bool,
+    is_copy: bool,
+    after_from: bool,
+    from_stdin: bool,
+}
+
+impl CopyFromStdin {
+    /// Returns true at the semicolon ending a `COPY ... FROM stdin`.
+    fn next(&mut self, token: &Token) -> bool {
+        if token.is_trivia() {
+            return false;
+        }
+        if token.kind == TokenKind::Semicolon {
+            let data_follows = self.is_copy && self.from_stdin;
+            *self = CopyFromStdin::default();
+            return data_follows;
+        }
+        if !self.started {
+            self.started = true;
+            self.is_copy = token.is_word("copy");
+        } else if self.after_from && token.is_word("stdin") {
+            self.from_stdin = true;
+        }
+        self.after_from = token.is_word("from");
+        false
+    }
+}
+
+struct Lexer<'a> {
+    text: &'a str,
+    pos: usize,
+    dialect: Dialect,
+}
+
+impl<'a> Lexer<'a> {
+    fn rest(&self) -> &'a str {
+        &self.text[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<char> {
+        self.rest().chars().nth(n)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn bump_while(&mut self, f: impl Fn(char) -> bool) {
+        while self.peek().is_some_and(&f) {
+            self.bump();
+        }
+    }
+
+    fn next_token(&mut self) -> Token<'a> {
+        let start = self.pos;
+        let mut terminated = true;
+        let c = self.bump().unwrap();
+        let kind = match c {
+            c if c.is_whitespace() => {
+                self.bump_while(char::is_whitespace);
+                TokenKind::Whitespace
+            }
+            '-' if self.peek() == Some('-') => {
+                self.bump_while(|c| c != '\n' && c != '\r');
+                TokenKind::LineComment
+            }
+            '#' => {
+                self.bump_while(|c| c != '\n' && c != '\r');
+                TokenKind::LineComment
+            }
+            '/' if self.peek() == Some('*') => {
+                self.bump();
+                match self.rest().find("*/") {
+                    Some(index) => self.pos += index + 2,
+                    None => {
+                        self.pos = self.text.len();
+                        terminated = false;
+                    }
+                }
+                TokenKind::BlockComment
+            }
+            '\'' => {
+                terminated = self.quoted('\'', &['\'', '\\']);
+                TokenKind::String
+            }
+            'N' | 'n' | 'E' | 'e' | 'X' | 'x' | 'B' | 'b' if self.peek() == Some('\'') => {
+                self.bump();
+                terminated = self.quoted('\'', &['\'', '\\']);
+                TokenKind::String
+            }
+            // BigQuery raw strings, where backslashes escape nothing
+            'R' | 'r' if self.dialect == Dialect::BigQuery && self.peek() == Some('\'') => {
+                self.bump();
+                terminated = self.quoted('\'', &[]);
+                TokenKind::String
+            }
+            'U' | 'u' if self.peek() == Some('&') && self.peek_nth(1) == Some('\'') => {
+                self.bump();
+                self.bump();
+                terminated = self.quoted('\'', &['\'']);
+                TokenKind::String
+            }
+            'U' | 'u' if self.peek() == Some('&') && self.peek_nth(1) == Some('"') => {
+                self.bump();
+                self.bump();
+                terminated = self.quoted('"', &['"']);
+                TokenKind::QuotedIdentifier
+            }
+            '"' => {
+                terminated = self.quoted('"', &['"', '\\']);
+                TokenKind::QuotedIdentifier
+            }
+            '`' => {
+                terminated = self.quoted('`', &['`']);
+                TokenKind::QuotedIdentifier
+            }
+            '[' if self.dialect == Dialect::SqlServer => {
+                terminated = self.quoted(']', &[']']);
+                TokenKind::QuotedIdentifier
+            }
+            // PostgreSQL only, but MySQL scripts commonly use `$$` as a
+            // DELIMITER so it's the one dialect where they must stay off
+            '$' if self.dialect != Dialect::MySql && self.dollar_tag().is_some() => {
+                terminated = self.dollar_quoted();
+                TokenKind::String
+            }
+            '0'..='9' => {
+                self.number();
+                TokenKind::Number
+            }
+            '.' if self.peek().is_some_and(|c| c.is_ascii_digit()) => {
+                self.number();
+                TokenKind::Number
+            }
+            c if is_word_start(c) => {
+                self.bump_while(is_word_char);
+                TokenKind::Word
+            }
+            '(' => TokenKind::OpenParen,
+            ')' => TokenKind::CloseParen,
+            ',' => TokenKind::Comma,
+            ';' => TokenKind::Semicolon,
+            '.' => TokenKind::Dot,
+            _ => {
+                self.operator(c);
+                TokenKind::Operator
+            }
+        };
+        Token {
+            kind,
+            text: &self.text[start..self.pos],
+            start,
+            terminated,
+        }
+    }
+
+    /// Consumes up to and including the closing `quote`, treating any of
+    /// `escapes` immediately followed by `quote` as an escaped quote. Returns
+    /// false if the input ends first.
+    fn quoted(&mut self, quote: char, escapes: &[char]) -> bool {
+        while let Some(c) = self.bump() {
+            if escapes.contains(&c) && self.peek() == Some(quote) {
+                self.bump();
+                continue;
+            }
+            if c == quote {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Returns the length of the `$tag$` opening at the current position,
+    /// just after its first `$`.
+    fn dollar_tag(&self) -> Option<usize> {
+        let rest = self.rest();
+        let tag_len = rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        if rest[..tag_len].starts_with(|c: char| c.is_ascii_digit()) {
+            return None;
+        }
+        rest[tag_len..].starts_with('$').then_some(tag_len + 1)
+    }
+
+    fn dollar_quoted(&mut self) -> bool {
+        let tag_len = self.dollar_tag().unwrap();
+        let tag = &self.text[self.pos - 1..self.pos + tag_len];
+        self.pos += tag_len;
+        match self.rest().find(tag) {
+            Some(index) => {
+                self.pos += index + tag.len();
+                true
+            }
+            None => {
+                self.pos = self.text.len();
+                false
+            }
+        }
+    }
+
+    /// The rest of the line of a `COPY ... FROM stdin;` and the rows after it.
+    fn copy_data(&mut self) -> Vec<Token<'a>> {
+        let rest = self.rest();
+        let Some(line_end) = rest.find('\n') else {
+            return Vec::new();
+        };
+        // the rows start on the line after the one the statement ends
+        if !rest[..line_end].trim().is_empty() || line_end + 1 == rest.len() {
+            return Vec::new();
+        }
+        let start = self.pos;
+        self.pos += line_end + 1;
+        let line_break = Token {
+            kind: TokenKind::Whitespace,
+            text: &self.text[start..self.pos],
+            start,
+            terminated: true,
+        };
+        let start = self.pos;
+        let mut terminated = false;
+        for line in self.rest().split_inclusive('\n') {
+            let row = line.trim_end_matches(['\r', '\n']);
+            if row == "\\." {
+                self.pos += row.len();
+                terminated = true;
+                break;
+            }
+            self.pos += line.len();
+        }
+        let data = Token {
+            kind: TokenKind::CopyData,
+            text: &self.text[start..self.pos],
+            start,
+            terminated,
+        };
+        vec![line_break, data]
+    }
+
+    fn number(&mut self) {
+        if self.rest().starts_with(['x', 'X']) && self.text[..self.pos].ends_with('0') {
+            self.bump();
+            self.bump_while(|c| c.is_ascii_hexdigit());
+            return;
+        }
+        self.bump_while(|c| c.is_ascii_digit());
+        if self.peek() == Some('.') && self.peek_nth(1) != Some('.') {
+            self.bump();
+            self.bump_while(|c| c.is_ascii_digit());
+        }
+        if self.peek().is_some_and(|c| c == 'e' || c == 'E') {
+            let digit_at = match self.peek_nth(1) {
+                Some('+' | '-') => 2,
+                _ => 1,
+            };
+            if self.peek_nth(digit_at).is_some_and(|c| c.is_ascii_digit()) {
+                for _ in 0..digit_at {
+                    self.bump();
+                }
+                self.bump_while(|c| c.is_ascii_digit());
+            }
+        }
+    }
+
+    fn operator(&mut self, first: char) {
+        const OPERATOR_CHARS: &[char] = &[
+            '!', '<', '>', '=', '|', ':', '-', '~', '*', '&', '@', '^', '?', '/', '%', '+',
+        ];
+        if !OPERATOR_CHARS.contains(&first) {
+            return;
+        }
+        // stop before anything that starts a comment
+        while self.peek().is_some_and(|c| OPERATOR_CHARS.contains(&c))
+            && !self.rest().starts_with("--")
+            && !self.rest().starts_with("/*")
+        {
+            self.bump();
+        }
+    }
+}
+
+fn is_word_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_' || c == '@' || c == '$'
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
@@ -1,39 +1,711 @@
+use std::io::BufRead;
+use std::path::Path;
+
 use anyhow::Result;
 use dprint_core::configuration::ConfigKeyValue;
 use dprint_core::configuration::ConfigurationDiagnostic;
 use dprint_core::configuration::NewLineKind;
+use dprint_core::configuration::ParseConfigurationError;
 use dprint_core::configuration::RECOMMENDED_GLOBAL_CONFIGURATION;
 use dprint_core::configuration::get_unknown_property_diagnostics;
 use dprint_core::configuration::resolve_new_line_kind;
 use dprint_core::configuration::{ConfigKeyMap, GlobalConfiguration};
 use dprint_core::configuration::{get_nullable_value, get_nullable_vec, get_value};
+use dprint_core::generate_str_to_from;
+use dprint_core::plugins::CancellationToken;
 use dprint_core::plugins::CheckConfigUpdatesMessage;
 use dprint_core::plugins::ConfigChange;
 use dprint_core::plugins::FormatResult;
+use dprint_core::plugins::NullCancellationToken;
 use dprint_core::plugins::PluginInfo;
 use dprint_core::plugins::PluginResolveConfigurationResult;
 use dprint_core::plugins::SyncFormatRequest;
 use dprint_core::plugins::SyncHostFormatRequest;
 use dprint_core::plugins::SyncPluginHandler;
+#[cfg(feature = "schema")]
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use sqlformat::FormatOptions;
 use sqlformat::Indent;
-use sqlformat::QueryParams;
+
+use crate::protect::Protected;
+
+mod alias;
+mod arguments;
+mod ast;
+mod by_list;
+mod cancellation;
+mod case_expression;
+mod clauses;
+mod copy_data;
+mod corpus;
+mod cte;
+mod delimiter;
+mod diagnostics;
+mod dollar_quote;
+mod encoding;
+mod glob;
+mod grant;
+mod hints;
+mod identifiers;
+mod inline_list;
+mod join;
+mod keyword_case;
+mod keywords;
+mod lexer;
+mod lint;
+mod literal_case;
+mod literals;
+mod logical_operator;
+mod merge;
+mod parallel;
+mod plpgsql;
+mod protect;
+mod river;
+#[cfg(feature = "schema")]
+mod schema;
+mod semicolons;
+mod set_operation;
+mod snippet;
+mod spark;
+mod sqlfluff;
+mod stability;
+mod statements;
+mod stream;
+mod subquery;
+mod trace;
+mod upsert;
+mod validate;
+mod warehouse;
+mod whitespace;
+mod window;
+
+pub use cancellation::CancelledError;
+pub use corpus::CorpusFailure;
+pub use corpus::CorpusReport;
+pub use corpus::fix_corpus;
+pub use corpus::run_corpus;
+pub use diagnostics::ErrorLocation;
+pub use diagnostics::FileError;
+pub use diagnostics::ParseError;
+pub use lint::LintDiagnostic;
+pub use lint::LintError;
+pub use lint::LintRule;
+pub use literals::AlteredLiteralError;
+#[cfg(feature = "schema")]
+pub use schema::config_schema;
+pub use snippet::SnippetOptions;
+pub use stability::UnstableFormatError;
+pub use statements::StatementKind;
+pub use statements::StatementSpan;
+pub use stream::FormattedStatements;
+pub use validate::ChangedTokensError;
 
 #[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
 #[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "schema", schemars(default))]
 pub struct Configuration {
+    /// Whether to use tabs (true) or spaces (false).
     pub use_tabs: bool,
+    /// The number of characters for an indent.
     pub indent_width: u8,
+    /// The kind of newline to use.
+    #[cfg_attr(feature = "schema", schemars(with = "schema::NewLineKind"))]
     pub new_line_kind: NewLineKind,
+    /// Use ALL CAPS for reserved words.
     pub uppercase: bool,
+    /// Number of line breaks between queries.
     pub lines_between_queries: u8,
+    /// Keep the query in a single line.
     pub inline: bool,
+    /// Maximum length of an inline block.
     pub max_inline_block: usize,
+    /// Maximum length of inline arguments. If unset keep every argument in a
+    /// separate line.
     pub max_inline_arguments: Option<usize>,
+    /// Inline the argument at the top level if they would fit a line of this
+    /// length.
     pub max_inline_top_level: Option<usize>,
+    /// Consider any JOIN statement as a top level keyword instead of a reserved
+    /// keyword.
     pub joins_as_top_level: bool,
+    /// Ignore case conversion for specified strings in array.
     pub ignore_case_convert: Option<Vec<String>>,
+    /// Words to keep in the case they are written in, matched in any case, like
+    /// functions or column names that are also keywords.
+    pub case_exceptions: Vec<String>,
+    /// Fail with a parse error instead of formatting SQL with unterminated
+    /// literals or unbalanced parentheses.
+    pub strict: bool,
+    /// Format the dollar-quoted bodies of `sql` and `plpgsql` functions,
+    /// procedures and DO blocks instead of keeping them verbatim.
+    pub format_function_bodies: bool,
+    /// The SQL dialect, which affects how quotes, brackets and dollar signs are
+    /// parsed.
+    pub dialect: Dialect,
+    /// What statements are laid out from. The layout options other than the
+    /// indentation, keyword case and lines between queries only apply to the
+    /// tokens engine.
+    pub engine: Engine,
+    /// How to quote identifiers, using the dialect's quote character (double
+    /// quotes, backticks for mysql, bigquery and spark, brackets for
+    /// sqlserver).
+    pub quote_identifiers: QuoteIdentifiers,
+    /// The case of unquoted identifiers, including the schema and table parts
+    /// of qualified names. Only applied where the server compares them
+    /// case-insensitively, so identifiers are left alone in mysql and bigquery,
+    /// whose table names can be case sensitive.
+    pub identifier_case: IdentifierCase,
+    /// The case of TRUE and FALSE.
+    pub boolean_case: LiteralCase,
+    /// The case of NULL.
+    pub null_case: LiteralCase,
+    /// The case of the exponent of numbers, as in 1e5 and 1E5.
+    pub exponent_case: LiteralCase,
+    /// The case of the prefix of hexadecimal literals, as in 0xff and X'ff'.
+    /// mysql only reads a lowercase 0x, which is kept.
+    pub hex_prefix_case: LiteralCase,
+    /// Check the SQL for `SELECT *`, implicit cross joins, DELETE or UPDATE
+    /// without WHERE, and inconsistent alias styles.
+    pub lint: bool,
+    /// What the findings of `lint` do to formatting.
+    pub lint_level: LintLevel,
+    /// Debugging aid that formats the output a second time and fails if it
+    /// changes again.
+    pub verify_stable: bool,
+    /// Compare the tokens of the output to those of the input and leave the
+    /// text unchanged if formatting changed more than whitespace and case.
+    pub validate_output: bool,
+    /// Where AND and OR go when a condition is split over several lines.
+    pub logical_operator_position: LogicalOperatorPosition,
+    /// How CASE expressions that don't fit on one line are laid out.
+    pub case_style: CaseStyle,
+    /// Keep CASE expressions on one line when the line ends within this many
+    /// characters. 0 disables.
+    pub case_inline_width: usize,
+    /// The number of blank lines between common table expressions.
+    pub lines_between_ctes: u8,
+    /// Whether the body of a common table expression is indented relative to
+    /// its name.
+    pub indent_cte_body: bool,
+    /// How a subquery that starts a clause is laid out.
+    pub subquery_style: SubqueryStyle,
+    /// Keep the blank lines between statements as written, up to
+    /// `maxBlankLines`, instead of using `linesBetweenQueries`.
+    pub preserve_blank_lines: bool,
+    /// The most blank lines kept between statements when `preserveBlankLines`
+    /// is enabled.
+    pub max_blank_lines: u8,
+    /// Whether the last statement ends with a semicolon.
+    pub require_semicolons: RequireSemicolons,
+    /// The encoding formatted files are written in. Files with a byte order
+    /// mark keep it.
+    pub encoding: Encoding,
+    /// Whether to end files with a newline. When false, files keep or lack one
+    /// as written.
+    pub insert_final_newline: bool,
+    /// Strip trailing spaces and tabs from every line and replace other tabs
+    /// with the indent, including in comments and text that's otherwise kept as
+    /// written. String literals, quoted identifiers and COPY rows are left
+    /// alone.
+    pub normalize_whitespace: bool,
+    /// How window specifications that don't fit on one line are laid out.
+    pub window_style: WindowStyle,
+    /// Keep window specifications on one line when the line ends within this
+    /// many characters. 0 disables.
+    pub window_inline_width: usize,
+    /// Keep GRANT and REVOKE statements on one line when they fit within this
+    /// many characters. 0 disables.
+    pub grant_inline_width: usize,
+    /// Keep GROUP BY, ORDER BY and PARTITION BY lists with at most this many
+    /// items on one line and put longer lists one item per line. If unset
+    /// sqlformat decides by their length.
+    pub max_inline_list_items: Option<usize>,
+    /// Keep select lists whose items fit in this many characters on one line
+    /// and put longer lists one item per line. If unset maxInlineArguments
+    /// decides.
+    pub max_inline_select_list: Option<usize>,
+    /// Keep IN lists whose items fit in this many characters on one line and
+    /// put longer lists one item per line. If unset maxInlineBlock decides.
+    pub max_inline_in_list: Option<usize>,
+    /// Keep function arguments that fit in this many characters on one line and
+    /// put longer ones one argument per line. If unset maxInlineBlock decides.
+    pub max_inline_function_arguments: Option<usize>,
+    /// How the arguments of function calls broken over several lines are
+    /// indented.
+    pub function_argument_indent: FunctionArgumentIndent,
+    /// Keep VALUES tuples whose items fit in this many characters on one line
+    /// and put longer tuples one item per line. If unset maxInlineBlock
+    /// decides.
+    pub max_inline_values: Option<usize>,
+    /// Where to put the ON condition of a join.
+    pub join_condition_position: JoinConditionPosition,
+    /// Keep the column list of USING on one line.
+    pub inline_join_using: bool,
+    /// Line up the column aliases of select-list items that are each on a line
+    /// of their own.
+    pub align_aliases: bool,
+    /// Whether column aliases are written with AS.
+    pub as_keyword: AsKeyword,
+    /// How top-level clause keywords are aligned.
+    pub keyword_alignment: KeywordAlignment,
+    /// The number of blank lines before and after UNION, INTERSECT and EXCEPT.
+    pub lines_around_set_operators: u8,
+    /// Wrap each query joined by UNION, INTERSECT or EXCEPT in parentheses.
+    pub parenthesize_set_operands: bool,
+    /// Values to put in place of query parameters: an array for ? and $1 style
+    /// parameters or an object for :name style ones. Values are inserted as
+    /// written, so strings need their quotes.
+    #[cfg_attr(feature = "schema", schemars(with = "schema::Params", transform = schema::without_default))]
+    pub params: QueryParams,
+    /// Leave files as they are. Mostly useful in overrides and frameworks.
+    pub skip: bool,
+    /// Options for the files matching a pattern. Patterns without a slash match
+    /// file names and others match the end of the path, with * and ** like in
+    /// gitignore files. The last override matching a file wins.
+    #[cfg_attr(feature = "schema", schemars(with = "Vec<schema::Override>"))]
+    pub overrides: Vec<ConfigOverride>,
+}
+
+/// Configuration for the files matching one of the `files` patterns.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ConfigOverride {
+    pub files: Vec<String>,
+    pub config: Configuration,
+}
+
+impl Configuration {
+    /// The configuration for the file at `path`, from the last of `overrides`
+    /// matching it.
+    pub fn for_path(&self, path: &Path) -> &Configuration {
+        self.overrides
+            .iter()
+            .rev()
+            .find(|o| o.files.iter().any(|pattern| glob::matches(pattern, path)))
+            .map_or(self, |o| &o.config)
+    }
+
+    /// One level of indentation.
+    pub(crate) fn indent(&self) -> String {
+        if self.use_tabs {
+            "\t".to_string()
+        } else {
+            " ".repeat(self.indent_width.into())
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub enum Dialect {
+    /// Generic SQL.
+    #[serde(rename = "generic")]
+    Generic,
+    /// PostgreSQL.
+    #[serde(rename = "postgresql")]
+    PostgreSql,
+    /// MySQL and MariaDB.
+    #[serde(rename = "mysql")]
+    MySql,
+    /// Microsoft SQL Server.
+    #[serde(rename = "sqlserver")]
+    SqlServer,
+    /// Snowflake.
+    #[serde(rename = "snowflake")]
+    Snowflake,
+    /// Google BigQuery.
+    #[serde(rename = "bigquery")]
+    BigQuery,
+    /// Spark SQL and HiveQL.
+    #[serde(rename = "spark")]
+    Spark,
+}
+
+generate_str_to_from![
+    Dialect,
+    [Generic, "generic"],
+    [PostgreSql, "postgresql"],
+    [MySql, "mysql"],
+    [SqlServer, "sqlserver"],
+    [Snowflake, "snowflake"],
+    [BigQuery, "bigquery"],
+    [Spark, "spark"]
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub enum QuoteIdentifiers {
+    /// Leave identifiers quoted as written.
+    #[serde(rename = "preserve")]
+    Preserve,
+    /// Quote every identifier that isn't a keyword or function name.
+    #[serde(rename = "always")]
+    Always,
+    /// Remove quotes from identifiers that don't need them.
+    #[serde(rename = "onlyWhenNeeded")]
+    OnlyWhenNeeded,
+}
+
+generate_str_to_from![
+    QuoteIdentifiers,
+    [Preserve, "preserve"],
+    [Always, "always"],
+    [OnlyWhenNeeded, "onlyWhenNeeded"]
+];
+
+/// What statements are laid out from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub enum Engine {
+    /// The tokens of the statement, read by sqlformat.
+    #[serde(rename = "tokens")]
+    Tokens,
+    /// The syntax tree sqlparser parses the statement into, falling back to the
+    /// tokens for statements with comments, that don't parse, or that sqlparser
+    /// would write differently.
+    #[serde(rename = "ast")]
+    Ast,
+}
+
+generate_str_to_from![Engine, [Tokens, "tokens"], [Ast, "ast"]];
+
+/// The case of unquoted identifiers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub enum IdentifierCase {
+    /// Leave identifiers in the case they're written in.
+    #[serde(rename = "preserve")]
+    Preserve,
+    /// Lowercase unquoted identifiers.
+    #[serde(rename = "lower")]
+    Lower,
+    /// Uppercase unquoted identifiers.
+    #[serde(rename = "upper")]
+    Upper,
+}
+
+generate_str_to_from![
+    IdentifierCase,
+    [Preserve, "preserve"],
+    [Lower, "lower"],
+    [Upper, "upper"]
+];
+
+/// The case of a kind of literal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub enum LiteralCase {
+    /// Leave literals in the case they're written in, or for TRUE and NULL,
+    /// the case `uppercase` gives keywords.
+    #[serde(rename = "preserve")]
+    Preserve,
+    /// Lowercase them.
+    #[serde(rename = "lower")]
+    Lower,
+    /// Uppercase them.
+    #[serde(rename = "upper")]
+    Upper,
+}
+
+generate_str_to_from![
+    LiteralCase,
+    [Preserve, "preserve"],
+    [Lower, "lower"],
+    [Upper, "upper"]
+];
+
+/// What the findings of `lint` do to formatting.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub enum LintLevel {
+    /// Format as usual. dprint has no channel for warnings, so the findings
+    /// only reach hosts calling `format_text_with_lints`.
+    #[serde(rename = "warn")]
+    Warn,
+    /// Fail formatting with the findings.
+    #[serde(rename = "error")]
+    Error,
+}
+
+generate_str_to_from![LintLevel, [Warn, "warn"], [Error, "error"]];
+
+/// Where `AND` and `OR` go when a condition is split over several lines.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub enum LogicalOperatorPosition {
+    /// Start each continued line with the operator.
+    #[serde(rename = "leadingNewline")]
+    LeadingNewline,
+    /// End the line before each continued line with the operator.
+    #[serde(rename = "trailingNewline")]
+    TrailingNewline,
+    /// Keep the whole condition on one line.
+    #[serde(rename = "inline")]
+    Inline,
+}
+
+generate_str_to_from![
+    LogicalOperatorPosition,
+    [LeadingNewline, "leadingNewline"],
+    [TrailingNewline, "trailingNewline"],
+    [Inline, "inline"]
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub enum CaseStyle {
+    /// Keep sqlformat's layout.
+    #[serde(rename = "default")]
+    Default,
+    /// Put each WHEN/ELSE branch on its own line with END aligned to CASE.
+    #[serde(rename = "aligned")]
+    Aligned,
+}
+
+generate_str_to_from![CaseStyle, [Default, "default"], [Aligned, "aligned"]];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub enum SubqueryStyle {
+    /// Put the opening parenthesis on the line below the clause keyword.
+    #[serde(rename = "packed")]
+    Packed,
+    /// Open the subquery on the clause keyword's line and align the closing
+    /// parenthesis with the keyword.
+    #[serde(rename = "indented")]
+    Indented,
+}
+
+generate_str_to_from![SubqueryStyle, [Packed, "packed"], [Indented, "indented"]];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub enum RequireSemicolons {
+    /// Terminate the last statement with a semicolon.
+    #[serde(rename = "always")]
+    Always,
+    /// Remove the semicolon after a file's only statement.
+    #[serde(rename = "never")]
+    Never,
+    /// Leave semicolons as written.
+    #[serde(rename = "preserve")]
+    Preserve,
+}
+
+generate_str_to_from![
+    RequireSemicolons,
+    [Always, "always"],
+    [Never, "never"],
+    [Preserve, "preserve"]
+];
+
+/// The encoding files are written back in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub enum Encoding {
+    /// The encoding the file was read in, detected from its byte order mark or
+    /// contents.
+    #[serde(rename = "auto")]
+    Auto,
+    /// UTF-8.
+    #[serde(rename = "utf-8")]
+    Utf8,
+    /// UTF-16, little endian.
+    #[serde(rename = "utf-16le")]
+    Utf16Le,
+    /// UTF-16, big endian.
+    #[serde(rename = "utf-16be")]
+    Utf16Be,
+    /// ISO-8859-1.
+    #[serde(rename = "latin1")]
+    Latin1,
+}
+
+generate_str_to_from![
+    Encoding,
+    [Auto, "auto"],
+    [Utf8, "utf-8"],
+    [Utf16Le, "utf-16le"],
+    [Utf16Be, "utf-16be"],
+    [Latin1, "latin1"]
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub enum FunctionArgumentIndent {
+    /// Put each argument on its own line, one indent deeper than the call.
+    #[serde(rename = "continuation")]
+    Continuation,
+    /// Keep the first argument after the opening parenthesis and line the
+    /// others up under it.
+    #[serde(rename = "aligned")]
+    Aligned,
+}
+
+generate_str_to_from![
+    FunctionArgumentIndent,
+    [Continuation, "continuation"],
+    [Aligned, "aligned"]
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub enum WindowStyle {
+    /// Keep sqlformat's layout.
+    #[serde(rename = "default")]
+    Default,
+    /// Put each of PARTITION BY, ORDER BY and the frame clause on its own line.
+    #[serde(rename = "clausePerLine")]
+    ClausePerLine,
+}
+
+generate_str_to_from![
+    WindowStyle,
+    [Default, "default"],
+    [ClausePerLine, "clausePerLine"]
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub enum JoinConditionPosition {
+    /// Keep the layout of the formatter.
+    #[serde(rename = "default")]
+    Default,
+    /// Keep ON on the line of the joined table and indent the lines of the
+    /// condition after it.
+    #[serde(rename = "sameLine")]
+    SameLine,
+    /// Put ON on an indented line after the joined table.
+    #[serde(rename = "nextLine")]
+    NextLine,
+}
+
+generate_str_to_from![
+    JoinConditionPosition,
+    [Default, "default"],
+    [SameLine, "sameLine"],
+    [NextLine, "nextLine"]
+];
+
+/// Whether column aliases are written with `AS`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub enum AsKeyword {
+    /// Add AS before every column alias.
+    #[serde(rename = "always")]
+    Always,
+    /// Remove AS before column aliases, except those that are keywords.
+    #[serde(rename = "never")]
+    Never,
+    /// Leave AS as written.
+    #[serde(rename = "preserve")]
+    Preserve,
+}
+
+generate_str_to_from![
+    AsKeyword,
+    [Always, "always"],
+    [Never, "never"],
+    [Preserve, "preserve"]
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub enum KeywordAlignment {
+    /// Start clause keywords at the left of the line with their clause indented
+    /// below them.
+    #[serde(rename = "left")]
+    Left,
+    /// Right-align top-level clause keywords so their clauses start at a common
+    /// column.
+    #[serde(rename = "river")]
+    River,
+}
+
+generate_str_to_from![KeywordAlignment, [Left, "left"], [River, "river"]];
+
+/// Values for the parameters of a query, like `?`, `$1` or `:name`.
+///
+/// With any set, the statements of a text are formatted as a whole, so
+/// `preserveBlankLines` has no effect.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QueryParams {
+    #[default]
+    None,
+    /// Values for `?` in order, or for `$1`, `?1` and the like by number.
+    Indexed(Vec<String>),
+    /// Values for `:name`, `@name` and `$name` by name.
+    Named(Vec<(String, String)>),
+}
+
+impl From<&QueryParams> for sqlformat::QueryParams {
+    fn from(params: &QueryParams) -> Self {
+        match params {
+            QueryParams::None => sqlformat::QueryParams::None,
+            QueryParams::Indexed(values) => sqlformat::QueryParams::Indexed(values.clone()),
+            QueryParams::Named(values) => sqlformat::QueryParams::Named(values.clone()),
+        }
+    }
+}
+
+/// A bundle of defaults selected with the `preset` key. Keys set explicitly
+/// still take precedence.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub enum Preset {
+    /// The sqlformat defaults.
+    #[serde(rename = "default")]
+    Default,
+    /// Keeps anything that fits within 100 characters on one line.
+    #[serde(rename = "compact")]
+    Compact,
+    /// Breaks nearly every block onto its own lines, with joins as top level
+    /// keywords and a blank line between queries.
+    #[serde(rename = "expanded")]
+    Expanded,
+    /// Approximates sqlfluff's default layout: uppercase keywords, joins as top
+    /// level keywords and argument lists inlined up to 80 characters.
+    #[serde(rename = "sqlfluff")]
+    Sqlfluff,
+}
+
+generate_str_to_from![
+    Preset,
+    [Default, "default"],
+    [Compact, "compact"],
+    [Expanded, "expanded"],
+    [Sqlfluff, "sqlfluff"]
+];
+
+impl Preset {
+    fn format_options(self) -> FormatOptions<'static> {
+        let defaults = FormatOptions {
+            uppercase: Some(false),
+            ..Default::default()
+        };
+        match self {
+            Preset::Default => defaults,
+            Preset::Compact => FormatOptions {
+                max_inline_block: 100,
+                max_inline_arguments: Some(100),
+                max_inline_top_level: Some(100),
+                ..defaults
+            },
+            Preset::Expanded => FormatOptions {
+                lines_between_queries: 2,
+                max_inline_block: 20,
+                joins_as_top_level: true,
+                ..defaults
+            },
+            Preset::Sqlfluff => FormatOptions {
+                uppercase: Some(true),
+                max_inline_arguments: Some(80),
+                joins_as_top_level: true,
+                ..defaults
+            },
+        }
+    }
 }
 
 impl<'a> From<&'a Configuration> for FormatOptions<'a> {
@@ -55,7 +727,14 @@ impl<'a> From<&'a Configuration> for FormatOptions<'a> {
                 .ignore_case_convert
                 .as_ref()
                 .map(|v| v.iter().map(|s| s.as_str()).collect()),
-            ..Default::default()
+            dialect: match config.dialect {
+                // for its brackets, which these index arrays with
+                Dialect::PostgreSql | Dialect::Snowflake | Dialect::BigQuery | Dialect::Spark => {
+                    sqlformat::Dialect::PostgreSql
+                }
+                Dialect::SqlServer => sqlformat::Dialect::SQLServer,
+                Dialect::Generic | Dialect::MySql => sqlformat::Dialect::Generic,
+            },
         }
     }
 }
@@ -68,34 +747,934 @@ impl Default for Configuration {
     }
 }
 
+/// Formats `text`, returning `None` if it's formatted already.
+///
+/// Telling that takes formatting it in full: sqlformat decides the layout of
+/// each statement from all of its tokens, so no cheaper pass over the text
+/// can say it would come out the same. Skipping files that haven't changed
+/// between runs is left to dprint's `incremental` option.
 pub fn format_text(text: &str, config: &Configuration) -> Result<Option<String>> {
-    let input_text = text;
-    let text = sqlformat::format(text, &QueryParams::None, &config.into());
+    format_text_cancellable(text, config, &NullCancellationToken)
+}
 
-    // ensure ends with newline
-    let text = if !text.ends_with('\n') {
-        let mut text = text;
-        text.push('\n');
-        text
+/// Like `format_text`, but fails with a `CancelledError` once `token` is
+/// cancelled, which is checked between the statements formatted one at a time
+/// and once formatting is done.
+pub fn format_text_cancellable(
+    text: &str,
+    config: &Configuration,
+    token: &dyn CancellationToken,
+) -> Result<Option<String>> {
+    let formatted = format_checked(text, config, token)?;
+    if formatted == text {
+        Ok(None)
     } else {
-        text
+        Ok(Some(formatted))
+    }
+}
+
+/// Formats SQL taken from another file, like a string in source code, so it
+/// can be put back where it came from.
+pub fn format_snippet(
+    text: &str,
+    config: &Configuration,
+    options: SnippetOptions,
+) -> Result<Option<String>> {
+    let dialect = lexer_dialect(text, config);
+    let mut formatted = format_checked(
+        &snippet::dedent(text, dialect),
+        config,
+        &NullCancellationToken,
+    )?;
+    if options.skip_trailing_newline {
+        let trimmed = formatted.trim_end_matches(['\r', '\n']).len();
+        formatted.truncate(trimmed);
+    }
+    let formatted = snippet::indent(&formatted, options.indent_offset, config, dialect);
+    if formatted == text {
+        Ok(None)
+    } else {
+        Ok(Some(formatted))
+    }
+}
+
+/// Formats `text` with `params` put in place of its parameters, like for a
+/// preview of the query that runs.
+pub fn format_text_with_params(
+    text: &str,
+    params: QueryParams,
+    config: &Configuration,
+) -> Result<Option<String>> {
+    let config = Configuration {
+        params,
+        ..config.clone()
     };
+    format_text(text, &config)
+}
+
+/// Formats the statements read from `reader` one at a time, without reading
+/// the whole of it into memory. `DELIMITER` directives are not followed, and
+/// `newLineKind: auto` picks the line ending of each statement on its own.
+pub fn format_statements<R: BufRead>(reader: R, config: &Configuration) -> FormattedStatements<R> {
+    FormattedStatements::new(reader, config)
+}
+
+/// Formats the contents of a file, which may start with a byte order mark or
+/// be UTF-16 or latin-1 encoded.
+pub fn format_bytes(bytes: &[u8], config: &Configuration) -> Result<Option<Vec<u8>>> {
+    format_bytes_cancellable(bytes, config, &NullCancellationToken)
+}
+
+fn format_bytes_cancellable(
+    bytes: &[u8],
+    config: &Configuration,
+    token: &dyn CancellationToken,
+) -> Result<Option<Vec<u8>>> {
+    let decoded = encoding::decode(bytes)?;
+    let encoding = match config.encoding {
+        Encoding::Auto => decoded.encoding,
+        encoding => encoding,
+    };
+    // UTF-16 can't be told apart reliably without one
+    let bom = decoded.bom
+        || (encoding != decoded.encoding
+            && matches!(encoding, Encoding::Utf16Le | Encoding::Utf16Be));
+    let text = format_text_cancellable(&decoded.text, config, token)?.unwrap_or(decoded.text);
+    let formatted = encoding::encode(&text, encoding, bom)?;
+    if formatted == bytes {
+        Ok(None)
+    } else {
+        Ok(Some(formatted))
+    }
+}
+
+/// Formats the contents of the file at `path` with the configuration for it,
+/// returning errors that say which file and statement they're about. Stops
+/// between statements once `token` is cancelled.
+pub fn format_file(
+    path: &Path,
+    bytes: &[u8],
+    config: &Configuration,
+    token: &dyn CancellationToken,
+) -> Result<Option<Vec<u8>>> {
+    let config = config.for_path(path);
+    if config.skip {
+        return Ok(None);
+    }
+    format_bytes_cancellable(bytes, config, token).map_err(|error| {
+        if error.is::<CancelledError>() {
+            return error;
+        }
+        let text = encoding::decode(bytes).ok().map(|decoded| decoded.text);
+        let dialect = text
+            .as_deref()
+            .map_or(config.dialect, |text| lexer_dialect(text, config));
+        FileError::new(path, text.as_deref(), dialect, error).into()
+    })
+}
+
+/// Formats `text` twice and reports where the second pass changed the output
+/// of the first.
+pub fn verify_stable(text: &str, config: &Configuration) -> Result<()> {
+    let token = &NullCancellationToken;
+    let formatted = format_output(text, config, token)?;
+    stability::compare(&formatted, &format_output(&formatted, config, token)?)?;
+    Ok(())
+}
+
+/// Formats `text` and reports where the output differs from it in more than
+/// whitespace, case and the changes the configuration asks for.
+pub fn validate_output(text: &str, config: &Configuration) -> Result<()> {
+    let formatted = format_output(text, config, &NullCancellationToken)?;
+    validate::compare(text, &formatted, config, lexer_dialect(text, config))?;
+    Ok(())
+}
+
+/// Formats `text`, verifying the output is stable and keeps the tokens of the
+/// input when configured to.
+fn format_checked(
+    text: &str,
+    config: &Configuration,
+    token: &dyn CancellationToken,
+) -> Result<String> {
+    Ok(format_validated(text, config, token)?.0)
+}
+
+/// Like `format_checked`, but also returns the first token formatting would
+/// have changed when `validateOutput` kept `text` as it is.
+fn format_validated(
+    text: &str,
+    config: &Configuration,
+    token: &dyn CancellationToken,
+) -> Result<(String, Option<ChangedTokensError>)> {
+    let formatted = format_output(text, config, token)?;
+    if config.verify_stable {
+        stability::compare(&formatted, &format_output(&formatted, config, token)?)?;
+    }
+    let dialect = lexer_dialect(text, config);
+    // parameter values replace tokens of the input
+    if config.validate_output
+        && config.params == QueryParams::None
+        && let Err(error) = validate::compare(text, &formatted, config, dialect)
+    {
+        return Ok((text.to_string(), Some(error)));
+    }
+    Ok((whitespace::normalize(&formatted, config, dialect), None))
+}
+
+fn format_output(
+    input: &str,
+    config: &Configuration,
+    token: &dyn CancellationToken,
+) -> Result<String> {
+    if config.strict {
+        diagnostics::check_syntax(input, lexer_dialect(input, config))?;
+    }
+    if config.lint && config.lint_level == LintLevel::Error {
+        let findings = lint_text(input, config);
+        if !findings.is_empty() {
+            return Err(LintError(findings).into());
+        }
+    }
+    // custom delimiters decide for themselves what ends a statement
+    let mut text = if delimiter::has_directives(input, config.dialect) {
+        format_script(input, config, token)
+    } else {
+        let text = semicolons::normalize(input, config.require_semicolons, config.dialect);
+        format_script(&text, config, token)
+    };
+    // the statements after the check that found it were left out
+    cancellation::check(token)?;
+
+    // ensure ends with newline, unless the input deliberately doesn't
+    let final_newline = config.insert_final_newline || input.ends_with('\n');
+    if final_newline && !text.ends_with('\n') {
+        text.push('\n');
+    }
 
     // newline
-    let text = if resolve_new_line_kind(&text, config.new_line_kind) == "\n" {
-        text.replace("\r\n", "\n")
+    let new_line = match config.new_line_kind {
+        NewLineKind::Auto => dominant_new_line(input),
+        kind => resolve_new_line_kind(&text, kind),
+    };
+    let dialect = lexer_dialect(input, config);
+    let text = literals::replace_new_lines(&text, new_line, config, dialect);
+    // parameter values are new literals
+    if config.params == QueryParams::None {
+        literals::verify(input, &text, config, dialect)?;
+    }
+    Ok(text)
+}
+
+/// The line ending used by most lines of `text`, preferring `\n` when there
+/// are none or as many of each.
+fn dominant_new_line(text: &str) -> &'static str {
+    let crlf = text.matches("\r\n").count();
+    let lf = text.matches('\n').count() - crlf;
+    if crlf > lf { "\r\n" } else { "\n" }
+}
+
+/// Formats `text` and traces printing the result along with the layout
+/// decisions made for each statement, for the trace analyzer of
+/// dprint-development.
+#[cfg(feature = "tracing")]
+pub fn trace_file(
+    text: &str,
+    config: &Configuration,
+) -> Result<dprint_core::formatting::TracingResult> {
+    let formatted = format_checked(text, config, &NullCancellationToken)?;
+    Ok(trace::trace(
+        text,
+        &formatted,
+        config,
+        lexer_dialect(text, config),
+    ))
+}
+
+/// Like `format_text`, but also returns the findings of `lint` when it's
+/// enabled, for hosts that can show them as warnings.
+pub fn format_text_with_lints(
+    text: &str,
+    config: &Configuration,
+) -> Result<(Option<String>, Vec<LintDiagnostic>)> {
+    let formatted = format_text(text, config)?;
+    let findings = if config.lint {
+        lint_text(text, config)
     } else {
-        // lazy
-        text.replace("\r\n", "\n").replace("\n", "\r\n")
+        Vec::new()
     };
+    Ok((formatted, findings))
+}
 
-    if text == input_text {
-        Ok(None)
+/// Like `format_text`, but also returns where formatting would have changed
+/// more than whitespace and case when `validateOutput` left `text` unchanged,
+/// for hosts that can show it as a warning.
+pub fn format_text_with_validation(
+    text: &str,
+    config: &Configuration,
+) -> Result<(Option<String>, Option<ChangedTokensError>)> {
+    let (formatted, error) = format_validated(text, config, &NullCancellationToken)?;
+    let formatted = (formatted != text).then_some(formatted);
+    Ok((formatted, error))
+}
+
+/// The configuration keys and values for the rules of the `.sqlfluff` file
+/// `text`, and descriptions of the rules that have no equivalent. For hosts
+/// that read the file themselves, like those that can't read files or that
+/// resolve its path against their own configuration file.
+pub fn sqlfluff_options(text: &str) -> (ConfigKeyMap, Vec<String>) {
+    sqlfluff::options(text)
+}
+
+/// Checks `text` for risky or inconsistent SQL without formatting it.
+pub fn lint_text(text: &str, config: &Configuration) -> Vec<LintDiagnostic> {
+    lint::lint(text, lexer_dialect(text, config))
+}
+
+/// Splits `text` into its statements the way formatting does, including
+/// those terminated by the delimiters of MySQL `DELIMITER` directives.
+pub fn split_statements(text: &str, dialect: Dialect) -> Vec<StatementSpan> {
+    statements::spans(text, dialect)
+}
+
+/// The dialect to tokenize the whole of `text` with, which is MySQL when it
+/// uses `DELIMITER` directives regardless of the configured dialect.
+fn lexer_dialect(text: &str, config: &Configuration) -> Dialect {
+    if delimiter::has_directives(text, config.dialect) {
+        Dialect::MySql
+    } else {
+        config.dialect
+    }
+}
+
+/// Formats a part of a statement as statements of its own.
+pub(crate) fn format_sql(text: &str, config: &Configuration) -> String {
+    format_script(text, config, &NullCancellationToken)
+}
+
+/// Formats `text`, leaving out the statements after `token` is cancelled.
+fn format_script(text: &str, config: &Configuration, token: &dyn CancellationToken) -> String {
+    let Some(chunks) = delimiter::split(text, config.dialect) else {
+        return format_statement_list(text, config, config.dialect, token);
+    };
+
+    let statement_separator = "\n".repeat(config.lines_between_queries.max(1).into());
+    let mut formatted = String::new();
+    let mut after_statement = false;
+    for chunk in chunks {
+        if !formatted.is_empty() {
+            formatted.push_str(if after_statement {
+                &statement_separator
+            } else {
+                "\n"
+            });
+        }
+        after_statement = false;
+        match chunk {
+            delimiter::Chunk::Directive(directive) => formatted.push_str(directive),
+            delimiter::Chunk::Statements(text) => {
+                formatted.push_str(&format_statement_list(text, config, Dialect::MySql, token));
+            }
+            delimiter::Chunk::Statement { text, delimiter } => {
+                formatted.push_str(&format_statement_list(text, config, Dialect::MySql, token));
+                formatted.push_str(delimiter.unwrap_or_default());
+                after_statement = true;
+            }
+        }
+    }
+    formatted
+}
+
+fn format_statement_list(
+    text: &str,
+    config: &Configuration,
+    dialect: Dialect,
+    token: &dyn CancellationToken,
+) -> String {
+    // parameters are numbered across the whole text
+    if config.params != QueryParams::None {
+        return format_sqlformat_input(text, config, dialect);
+    }
+    let format_unless_cancelled = |text: &str| {
+        if token.is_cancelled() {
+            String::new()
+        } else {
+            format_sqlformat_input(text, config, dialect)
+        }
+    };
+    if !config.preserve_blank_lines {
+        let pieces = statements::pieces(text, dialect);
+        if pieces.len() < 2 {
+            return format_sqlformat_input(text, config, dialect);
+        }
+        let formatted = parallel::map(&pieces, |piece| format_unless_cancelled(piece));
+        // sqlformat puts statements on lines of their own either way
+        return formatted.join(&"\n".repeat(config.lines_between_queries.max(1).into()));
+    }
+    let statements = statements::split(text, dialect);
+    let formatted_statements = parallel::map(&statements, |statement| {
+        format_unless_cancelled(statement.text)
+    });
+    let mut formatted = String::new();
+    for (statement, formatted_statement) in statements.iter().zip(formatted_statements) {
+        if !formatted.is_empty() {
+            let blank_lines = statement
+                .blank_lines_before
+                .min(config.max_blank_lines.into());
+            formatted.push_str(&"\n".repeat(blank_lines + 1));
+        }
+        formatted.push_str(&formatted_statement);
+    }
+    formatted
+}
+
+fn format_sqlformat_input(text: &str, config: &Configuration, dialect: Dialect) -> String {
+    if let Some((statement, data, rest)) = copy_data::split(text, dialect) {
+        let mut formatted = format_sqlformat_input(statement, config, dialect);
+        formatted.push('\n');
+        formatted.push_str(data);
+        if !rest.trim().is_empty() {
+            formatted.push_str(&"\n".repeat(config.lines_between_queries.max(1).into()));
+            formatted.push_str(&format_sqlformat_input(rest, config, dialect));
+        }
+        return formatted;
+    }
+    // parameters are put in place by sqlformat
+    if config.engine == Engine::Ast && config.params == QueryParams::None {
+        return ast::format(text, config, dialect, |statement| {
+            format_tokens(statement, config, dialect)
+        });
+    }
+    format_tokens(text, config, dialect)
+}
+
+fn format_tokens(text: &str, config: &Configuration, dialect: Dialect) -> String {
+    let text = set_operation::parenthesize(text, config, dialect);
+    let text = identifiers::normalize_quotes(&text, config.quote_identifiers, dialect);
+    // after unquoting, so identifiers unquoted get the same case on a second run
+    let text = identifiers::change_case(&text, config, dialect);
+    let text = keyword_case::uppercase_compared(&text, dialect);
+    let mut protected = Protected::default();
+    let text = merge::protect(&text, config, dialect, &mut protected);
+    let text = grant::protect(&text, config, dialect, &mut protected);
+    let text = cte::protect(&text, config, dialect, &mut protected);
+    let text = dollar_quote::protect(&text, config, dialect, &mut protected);
+    let text = literals::protect(&text, dialect, &mut protected);
+    let text = warehouse::protect(&text, dialect, &mut protected);
+    let text = spark::protect(&text, dialect, &mut protected);
+    let (text, clauses) = clauses::hide(&text, dialect);
+    let mut options: FormatOptions = config.into();
+    let exceptions = keyword_case::exceptions(&text, &config.case_exceptions, dialect);
+    if !exceptions.is_empty() {
+        let mut ignored = options.ignore_case_convert.unwrap_or_default();
+        ignored.extend(exceptions.iter().map(String::as_str));
+        options.ignore_case_convert = Some(ignored);
+    }
+    let (text, attached) = hints::detach(&text, dialect);
+    let formatted = sqlformat::format(&text, &(&config.params).into(), &options);
+    let formatted = hints::attach(formatted, &attached, dialect);
+    let formatted = clauses::restore(formatted, &clauses, dialect);
+    let formatted = upsert::layout(formatted, dialect);
+    let formatted = warehouse::tighten_casts(formatted, dialect);
+    let formatted = case_expression::layout(formatted, config, dialect);
+    let formatted = window::layout(formatted, config, dialect);
+    let formatted = by_list::layout(formatted, config, dialect);
+    let formatted = join::layout(formatted, config, dialect);
+    let formatted =
+        logical_operator::reposition(formatted, config.logical_operator_position, dialect);
+    let formatted = subquery::layout(formatted, config, dialect);
+    let formatted = protected.restore(&formatted);
+    let formatted = literal_case::change_case(formatted, config, dialect);
+    // after restoring, so the lists are measured as written
+    let formatted = inline_list::layout(formatted, config, dialect);
+    let formatted = set_operation::layout(formatted, config, dialect);
+    // after restoring, so the aliases line up with the text as written
+    let formatted = alias::layout(formatted, config, dialect);
+    let formatted = river::layout(formatted, config, dialect);
+    // last, so the arguments line up with the text as laid out
+    arguments::align(formatted, config, dialect)
+}
+
+/// Like `get_nullable_value`, but uses `default` when the key is missing so
+/// that an explicit `null` can still unset a preset's value.
+fn get_nullable_value_or<T>(
+    config: &mut ConfigKeyMap,
+    key: &str,
+    default: Option<T>,
+    diagnostics: &mut Vec<ConfigurationDiagnostic>,
+) -> Option<T>
+where
+    T: std::str::FromStr,
+    <T as std::str::FromStr>::Err: std::fmt::Display,
+{
+    if config.contains_key(key) {
+        get_nullable_value(config, key, diagnostics)
+    } else {
+        default
+    }
+}
+
+/// Resolves each of `overrides` from the base configuration with the keys of
+/// the override replacing its own.
+fn resolve_overrides(
+    value: ConfigKeyValue,
+    config: &ConfigKeyMap,
+    global_config: &GlobalConfiguration,
+    diagnostics: &mut Vec<ConfigurationDiagnostic>,
+) -> Vec<ConfigOverride> {
+    let ConfigKeyValue::Array(values) = value else {
+        diagnostics.push(ConfigurationDiagnostic {
+            property_name: "overrides".to_string(),
+            message: "Expected an array of objects.".to_string(),
+        });
+        return Vec::new();
+    };
+    let mut overrides = Vec::new();
+    for (i, value) in values.into_iter().enumerate() {
+        let property_name = format!("overrides[{i}]");
+        let ConfigKeyValue::Object(mut fragment) = value else {
+            diagnostics.push(ConfigurationDiagnostic {
+                property_name,
+                message: "Expected an object.".to_string(),
+            });
+            continue;
+        };
+        let files = match fragment.shift_remove("files") {
+            Some(ConfigKeyValue::String(pattern)) => Some(vec![pattern]),
+            Some(ConfigKeyValue::Array(patterns)) => patterns
+                .into_iter()
+                .map(|pattern| match pattern {
+                    ConfigKeyValue::String(pattern) => Some(pattern),
+                    _ => None,
+                })
+                .collect(),
+            _ => None,
+        };
+        let Some(files) = files else {
+            diagnostics.push(ConfigurationDiagnostic {
+                property_name: format!("{property_name}.files"),
+                message: "Expected a pattern or an array of patterns.".to_string(),
+            });
+            continue;
+        };
+
+        overrides.push(resolve_override(
+            files,
+            fragment,
+            &property_name,
+            config,
+            global_config,
+            diagnostics,
+        ));
+    }
+    overrides
+}
+
+/// The file patterns of the migrations of each kind, across migration tools.
+const MIGRATION_FILES: &[(&str, &[&str])] = &[
+    // Flyway versioned migrations, and those of sqlx, golang-migrate, dbmate
+    // and the like split into files per direction
+    ("up", &["V*__*.sql", "*.up.sql"]),
+    // Flyway undo migrations
+    ("down", &["U*__*.sql", "*.down.sql"]),
+    // Flyway repeatable migrations
+    ("repeatable", &["R__*.sql"]),
+];
+
+/// Resolves the options of `frameworks`, keyed by the kind of migration they
+/// apply to, into overrides for the files of that kind.
+fn resolve_frameworks(
+    value: ConfigKeyValue,
+    config: &ConfigKeyMap,
+    global_config: &GlobalConfiguration,
+    diagnostics: &mut Vec<ConfigurationDiagnostic>,
+) -> Vec<ConfigOverride> {
+    let ConfigKeyValue::Object(values) = value else {
+        diagnostics.push(ConfigurationDiagnostic {
+            property_name: "frameworks".to_string(),
+            message: "Expected an object.".to_string(),
+        });
+        return Vec::new();
+    };
+    let mut overrides = Vec::new();
+    for (kind, value) in values {
+        let property_name = format!("frameworks.{kind}");
+        let Some((_, files)) = MIGRATION_FILES.iter().find(|(name, _)| *name == kind) else {
+            diagnostics.push(ConfigurationDiagnostic {
+                property_name,
+                message: "Expected one of up, down or repeatable.".to_string(),
+            });
+            continue;
+        };
+        let ConfigKeyValue::Object(fragment) = value else {
+            diagnostics.push(ConfigurationDiagnostic {
+                property_name,
+                message: "Expected an object.".to_string(),
+            });
+            continue;
+        };
+        let files = files.iter().map(|pattern| pattern.to_string()).collect();
+        overrides.push(resolve_override(
+            files,
+            fragment,
+            &property_name,
+            config,
+            global_config,
+            diagnostics,
+        ));
+    }
+    overrides
+}
+
+/// Resolves the base configuration with the keys of `fragment` replacing its
+/// own.
+fn resolve_override(
+    files: Vec<String>,
+    fragment: ConfigKeyMap,
+    property_name: &str,
+    config: &ConfigKeyMap,
+    global_config: &GlobalConfiguration,
+    diagnostics: &mut Vec<ConfigurationDiagnostic>,
+) -> ConfigOverride {
+    let keys: Vec<_> = fragment.keys().cloned().collect();
+    let mut merged = config.clone();
+    merged.extend(fragment);
+    let mut override_diagnostics = Vec::new();
+    let config = resolve_configuration(merged, global_config, &mut override_diagnostics);
+    // the ones for keys of the base configuration were already reported
+    diagnostics.extend(
+        override_diagnostics
+            .into_iter()
+            .filter(|diagnostic| keys.contains(&diagnostic.property_name))
+            .map(|diagnostic| ConfigurationDiagnostic {
+                property_name: format!("{property_name}.{}", diagnostic.property_name),
+                message: diagnostic.message,
+            }),
+    );
+    ConfigOverride { files, config }
+}
+
+/// Adds the options read from the `.sqlfluff` file at `path` to `config`,
+/// except the ones it already has.
+fn import_sqlfluff(
+    path: ConfigKeyValue,
+    config: &mut ConfigKeyMap,
+    diagnostics: &mut Vec<ConfigurationDiagnostic>,
+) {
+    let diagnostic = |message| ConfigurationDiagnostic {
+        property_name: "sqlfluffConfigPath".to_string(),
+        message,
+    };
+    let ConfigKeyValue::String(path) = path else {
+        diagnostics.push(diagnostic("Expected a string.".to_string()));
+        return;
+    };
+    if cfg!(target_arch = "wasm32") {
+        diagnostics.push(diagnostic(
+            "Not supported by the WebAssembly plugin, which can't read files.".to_string(),
+        ));
+        return;
+    }
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(err) => {
+            diagnostics.push(diagnostic(format!("Could not read {path}: {err}")));
+            return;
+        }
+    };
+    let (options, unsupported) = sqlfluff_options(&text);
+    for (key, value) in options {
+        config.entry(key).or_insert(value);
+    }
+    diagnostics.extend(
+        unsupported
+            .into_iter()
+            .map(|rule| diagnostic(format!("The sqlfluff setting for {rule} is not supported."))),
+    );
+}
+
+/// Reads `params` as an array of values for indexed parameters or an object
+/// with values for named ones.
+fn resolve_params(
+    params: ConfigKeyValue,
+    diagnostics: &mut Vec<ConfigurationDiagnostic>,
+) -> QueryParams {
+    let mut values = Vec::new();
+    let mut names = Vec::new();
+    let indexed = matches!(params, ConfigKeyValue::Array(_));
+    match params {
+        ConfigKeyValue::Array(params) => values.extend(params),
+        ConfigKeyValue::Object(params) => {
+            for (name, value) in params {
+                names.push(name);
+                values.push(value);
+            }
+        }
+        ConfigKeyValue::Null => return QueryParams::None,
+        _ => {
+            diagnostics.push(ConfigurationDiagnostic {
+                property_name: "params".to_string(),
+                message: "Expected an array or an object.".to_string(),
+            });
+            return QueryParams::None;
+        }
+    }
+    let values: Option<Vec<_>> = values
+        .into_iter()
+        .map(|value| match value {
+            ConfigKeyValue::String(value) => Some(value),
+            ConfigKeyValue::Number(value) => Some(value.to_string()),
+            ConfigKeyValue::Bool(value) => Some(value.to_string()),
+            _ => None,
+        })
+        .collect();
+    let Some(values) = values else {
+        diagnostics.push(ConfigurationDiagnostic {
+            property_name: "params".to_string(),
+            message: "Expected only string, number or boolean values.".to_string(),
+        });
+        return QueryParams::None;
+    };
+    if indexed {
+        QueryParams::Indexed(values)
     } else {
-        Ok(Some(text))
+        QueryParams::Named(names.into_iter().zip(values).collect())
     }
 }
 
+fn resolve_configuration(
+    mut config: ConfigKeyMap,
+    global_config: &GlobalConfiguration,
+    diagnostics: &mut Vec<ConfigurationDiagnostic>,
+) -> Configuration {
+    let preset: Preset = get_value(&mut config, "preset", Preset::Default, diagnostics);
+    let default_format_options = preset.format_options();
+
+    let resolved_config = Configuration {
+        use_tabs: get_value(
+            &mut config,
+            "useTabs",
+            global_config
+                .use_tabs
+                .unwrap_or(RECOMMENDED_GLOBAL_CONFIGURATION.use_tabs),
+            diagnostics,
+        ),
+        indent_width: get_value(
+            &mut config,
+            "indentWidth",
+            global_config
+                .indent_width
+                .unwrap_or(RECOMMENDED_GLOBAL_CONFIGURATION.indent_width),
+            diagnostics,
+        ),
+        new_line_kind: get_value(
+            &mut config,
+            "newLineKind",
+            global_config
+                .new_line_kind
+                .unwrap_or(RECOMMENDED_GLOBAL_CONFIGURATION.new_line_kind),
+            diagnostics,
+        ),
+        uppercase: get_value(
+            &mut config,
+            "uppercase",
+            default_format_options.uppercase.unwrap_or_default(),
+            diagnostics,
+        ),
+        lines_between_queries: get_value(
+            &mut config,
+            "linesBetweenQueries",
+            default_format_options.lines_between_queries,
+            diagnostics,
+        ),
+        inline: get_value(
+            &mut config,
+            "inline",
+            default_format_options.inline,
+            diagnostics,
+        ),
+        max_inline_block: get_value(
+            &mut config,
+            "maxInlineBlock",
+            default_format_options.max_inline_block,
+            diagnostics,
+        ),
+        max_inline_arguments: get_nullable_value_or(
+            &mut config,
+            "maxInlineArguments",
+            default_format_options.max_inline_arguments,
+            diagnostics,
+        ),
+        max_inline_top_level: get_nullable_value_or(
+            &mut config,
+            "maxInlineTopLevel",
+            default_format_options.max_inline_top_level,
+            diagnostics,
+        ),
+        joins_as_top_level: get_value(
+            &mut config,
+            "joinsAsTopLevel",
+            default_format_options.joins_as_top_level,
+            diagnostics,
+        ),
+        ignore_case_convert: get_nullable_vec(
+            &mut config,
+            "ignoreCaseConvert",
+            |value, _index, diagnostics| match value {
+                ConfigKeyValue::String(value) => Some(value),
+                _ => {
+                    diagnostics.push(ConfigurationDiagnostic {
+                        property_name: "ignoreCaseConvert".into(),
+                        message: "Expected only string values.".to_string(),
+                    });
+                    None
+                }
+            },
+            diagnostics,
+        ),
+        case_exceptions: get_nullable_vec(
+            &mut config,
+            "caseExceptions",
+            |value, _index, diagnostics| match value {
+                ConfigKeyValue::String(value) => Some(value),
+                _ => {
+                    diagnostics.push(ConfigurationDiagnostic {
+                        property_name: "caseExceptions".into(),
+                        message: "Expected only string values.".to_string(),
+                    });
+                    None
+                }
+            },
+            diagnostics,
+        )
+        .unwrap_or_default(),
+        strict: get_value(&mut config, "strict", false, diagnostics),
+        format_function_bodies: get_value(&mut config, "formatFunctionBodies", false, diagnostics),
+        dialect: get_value(&mut config, "dialect", Dialect::Generic, diagnostics),
+        engine: get_value(&mut config, "engine", Engine::Tokens, diagnostics),
+        quote_identifiers: get_value(
+            &mut config,
+            "quoteIdentifiers",
+            QuoteIdentifiers::Preserve,
+            diagnostics,
+        ),
+        identifier_case: get_value(
+            &mut config,
+            "identifierCase",
+            IdentifierCase::Preserve,
+            diagnostics,
+        ),
+        boolean_case: get_value(
+            &mut config,
+            "booleanCase",
+            LiteralCase::Preserve,
+            diagnostics,
+        ),
+        null_case: get_value(&mut config, "nullCase", LiteralCase::Preserve, diagnostics),
+        exponent_case: get_value(
+            &mut config,
+            "exponentCase",
+            LiteralCase::Preserve,
+            diagnostics,
+        ),
+        hex_prefix_case: get_value(
+            &mut config,
+            "hexPrefixCase",
+            LiteralCase::Preserve,
+            diagnostics,
+        ),
+        lint: get_value(&mut config, "lint", false, diagnostics),
+        lint_level: get_value(&mut config, "lintLevel", LintLevel::Warn, diagnostics),
+        verify_stable: get_value(&mut config, "verifyStable", false, diagnostics),
+        validate_output: get_value(&mut config, "validateOutput", false, diagnostics),
+        logical_operator_position: get_value(
+            &mut config,
+            "logicalOperatorPosition",
+            LogicalOperatorPosition::LeadingNewline,
+            diagnostics,
+        ),
+        case_style: get_value(&mut config, "caseStyle", CaseStyle::Default, diagnostics),
+        case_inline_width: get_value(&mut config, "caseInlineWidth", 0, diagnostics),
+        lines_between_ctes: get_value(&mut config, "linesBetweenCtes", 0, diagnostics),
+        indent_cte_body: get_value(&mut config, "indentCteBody", true, diagnostics),
+        subquery_style: get_value(
+            &mut config,
+            "subqueryStyle",
+            SubqueryStyle::Packed,
+            diagnostics,
+        ),
+        preserve_blank_lines: get_value(&mut config, "preserveBlankLines", false, diagnostics),
+        max_blank_lines: get_value(&mut config, "maxBlankLines", 2, diagnostics),
+        require_semicolons: get_value(
+            &mut config,
+            "requireSemicolons",
+            RequireSemicolons::Preserve,
+            diagnostics,
+        ),
+        encoding: get_value(&mut config, "encoding", Encoding::Auto, diagnostics),
+        insert_final_newline: get_value(&mut config, "insertFinalNewline", true, diagnostics),
+        normalize_whitespace: get_value(&mut config, "normalizeWhitespace", false, diagnostics),
+        window_style: get_value(
+            &mut config,
+            "windowStyle",
+            WindowStyle::Default,
+            diagnostics,
+        ),
+        window_inline_width: get_value(&mut config, "windowInlineWidth", 0, diagnostics),
+        grant_inline_width: get_value(&mut config, "grantInlineWidth", 0, diagnostics),
+        max_inline_list_items: get_nullable_value(&mut config, "maxInlineListItems", diagnostics),
+        max_inline_select_list: get_nullable_value(&mut config, "maxInlineSelectList", diagnostics),
+        max_inline_in_list: get_nullable_value(&mut config, "maxInlineInList", diagnostics),
+        max_inline_function_arguments: get_nullable_value(
+            &mut config,
+            "maxInlineFunctionArguments",
+            diagnostics,
+        ),
+        function_argument_indent: get_value(
+            &mut config,
+            "functionArgumentIndent",
+            FunctionArgumentIndent::Continuation,
+            diagnostics,
+        ),
+        max_inline_values: get_nullable_value(&mut config, "maxInlineValues", diagnostics),
+        join_condition_position: get_value(
+            &mut config,
+            "joinConditionPosition",
+            JoinConditionPosition::Default,
+            diagnostics,
+        ),
+        inline_join_using: get_value(&mut config, "inlineJoinUsing", false, diagnostics),
+        align_aliases: get_value(&mut config, "alignAliases", false, diagnostics),
+        as_keyword: get_value(&mut config, "asKeyword", AsKeyword::Preserve, diagnostics),
+        keyword_alignment: get_value(
+            &mut config,
+            "keywordAlignment",
+            KeywordAlignment::Left,
+            diagnostics,
+        ),
+        lines_around_set_operators: get_value(
+            &mut config,
+            "linesAroundSetOperators",
+            0,
+            diagnostics,
+        ),
+        parenthesize_set_operands: get_value(
+            &mut config,
+            "parenthesizeSetOperands",
+            false,
+            diagnostics,
+        ),
+        skip: get_value(&mut config, "skip", false, diagnostics),
+        params: config
+            .shift_remove("params")
+            .map(|params| resolve_params(params, diagnostics))
+            .unwrap_or_default(),
+        overrides: Vec::new(),
+    };
+    diagnostics.extend(get_unknown_property_diagnostics(config));
+    resolved_config
+}
+
 pub struct SqlPluginHandler {}
 
 impl SqlPluginHandler {
@@ -113,86 +1692,26 @@ impl SyncPluginHandler<Configuration> for SqlPluginHandler {
     ) -> PluginResolveConfigurationResult<Configuration> {
         let mut diagnostics = Vec::new();
         let mut config = config;
-        let default_format_options = FormatOptions::default();
-
-        let resolved_config = Configuration {
-            use_tabs: get_value(
-                &mut config,
-                "useTabs",
-                global_config
-                    .use_tabs
-                    .unwrap_or(RECOMMENDED_GLOBAL_CONFIGURATION.use_tabs),
-                &mut diagnostics,
-            ),
-            indent_width: get_value(
-                &mut config,
-                "indentWidth",
-                global_config
-                    .indent_width
-                    .unwrap_or(RECOMMENDED_GLOBAL_CONFIGURATION.indent_width),
-                &mut diagnostics,
-            ),
-            new_line_kind: get_value(
-                &mut config,
-                "newLineKind",
-                global_config
-                    .new_line_kind
-                    .unwrap_or(RECOMMENDED_GLOBAL_CONFIGURATION.new_line_kind),
-                &mut diagnostics,
-            ),
-            uppercase: get_value(&mut config, "uppercase", false, &mut diagnostics),
-            lines_between_queries: get_value(
-                &mut config,
-                "linesBetweenQueries",
-                default_format_options.lines_between_queries,
-                &mut diagnostics,
-            ),
-            inline: get_value(
-                &mut config,
-                "inline",
-                default_format_options.inline,
-                &mut diagnostics,
-            ),
-            max_inline_block: get_value(
-                &mut config,
-                "maxInlineBlock",
-                default_format_options.max_inline_block,
-                &mut diagnostics,
-            ),
-            max_inline_arguments: get_nullable_value(
-                &mut config,
-                "maxInlineArguments",
-                &mut diagnostics,
-            ),
-            max_inline_top_level: get_nullable_value(
-                &mut config,
-                "maxInlineTopLevel",
-                &mut diagnostics,
-            ),
-            joins_as_top_level: get_value(
-                &mut config,
-                "joinsAsTopLevel",
-                default_format_options.joins_as_top_level,
-                &mut diagnostics,
-            ),
-            ignore_case_convert: get_nullable_vec(
-                &mut config,
-                "ignoreCaseConvert",
-                |value, _index, diagnostics| match value {
-                    ConfigKeyValue::String(value) => Some(value),
-                    _ => {
-                        diagnostics.push(ConfigurationDiagnostic {
-                            property_name: "ignoreCaseConvert".into(),
-                            message: "Expected only string values.".to_string(),
-                        });
-                        None
-                    }
-                },
+        let overrides = config.shift_remove("overrides");
+        let frameworks = config.shift_remove("frameworks");
+        if let Some(path) = config.shift_remove("sqlfluffConfigPath") {
+            import_sqlfluff(path, &mut config, &mut diagnostics);
+        }
+        let mut resolved_config =
+            resolve_configuration(config.clone(), global_config, &mut diagnostics);
+        if let Some(frameworks) = frameworks {
+            resolved_config.overrides =
+                resolve_frameworks(frameworks, &config, global_config, &mut diagnostics);
+        }
+        // after those of frameworks, so the ones for specific files win
+        if let Some(overrides) = overrides {
+            resolved_config.overrides.extend(resolve_overrides(
+                overrides,
+                &config,
+                global_config,
                 &mut diagnostics,
-            ),
-        };
-
-        diagnostics.extend(get_unknown_property_diagnostics(config));
+            ));
+        }
 
         PluginResolveConfigurationResult {
             config: resolved_config,
@@ -237,8 +1756,12 @@ impl SyncPluginHandler<Configuration> for SqlPluginHandler {
         request: SyncFormatRequest<Configuration>,
         mut _format_with_host: impl FnMut(SyncHostFormatRequest) -> FormatResult,
     ) -> FormatResult {
-        let file_text = String::from_utf8(request.file_bytes)?;
-        format_text(&file_text, request.config).map(|maybe_text| maybe_text.map(|t| t.into_bytes()))
+        format_file(
+            request.file_path,
+            &request.file_bytes,
+            request.config,
+            request.token,
+        )
     }
 }
 
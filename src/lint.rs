@@ -0,0 +1,273 @@
+//! Style and risk checks that run over the token stream without formatting.
+
+use dprint_core::configuration::ParseConfigurationError;
+use dprint_core::generate_str_to_from;
+
+use crate::Dialect;
+use crate::diagnostics::line_column;
+use crate::keywords::is_keyword;
+use crate::lexer::Token;
+use crate::lexer::TokenKind;
+use crate::lexer::tokenize;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LintRule {
+    /// `SELECT *` or `t.*` in a select list.
+    SelectStar,
+    /// Tables joined with a comma in `FROM` instead of an explicit `JOIN`.
+    ImplicitCrossJoin,
+    /// `DELETE` or `UPDATE` without a `WHERE` clause.
+    MissingWhere,
+    /// A statement mixing aliases with and without `AS`.
+    InconsistentAlias,
+}
+
+generate_str_to_from![
+    LintRule,
+    [SelectStar, "select-star"],
+    [ImplicitCrossJoin, "implicit-cross-join"],
+    [MissingWhere, "missing-where"],
+    [InconsistentAlias, "inconsistent-alias"]
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintDiagnostic {
+    pub rule: LintRule,
+    pub message: String,
+    /// 1-based line number.
+    pub line: usize,
+    /// 1-based column number, counted in characters.
+    pub column: usize,
+}
+
+impl std::fmt::Display for LintDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}: {} ({})",
+            self.line, self.column, self.message, self.rule
+        )
+    }
+}
+
+/// The error returned by `format_text` when `lint` finds problems and
+/// `lintLevel` is `error`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintError(pub Vec<LintDiagnostic>);
+
+impl std::fmt::Display for LintError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Found {} lint problem(s):", self.0.len())?;
+        for diagnostic in &self.0 {
+            write!(f, "\n  {diagnostic}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for LintError {}
+
+pub(crate) fn lint(text: &str, dialect: Dialect) -> Vec<LintDiagnostic> {
+    let tokens = tokenize(text, dialect);
+    let mut linter = Linter {
+        text,
+        diagnostics: Vec::new(),
+    };
+    for statement in tokens.split(|t| t.kind == TokenKind::Semicolon) {
+        let significant: Vec<_> = statement.iter().filter(|t| !t.is_trivia()).collect();
+        if !significant.is_empty() {
+            linter.statement(&significant);
+        }
+    }
+    linter.diagnostics
+}
+
+struct Linter<'a> {
+    text: &'a str,
+    diagnostics: Vec<LintDiagnostic>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Clause {
+    Other,
+    Select,
+    From,
+}
+
+/// State tracked per parenthesis depth.
+struct Group {
+    clause: Clause,
+    /// `EXISTS (SELECT * ...)` is idiomatic, so it isn't reported.
+    in_exists: bool,
+    /// Whether the closing parenthesis ends something that can be aliased.
+    is_operand: bool,
+}
+
+fn is_clause_keyword(word: &str) -> bool {
+    matches!(
+        word.to_ascii_uppercase().as_str(),
+        "WHERE"
+            | "GROUP"
+            | "HAVING"
+            | "ORDER"
+            | "LIMIT"
+            | "OFFSET"
+            | "FETCH"
+            | "UNION"
+            | "INTERSECT"
+            | "EXCEPT"
+            | "MINUS"
+            | "WINDOW"
+            | "QUALIFY"
+            | "ON"
+            | "USING"
+            | "SET"
+            | "VALUES"
+            | "RETURNING"
+            | "INTO"
+            | "FOR"
+    )
+}
+
+impl Linter<'_> {
+    fn report(&mut self, token: &Token, rule: LintRule, message: &str) {
+        let (line, column) = line_column(self.text, token.start);
+        self.diagnostics.push(LintDiagnostic {
+            rule,
+            message: message.to_string(),
+            line,
+            column,
+        });
+    }
+
+    fn statement(&mut self, tokens: &[&Token]) {
+        self.missing_where(tokens);
+
+        let mut groups = vec![Group {
+            clause: Clause::Other,
+            in_exists: false,
+            is_operand: false,
+        }];
+        let mut explicit_alias = None;
+        let mut prev_is_operand = false;
+        for (i, token) in tokens.iter().enumerate() {
+            let prev = i.checked_sub(1).map(|i| tokens[i]);
+            let nested = groups.len() > 1;
+            let group = groups.last_mut().unwrap();
+            let mut is_operand = false;
+            match token.kind {
+                TokenKind::OpenParen => {
+                    let is_subquery = tokens.get(i + 1).is_some_and(|t| t.is_word("select"));
+                    let after_function =
+                        prev.is_some_and(|t| t.kind == TokenKind::Word && !is_keyword(t.text));
+                    groups.push(Group {
+                        clause: Clause::Other,
+                        in_exists: prev.is_some_and(|t| t.is_word("exists")),
+                        // ex. `count(*) c` or `(select ...) s`
+                        is_operand: is_subquery || after_function,
+                    });
+                }
+                TokenKind::CloseParen if nested => {
+                    is_operand = groups.pop().unwrap().is_operand;
+                }
+                TokenKind::Word if token.is_word("select") => group.clause = Clause::Select,
+                TokenKind::Word if token.is_word("from") || token.is_word("join") => {
+                    group.clause = Clause::From;
+                }
+                TokenKind::Word if is_clause_keyword(token.text) => group.clause = Clause::Other,
+                TokenKind::Word if is_keyword(token.text) => {}
+                TokenKind::Word | TokenKind::QuotedIdentifier if group.clause != Clause::Other => {
+                    if prev.is_some_and(|t| t.is_word("as")) {
+                        self.alias(token, true, &mut explicit_alias);
+                    } else if prev_is_operand {
+                        self.alias(token, false, &mut explicit_alias);
+                    } else {
+                        is_operand = true;
+                    }
+                }
+                TokenKind::Number | TokenKind::String => is_operand = true,
+                TokenKind::Operator
+                    if token.text == "*" && group.clause == Clause::Select && !group.in_exists =>
+                {
+                    let starts_item = prev.is_none_or(|t| {
+                        matches!(t.kind, TokenKind::Comma | TokenKind::Dot)
+                            || t.is_word("select")
+                            || t.is_word("distinct")
+                            || t.is_word("all")
+                    });
+                    if starts_item {
+                        self.report(
+                            token,
+                            LintRule::SelectStar,
+                            "Avoid `SELECT *`, list the columns explicitly",
+                        );
+                    }
+                }
+                TokenKind::Comma if group.clause == Clause::From => {
+                    self.report(
+                        token,
+                        LintRule::ImplicitCrossJoin,
+                        "Implicit cross join, use an explicit JOIN",
+                    );
+                }
+                TokenKind::Dot => {
+                    // keep treating a dotted name as one operand
+                    prev_is_operand = false;
+                    continue;
+                }
+                _ => {}
+            }
+            prev_is_operand = is_operand;
+        }
+    }
+
+    fn alias(&mut self, token: &Token, explicit: bool, style: &mut Option<bool>) {
+        match style {
+            None => *style = Some(explicit),
+            Some(style) if *style != explicit => {
+                let message = if explicit {
+                    "Alias uses AS while earlier aliases in the statement don't"
+                } else {
+                    "Alias is missing AS while earlier aliases in the statement use it"
+                };
+                self.report(token, LintRule::InconsistentAlias, message);
+            }
+            Some(_) => {}
+        }
+    }
+
+    fn missing_where(&mut self, tokens: &[&Token]) {
+        let mut depth = 0usize;
+        let mut statement = None;
+        for token in tokens {
+            match token.kind {
+                TokenKind::OpenParen => depth += 1,
+                TokenKind::CloseParen => depth = depth.saturating_sub(1),
+                _ if depth > 0 => {}
+                // skip past any leading WITH clause
+                TokenKind::Word if statement.is_none() => {
+                    if token.is_word("delete") || token.is_word("update") {
+                        statement = Some(*token);
+                    } else if is_keyword(token.text)
+                        && !matches!(
+                            token.text.to_ascii_uppercase().as_str(),
+                            "WITH" | "RECURSIVE" | "AS"
+                        )
+                    {
+                        // not a DELETE or UPDATE
+                        return;
+                    }
+                }
+                TokenKind::Word if token.is_word("where") => return,
+                _ => {}
+            }
+        }
+        if let Some(token) = statement {
+            let message = format!(
+                "{} without a WHERE clause affects every row",
+                token.text.to_ascii_uppercase()
+            );
+            self.report(token, LintRule::MissingWhere, &message);
+        }
+    }
+}
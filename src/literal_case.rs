@@ -0,0 +1,82 @@
+//! The case of literals: `booleanCase`, `nullCase`, `exponentCase` and
+//! `hexPrefixCase`.
+//!
+//! sqlformat cases `TRUE` and `NULL` like keywords but leaves `FALSE` as
+//! written, and keeps numbers as written, so these are cased after it runs.
+
+use crate::Configuration;
+use crate::Dialect;
+use crate::LiteralCase;
+use crate::lexer::Token;
+use crate::lexer::TokenKind;
+use crate::lexer::tokenize;
+
+pub(crate) fn change_case(formatted: String, config: &Configuration, dialect: Dialect) -> String {
+    let cases = [
+        config.boolean_case,
+        config.null_case,
+        config.exponent_case,
+        config.hex_prefix_case,
+    ];
+    if cases.iter().all(|&case| case == LiteralCase::Preserve) {
+        return formatted;
+    }
+    let tokens = tokenize(&formatted, dialect);
+    let mut result = String::with_capacity(formatted.len());
+    for (i, token) in tokens.iter().enumerate() {
+        let after_dot = tokens[..i]
+            .iter()
+            .rfind(|t| !t.is_trivia())
+            .is_some_and(|t| t.kind == TokenKind::Dot);
+        match token.kind {
+            TokenKind::Word if after_dot => result.push_str(token.text),
+            TokenKind::Word if token.is_word("true") || token.is_word("false") => {
+                result.push_str(&recase(token.text, config.boolean_case));
+            }
+            TokenKind::Word if token.is_word("null") => {
+                result.push_str(&recase(token.text, config.null_case));
+            }
+            TokenKind::Number => result.push_str(&number(token.text, config, dialect)),
+            TokenKind::String if is_hex_string(token) => {
+                let case = config.hex_prefix_case;
+                result.push_str(&recase(&token.text[..1], case));
+                result.push_str(&token.text[1..]);
+            }
+            _ => result.push_str(token.text),
+        }
+    }
+    result
+}
+
+/// Whether `token` is a hexadecimal string like `X'1F'`.
+pub(crate) fn is_hex_string(token: &Token) -> bool {
+    token.kind == TokenKind::String && token.text.starts_with(['x', 'X'])
+}
+
+fn number(text: &str, config: &Configuration, dialect: Dialect) -> String {
+    if let Some(digits) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        // mysql only reads a lowercase `0x`
+        let case = match config.hex_prefix_case {
+            LiteralCase::Upper if dialect == Dialect::MySql => LiteralCase::Lower,
+            case => case,
+        };
+        return format!("0{}{digits}", recase(&text[1..2], case));
+    }
+    match text.find(['e', 'E']) {
+        Some(e) => format!(
+            "{}{}{}",
+            &text[..e],
+            recase(&text[e..e + 1], config.exponent_case),
+            &text[e + 1..]
+        ),
+        None => text.to_string(),
+    }
+}
+
+fn recase(text: &str, case: LiteralCase) -> String {
+    match case {
+        LiteralCase::Preserve => text.to_string(),
+        LiteralCase::Lower => text.to_lowercase(),
+        LiteralCase::Upper => text.to_uppercase(),
+    }
+}
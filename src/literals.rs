@@ -0,0 +1,140 @@
+//! Keeping the contents of string literals, quoted identifiers and comments
+//! as written.
+//!
+//! sqlformat only knows strings that start with a quote, or with an uppercase
+//! prefix, and splits the rest into a word and a string (`e 'a\nb'`,
+//! `U & 'd\0061t'`), which changes their meaning. It also splits hexadecimal
+//! numbers and those with an uppercase exponent after their first digit
+//! (`0 xff`, `1 E5`). Those are protected before formatting, and the output is
+//! checked against the input so that anything else that would change a
+//! literal is reported instead of written.
+
+use crate::Configuration;
+use crate::Dialect;
+use crate::QuoteIdentifiers;
+use crate::diagnostics::line_column;
+use crate::lexer::Token;
+use crate::lexer::TokenKind;
+use crate::lexer::tokenize;
+use crate::literal_case::is_hex_string;
+use crate::protect::Protected;
+
+/// An error describing a literal formatting would have changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlteredLiteralError {
+    /// 1-based line number of the literal in the input.
+    pub line: usize,
+    /// 1-based column number of the literal, counted in characters.
+    pub column: usize,
+    /// The literal as written.
+    pub original: String,
+    /// The literal in the formatted output, if it's there at all.
+    pub formatted: Option<String>,
+}
+
+impl std::fmt::Display for AlteredLiteralError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Formatting would change the literal at line {}, column {}:\n  input:  {}\n  output: {}",
+            self.line,
+            self.column,
+            self.original,
+            self.formatted.as_deref().unwrap_or("(missing)")
+        )
+    }
+}
+
+impl std::error::Error for AlteredLiteralError {}
+
+pub(crate) fn protect(text: &str, dialect: Dialect, protected: &mut Protected) -> String {
+    let mut result = String::with_capacity(text.len());
+    for token in tokenize(text, dialect) {
+        let prefixed = matches!(token.kind, TokenKind::String | TokenKind::QuotedIdentifier)
+            && !token.text.starts_with(['\'', '"', '`', '[', '$']);
+        let split = token.kind == TokenKind::Number && token.text.contains(['x', 'X', 'E']);
+        if prefixed || split {
+            result.push_str(&protected.insert(token.text.to_string()));
+        } else {
+            result.push_str(token.text);
+        }
+    }
+    result
+}
+
+/// Replaces the line endings of `text` with `new_line`, except inside
+/// literals.
+pub(crate) fn replace_new_lines(
+    text: &str,
+    new_line: &str,
+    config: &Configuration,
+    dialect: Dialect,
+) -> String {
+    let mut result = String::with_capacity(text.len());
+    for token in tokenize(text, dialect) {
+        if is_literal(&token, config) || !token.text.contains('\n') {
+            result.push_str(token.text);
+        } else {
+            result.push_str(&token.text.replace("\r\n", "\n").replace('\n', new_line));
+        }
+    }
+    result
+}
+
+/// Checks that `output` has the literals of `input`, in the same order and
+/// with the same contents.
+pub(crate) fn verify(
+    input: &str,
+    output: &str,
+    config: &Configuration,
+    dialect: Dialect,
+) -> Result<(), AlteredLiteralError> {
+    let before = tokenize(input, dialect);
+    let after = tokenize(output, dialect);
+    let mut after = after.iter().filter(|t| is_literal(t, config));
+    for token in before.iter().filter(|t| is_literal(t, config)) {
+        let formatted = after.next();
+        // unparseable, and running to the end where the final newline goes
+        if !token.terminated {
+            break;
+        }
+        if formatted.is_some_and(|f| same_contents(token, f)) {
+            continue;
+        }
+        let (line, column) = line_column(input, token.start);
+        return Err(AlteredLiteralError {
+            line,
+            column,
+            original: token.text.to_string(),
+            formatted: formatted.map(|t| t.text.to_string()),
+        });
+    }
+    Ok(())
+}
+
+fn is_literal(token: &Token, config: &Configuration) -> bool {
+    match token.kind {
+        TokenKind::String => !(config.format_function_bodies && token.text.starts_with('$')),
+        TokenKind::QuotedIdentifier => config.quote_identifiers == QuoteIdentifiers::Preserve,
+        TokenKind::LineComment | TokenKind::BlockComment | TokenKind::CopyData => true,
+        _ => false,
+    }
+}
+
+fn same_contents(before: &Token, after: &Token) -> bool {
+    match before.kind {
+        // sqlformat drops the spaces at their end
+        TokenKind::LineComment => before.text.trim_end() == after.text.trim_end(),
+        // and indents their lines along with the query
+        TokenKind::BlockComment => before
+            .text
+            .lines()
+            .map(str::trim)
+            .eq(after.text.lines().map(str::trim)),
+        // `hexPrefixCase` changes the case of their prefix
+        TokenKind::String if is_hex_string(before) => {
+            is_hex_string(after) && before.text[1..] == after.text[1..]
+        }
+        _ => before.text == after.text,
+    }
+}
@@ -0,0 +1,55 @@
+//! Placement of `AND` and `OR` relative to the line breaks sqlformat puts
+//! before them, per `logicalOperatorPosition`.
+
+use crate::Dialect;
+use crate::LogicalOperatorPosition;
+use crate::lexer::TokenKind;
+use crate::lexer::tokenize;
+
+pub(crate) fn reposition(
+    formatted: String,
+    position: LogicalOperatorPosition,
+    dialect: Dialect,
+) -> String {
+    if position == LogicalOperatorPosition::LeadingNewline {
+        return formatted;
+    }
+
+    let tokens = tokenize(&formatted, dialect);
+    let mut result = String::with_capacity(formatted.len());
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = &tokens[i];
+        let starts_line = i >= 2
+            && tokens[i - 1].kind == TokenKind::Whitespace
+            && tokens[i - 1].text.contains('\n')
+            // moving onto the previous line would comment it out
+            && tokens[i - 2].kind != TokenKind::LineComment;
+        if !(starts_line && (token.is_word("and") || token.is_word("or"))) {
+            result.push_str(token.text);
+            i += 1;
+            continue;
+        }
+
+        // the line break and indentation are already in `result`
+        let line_break = tokens[i - 1].text;
+        result.truncate(result.len() - line_break.len());
+        result.push(' ');
+        result.push_str(token.text);
+        i += 1;
+        let space = tokens
+            .get(i)
+            .is_some_and(|t| t.kind == TokenKind::Whitespace && !t.text.contains('\n'));
+        // `and (` stays together so the group opens on the operator's line
+        let opens_group = tokens
+            .get(i + usize::from(space))
+            .is_some_and(|t| t.kind == TokenKind::OpenParen);
+        if position == LogicalOperatorPosition::TrailingNewline && !opens_group {
+            result.push_str(line_break);
+            if space {
+                i += 1;
+            }
+        }
+    }
+    result
+}
@@ -0,0 +1,306 @@
+//! Layout of `MERGE` statements.
+//!
+//! sqlformat knows `MERGE` but none of the clauses after its target, so it
+//! runs them together, breaking lines wherever a keyword it knows comes up.
+//! The statement is laid out here instead, with `USING` and `ON` as clauses
+//! and each `WHEN` branch as a block of its own whose action is formatted like
+//! a statement:
+//!
+//! ```sql
+//! merge into
+//!   target t
+//! using
+//!   source s
+//! on
+//!   t.id = s.id
+//! when matched then
+//!   update
+//!   set
+//!     val = s.val
+//! when not matched then
+//!   insert
+//!     (id, val)
+//!   values
+//!     (s.id, s.val)
+//! ```
+//!
+//! The `OUTPUT` clause of SQL Server goes after the branches, on one line.
+//! Statements with comments in the parts written on one line are left to
+//! sqlformat.
+
+use sqlformat::FormatOptions;
+
+use crate::Configuration;
+use crate::Dialect;
+use crate::lexer::Token;
+use crate::lexer::TokenKind;
+use crate::lexer::matching_paren;
+use crate::lexer::tokenize;
+use crate::literals;
+use crate::protect::Protected;
+
+/// The words of a branch before its condition or `THEN`.
+const BRANCH_WORDS: &[&str] = &["by", "matched", "not", "source", "target", "when"];
+
+struct Merge<'a> {
+    into: bool,
+    target: &'a [Token<'a>],
+    source: &'a [Token<'a>],
+    condition: &'a [Token<'a>],
+    branches: Vec<Branch<'a>>,
+    /// SQL Server's `OUTPUT` clause, without the keyword.
+    output: Option<&'a [Token<'a>]>,
+}
+
+struct Branch<'a> {
+    /// `WHEN [NOT] MATCHED [BY SOURCE | TARGET]`.
+    head: &'a [Token<'a>],
+    /// What follows `AND`, if anything.
+    condition: Option<&'a [Token<'a>]>,
+    action: &'a [Token<'a>],
+}
+
+/// Replaces every `MERGE` statement in `text` with a placeholder.
+pub(crate) fn protect(
+    text: &str,
+    config: &Configuration,
+    dialect: Dialect,
+    protected: &mut Protected,
+) -> String {
+    let tokens = tokenize(text, dialect);
+    let mut result = String::with_capacity(text.len());
+    let mut pos = 0;
+    let mut at_statement_start = true;
+    let mut depth = 0usize;
+    for (i, token) in tokens.iter().enumerate() {
+        if token.is_trivia() || token.start < pos {
+            continue;
+        }
+        match token.kind {
+            TokenKind::OpenParen => depth += 1,
+            TokenKind::CloseParen => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        let starts_merge = at_statement_start && token.is_word("merge");
+        at_statement_start = depth == 0 && token.kind == TokenKind::Semicolon;
+        if !starts_merge {
+            continue;
+        }
+        let end = statement_end(&tokens, i);
+        let Some(merge) = parse(&tokens[i..end]) else {
+            continue;
+        };
+        let last = &tokens[end - 1];
+        result.push_str(&text[pos..token.start]);
+        result.push_str(&protected.insert_block(render(text, &merge, config, dialect), dialect));
+        pos = last.start + last.text.len();
+    }
+    result.push_str(&text[pos..]);
+    result
+}
+
+/// The index just after the last significant token of the statement starting
+/// at `tokens[start]`.
+fn statement_end(tokens: &[Token], start: usize) -> usize {
+    let mut depth = 0usize;
+    let mut end = start;
+    for (i, token) in tokens.iter().enumerate().skip(start) {
+        match token.kind {
+            TokenKind::OpenParen => depth += 1,
+            TokenKind::CloseParen => depth = depth.saturating_sub(1),
+            TokenKind::Semicolon if depth == 0 => break,
+            _ => {}
+        }
+        if !token.is_trivia() {
+            end = i + 1;
+        }
+    }
+    end
+}
+
+fn parse<'a>(tokens: &'a [Token<'a>]) -> Option<Merge<'a>> {
+    // the words of the statement outside of parentheses and `CASE`, by index
+    let mut words = Vec::new();
+    let mut depth = 0usize;
+    let mut cases = 0usize;
+    for (i, token) in tokens.iter().enumerate() {
+        match token.kind {
+            TokenKind::OpenParen => depth += 1,
+            TokenKind::CloseParen => depth = depth.saturating_sub(1),
+            TokenKind::Word if depth == 0 && token.is_word("case") => cases += 1,
+            TokenKind::Word if depth == 0 && cases > 0 && token.is_word("end") => cases -= 1,
+            TokenKind::Word if depth == 0 && cases == 0 => words.push(i),
+            _ => {}
+        }
+    }
+    let find = |word: &str, from: usize| {
+        words
+            .iter()
+            .copied()
+            .find(|&i| i >= from && tokens[i].is_word(word))
+    };
+
+    let mut start = next_significant(tokens, 1);
+    let into = tokens.get(start).is_some_and(|t| t.is_word("into"));
+    if into {
+        start = next_significant(tokens, start + 1);
+    }
+    let using = find("using", start)?;
+    let on = find("on", using)?;
+    let first_when = find("when", on)?;
+    let output = find("output", first_when);
+    let end = output.unwrap_or(tokens.len());
+    let mut branches = Vec::new();
+    let mut when = first_when;
+    loop {
+        let then = find("then", when)?;
+        let next = find("when", then).filter(|&next| next < end);
+        let head_end = find("and", when).filter(|&and| and < then).unwrap_or(then);
+        if tokens[when..head_end]
+            .iter()
+            .any(|t| !t.is_trivia() && !BRANCH_WORDS.iter().any(|w| t.is_word(w)))
+        {
+            return None;
+        }
+        branches.push(Branch {
+            head: &tokens[when..head_end],
+            condition: (head_end < then).then(|| &tokens[head_end + 1..then]),
+            action: &tokens[then + 1..next.unwrap_or(end)],
+        });
+        match next {
+            Some(next) => when = next,
+            None => break,
+        }
+    }
+    let merge = Merge {
+        into,
+        target: &tokens[start..using],
+        source: &tokens[using + 1..on],
+        condition: &tokens[on + 1..first_when],
+        branches,
+        output: output.map(|output| &tokens[output + 1..]),
+    };
+    // the parts written on one line
+    let inline_parts = [merge.target, merge.source, merge.condition]
+        .into_iter()
+        .chain(merge.branches.iter().flat_map(|b| b.condition))
+        .chain(merge.output);
+    let has_comment = inline_parts
+        .clone()
+        .flatten()
+        .any(|t| matches!(t.kind, TokenKind::LineComment | TokenKind::BlockComment));
+    if has_comment
+        || inline_parts.clone().any(is_empty)
+        || merge.branches.iter().any(|b| is_empty(b.action))
+    {
+        return None;
+    }
+    Some(merge)
+}
+
+fn render(text: &str, merge: &Merge, config: &Configuration, dialect: Dialect) -> String {
+    let indent = config.indent();
+    let keyword = |word: &str| {
+        if config.uppercase {
+            word.to_uppercase()
+        } else {
+            word.to_lowercase()
+        }
+    };
+    let mut protected = Protected::default();
+    let mut lines = vec![if merge.into {
+        keyword("merge into")
+    } else {
+        keyword("merge")
+    }];
+    lines.push(format!(
+        "{indent}{}",
+        inline(text, merge.target, config, dialect)
+    ));
+    lines.push(keyword("using"));
+    lines.push(source(text, merge.source, config, dialect, &mut protected));
+    lines.push(keyword("on"));
+    lines.push(format!(
+        "{indent}{}",
+        inline(text, merge.condition, config, dialect)
+    ));
+    for branch in &merge.branches {
+        let mut head: Vec<_> = branch
+            .head
+            .iter()
+            .filter(|t| !t.is_trivia())
+            .map(|t| keyword(t.text))
+            .collect();
+        if let Some(condition) = branch.condition {
+            head.push(keyword("and"));
+            head.push(inline(text, condition, config, dialect));
+        }
+        head.push(keyword("then"));
+        lines.push(head.join(" "));
+        let action = crate::format_sql(span(text, branch.action), config);
+        let placeholder = protected.insert_block(action, dialect);
+        lines.push(format!("{indent}{placeholder}"));
+    }
+    if let Some(output) = merge.output {
+        lines.push(keyword("output"));
+        lines.push(format!("{indent}{}", inline(text, output, config, dialect)));
+    }
+    protected.restore(&lines.join("\n"))
+}
+
+/// The indented source of the merge, with a subquery laid out on lines of
+/// its own.
+fn source(
+    text: &str,
+    tokens: &[Token],
+    config: &Configuration,
+    dialect: Dialect,
+    protected: &mut Protected,
+) -> String {
+    let indent = config.indent();
+    let open = next_significant(tokens, 0);
+    let close = matching_paren(tokens, open).filter(|_| tokens[open].kind == TokenKind::OpenParen);
+    let Some(close) = close else {
+        return format!("{indent}{}", inline(text, tokens, config, dialect));
+    };
+    let body = crate::format_sql(span(text, &tokens[open + 1..close]), config);
+    let placeholder = protected.insert_block(body, dialect);
+    let mut source = format!("{indent}(\n{indent}{indent}{placeholder}\n{indent})");
+    let alias = &tokens[close + 1..];
+    if !is_empty(alias) {
+        source.push(' ');
+        source.push_str(&inline(text, alias, config, dialect));
+    }
+    source
+}
+
+/// Formats `tokens` on one line, the way sqlformat would format them.
+fn inline(text: &str, tokens: &[Token], config: &Configuration, dialect: Dialect) -> String {
+    let mut protected = Protected::default();
+    let text = literals::protect(span(text, tokens), dialect, &mut protected);
+    let options = FormatOptions {
+        inline: true,
+        ..config.into()
+    };
+    let formatted = sqlformat::format(&text, &(&config.params).into(), &options);
+    protected.restore(&formatted)
+}
+
+/// The text of `tokens`, a slice of the tokens of `text`.
+fn span<'a>(text: &'a str, tokens: &[Token]) -> &'a str {
+    match (tokens.first(), tokens.last()) {
+        (Some(first), Some(last)) => &text[first.start..last.start + last.text.len()],
+        _ => "",
+    }
+}
+
+fn is_empty(tokens: &[Token]) -> bool {
+    tokens.iter().all(Token::is_trivia)
+}
+
+fn next_significant(tokens: &[Token], from: usize) -> usize {
+    tokens[from.min(tokens.len())..]
+        .iter()
+        .position(|t| !t.is_trivia())
+        .map_or(tokens.len(), |i| from + i)
+}
@@ -0,0 +1,24 @@
+//! Formatting the statements of large files on several threads, with the
+//! `parallel` feature.
+
+#[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+use rayon::prelude::*;
+
+/// The number of statements below which formatting them one after another is
+/// faster than handing them out to threads.
+#[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+const MIN_STATEMENTS: usize = 64;
+
+/// Maps each of `items` with `f`, in parallel when there are enough of them,
+/// keeping their order.
+pub(crate) fn map<T, F>(items: &[T], f: F) -> Vec<String>
+where
+    T: Sync,
+    F: Fn(&T) -> String + Sync + Send,
+{
+    #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+    if items.len() >= MIN_STATEMENTS {
+        return items.par_iter().map(f).collect();
+    }
+    items.iter().map(f).collect()
+}
@@ -0,0 +1,325 @@
+//! Formatting the bodies of `plpgsql` routines for `formatFunctionBodies`.
+//!
+//! sqlformat doesn't know the blocks and control structures of PL/pgSQL, so
+//! they're laid out here: each on lines of their own, with the statements
+//! inside indented a level. The SQL statements among them are formatted on
+//! their own, and the others are written on one line. Bodies whose blocks
+//! can't be followed, or with comments inside a statement, are left as they
+//! are.
+
+use crate::Configuration;
+use crate::Dialect;
+use crate::RequireSemicolons;
+use crate::lexer::Token;
+use crate::lexer::TokenKind;
+use crate::lexer::joined;
+use crate::lexer::tokenize;
+use crate::snippet;
+
+/// The statements formatted as SQL.
+const SQL_STATEMENTS: &[&str] = &["delete", "insert", "merge", "select", "update", "with"];
+
+/// The words of PL/pgSQL itself, which are cased like keywords.
+const KEYWORDS: &[&str] = &[
+    "begin",
+    "case",
+    "close",
+    "continue",
+    "declare",
+    "else",
+    "elseif",
+    "elsif",
+    "end",
+    "exception",
+    "execute",
+    "exit",
+    "fetch",
+    "for",
+    "foreach",
+    "if",
+    "in",
+    "loop",
+    "open",
+    "perform",
+    "raise",
+    "return",
+    "reverse",
+    "then",
+    "when",
+    "while",
+];
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Declare,
+    Block,
+    /// The `EXCEPTION` section of a block, whose handlers are indented.
+    Exception,
+    If,
+    Loop,
+    /// Whose `WHEN` and `ELSE` are indented.
+    Case,
+}
+
+struct Frame {
+    kind: Kind,
+    /// The depth of the lines starting and ending it.
+    depth: usize,
+}
+
+struct Formatter<'a, 'c> {
+    body: &'a str,
+    tokens: Vec<Token<'a>>,
+    pos: usize,
+    config: &'c Configuration,
+    dialect: Dialect,
+    lines: Vec<String>,
+    stack: Vec<Frame>,
+    depth: usize,
+}
+
+/// Formats the plpgsql `body`, or returns `None` if its structure can't be
+/// followed.
+pub(crate) fn format(body: &str, config: &Configuration, dialect: Dialect) -> Option<String> {
+    let mut formatter = Formatter {
+        body,
+        tokens: tokenize(body, dialect),
+        pos: 0,
+        config,
+        dialect,
+        lines: Vec::new(),
+        stack: Vec::new(),
+        depth: 0,
+    };
+    while formatter.statement()? {}
+    formatter
+        .stack
+        .is_empty()
+        .then(|| formatter.lines.join("\n"))
+}
+
+impl<'a> Formatter<'a, '_> {
+    /// Lays out the statement or block keyword at the current position,
+    /// returning false once there are none left.
+    fn statement(&mut self) -> Option<bool> {
+        let mut new_line = self.pos == 0;
+        while let Some(token) = self
+            .tokens
+            .get(self.pos)
+            .filter(|t| t.kind == TokenKind::Whitespace)
+        {
+            new_line |= token.text.contains('\n');
+            self.pos += 1;
+        }
+        let Some(&first) = self.tokens.get(self.pos) else {
+            return Some(false);
+        };
+        if matches!(first.kind, TokenKind::LineComment | TokenKind::BlockComment) {
+            self.pos += 1;
+            let comment = first.text.trim_end();
+            match self.lines.last_mut() {
+                // comments after a statement stay on its line
+                Some(line) if !new_line => *line = format!("{line} {comment}"),
+                _ => self.push(self.depth, comment.to_string()),
+            }
+            return Some(true);
+        }
+        let word = |w: &str| first.is_word(w);
+        if first.kind == TokenKind::Operator && first.text == "<<" {
+            let label = self.take_until(|t| t.kind == TokenKind::Operator && t.text == ">>")?;
+            self.push(self.depth, joined(&label));
+        } else if word("declare") {
+            self.pos += 1;
+            self.open(Kind::Declare, self.depth, self.keyword("declare"));
+        } else if word("begin") {
+            self.pos += 1;
+            let depth = match self.stack.last() {
+                Some(frame) if frame.kind == Kind::Declare => self.stack.pop()?.depth,
+                _ => self.depth,
+            };
+            self.open(Kind::Block, depth, self.keyword("begin"));
+        } else if word("exception") {
+            self.pos += 1;
+            let frame = self.stack.last_mut().filter(|f| f.kind == Kind::Block)?;
+            frame.kind = Kind::Exception;
+            let depth = frame.depth;
+            self.push(depth, self.keyword("exception"));
+        } else if word("when") {
+            let frame = self
+                .stack
+                .last()
+                .filter(|f| matches!(f.kind, Kind::Exception | Kind::Case))?;
+            let depth = frame.depth + 1;
+            let line = self.take_until(|t| t.is_word("then"))?;
+            self.push(depth, self.cased(&line));
+            self.depth = depth + 1;
+        } else if word("if") {
+            let line = self.take_until(|t| t.is_word("then"))?;
+            self.open(Kind::If, self.depth, self.cased(&line));
+        } else if word("elsif") || word("elseif") {
+            let depth = self.stack.last().filter(|f| f.kind == Kind::If)?.depth;
+            let line = self.take_until(|t| t.is_word("then"))?;
+            self.push(depth, self.cased(&line));
+            self.depth = depth + 1;
+        } else if word("else") {
+            self.pos += 1;
+            let depth = match self.stack.last()? {
+                Frame {
+                    kind: Kind::If,
+                    depth,
+                } => *depth,
+                Frame {
+                    kind: Kind::Case,
+                    depth,
+                } => depth + 1,
+                _ => return None,
+            };
+            self.push(depth, self.keyword("else"));
+            self.depth = depth + 1;
+        } else if word("case") {
+            let start = self.pos;
+            self.pos += 1;
+            let mut line = self.take_while(|t| !t.is_word("when"));
+            line.insert(0, self.tokens[start]);
+            self.open(Kind::Case, self.depth, self.cased(&line));
+            // the `WHEN`s are indented by `statement` itself
+            self.depth -= 1;
+        } else if word("loop") || word("while") || word("for") || word("foreach") {
+            let line = self.take_until(|t| t.is_word("loop"))?;
+            self.open(Kind::Loop, self.depth, self.cased(&line));
+        } else if word("end") {
+            let line = self.take_until(|t| t.kind == TokenKind::Semicolon)?;
+            let ends = line
+                .iter()
+                .filter(|t| !t.is_trivia())
+                .nth(1)
+                .map(|t| t.text.to_ascii_lowercase());
+            let kind = match ends.as_deref() {
+                Some("if") => vec![Kind::If],
+                Some("loop") => vec![Kind::Loop],
+                Some("case") => vec![Kind::Case],
+                // `END` or `END label`
+                _ => vec![Kind::Block, Kind::Exception],
+            };
+            let frame = self.stack.pop().filter(|f| kind.contains(&f.kind))?;
+            self.push(frame.depth, self.cased(&line));
+            self.depth = frame.depth;
+        } else {
+            let statement = self.take_until(|t| t.kind == TokenKind::Semicolon)?;
+            let is_sql = SQL_STATEMENTS.iter().any(|w| first.is_word(w));
+            if is_sql {
+                self.push_sql(&statement);
+            } else if statement.iter().any(|t| t.kind == TokenKind::LineComment) {
+                return None;
+            } else {
+                self.push(self.depth, self.cased(&statement));
+            }
+        }
+        Some(true)
+    }
+
+    /// Takes the tokens up to and including the first outside parentheses
+    /// and CASE expressions that `stop` is true for, or those up to the end
+    /// of the body if there's none and `stop` is for a semicolon.
+    fn take_until(&mut self, stop: impl Fn(&Token) -> bool) -> Option<Vec<Token<'a>>> {
+        let mut taken = self.take_while(|t| !stop(t));
+        match self.tokens.get(self.pos) {
+            Some(&token) => {
+                self.pos += 1;
+                taken.push(token);
+                Some(taken)
+            }
+            // the last `END` of a DO block can do without its semicolon
+            None => stop(&Token {
+                kind: TokenKind::Semicolon,
+                text: ";",
+                start: 0,
+                terminated: true,
+            })
+            .then_some(taken),
+        }
+    }
+
+    /// Takes the tokens before the first outside parentheses and CASE
+    /// expressions that `go_on` is false for.
+    fn take_while(&mut self, go_on: impl Fn(&Token) -> bool) -> Vec<Token<'a>> {
+        let start = self.pos;
+        let mut parens = 0usize;
+        let mut cases = 0usize;
+        let mut previous: Option<Token> = None;
+        while let Some(&token) = self.tokens.get(self.pos) {
+            if parens == 0 && cases == 0 && self.pos > start && !go_on(&token) {
+                break;
+            }
+            // the `CASE` of `END CASE` closes a statement rather than opening
+            // an expression
+            let ends = previous.is_some_and(|t| t.is_word("end"));
+            match token.kind {
+                TokenKind::OpenParen => parens += 1,
+                TokenKind::CloseParen => parens = parens.saturating_sub(1),
+                _ if token.is_word("case") && self.pos > start && !ends => cases += 1,
+                _ if token.is_word("end") && cases > 0 => cases -= 1,
+                _ => {}
+            }
+            if !token.is_trivia() {
+                previous = Some(token);
+            }
+            self.pos += 1;
+        }
+        self.tokens[start..self.pos].to_vec()
+    }
+
+    fn open(&mut self, kind: Kind, depth: usize, line: String) {
+        self.push(depth, line);
+        self.stack.push(Frame { kind, depth });
+        self.depth = depth + 1;
+    }
+
+    fn push(&mut self, depth: usize, line: String) {
+        self.lines
+            .push(format!("{}{line}", self.config.indent().repeat(depth)));
+    }
+
+    fn push_sql(&mut self, statement: &[Token]) {
+        let start = statement[0].start;
+        let last = statement[statement.len() - 1];
+        let text = &self.body[start..last.start + last.text.len()];
+        let config = Configuration {
+            require_semicolons: RequireSemicolons::Preserve,
+            ..self.config.clone()
+        };
+        let formatted = crate::format_sql(text, &config);
+        let width = usize::from(self.config.indent_width.max(1));
+        self.lines.push(snippet::indent(
+            formatted.trim_end(),
+            self.depth * width,
+            self.config,
+            self.dialect,
+        ));
+    }
+
+    /// `tokens` on one line, with the words of PL/pgSQL cased like keywords.
+    fn cased(&self, tokens: &[Token]) -> String {
+        let mut line = String::new();
+        let text = joined(tokens);
+        let mut pos = 0;
+        for token in tokenize(&text, self.dialect) {
+            line.push_str(&text[pos..token.start]);
+            if token.kind == TokenKind::Word && KEYWORDS.iter().any(|w| token.is_word(w)) {
+                line.push_str(&self.keyword(token.text));
+            } else {
+                line.push_str(token.text);
+            }
+            pos = token.start + token.text.len();
+        }
+        line
+    }
+
+    fn keyword(&self, word: &str) -> String {
+        if self.config.uppercase {
+            word.to_uppercase()
+        } else {
+            word.to_lowercase()
+        }
+    }
+}
@@ -0,0 +1,93 @@
+//! Swaps regions sqlformat must not touch for opaque string literals, which
+//! it passes through untouched, and puts the originals back afterwards.
+
+use crate::Dialect;
+use crate::lexer::TokenKind;
+use crate::lexer::tokenize;
+
+const MARKER: char = '\u{E000}';
+
+#[derive(Default)]
+pub(crate) struct Protected {
+    regions: Vec<Region>,
+}
+
+struct Region {
+    text: String,
+    /// Offsets of the lines in `text` that get the indentation of the line
+    /// the placeholder ends up on.
+    indent_at: Vec<usize>,
+}
+
+impl Protected {
+    /// Stores `region` and returns the placeholder to format in its place.
+    pub fn insert(&mut self, region: String) -> String {
+        self.push(Region {
+            text: region,
+            indent_at: Vec::new(),
+        })
+    }
+
+    /// Like `insert`, but for multi-line regions laid out from column 0 that
+    /// should follow the indentation of wherever the placeholder is put.
+    /// Lines inside literals and comments are left as they are.
+    pub fn insert_block(&mut self, region: String, dialect: Dialect) -> String {
+        let tokens = tokenize(&region, dialect);
+        let indent_at = tokens
+            .iter()
+            .filter(|t| t.kind == TokenKind::Whitespace)
+            .flat_map(|t| t.text.match_indices('\n').map(|(i, _)| t.start + i + 1))
+            .filter(|&i| !region[i..].starts_with(['\n', '\r']) && i < region.len())
+            .collect();
+        self.push(Region {
+            text: region,
+            indent_at,
+        })
+    }
+
+    fn push(&mut self, region: Region) -> String {
+        self.regions.push(region);
+        format!("'{MARKER}{}{MARKER}'", self.regions.len() - 1)
+    }
+
+    pub fn restore(&self, formatted: &str) -> String {
+        let mut restored = String::with_capacity(formatted.len());
+        let mut rest = formatted;
+        while let Some(start) = rest.find(&format!("'{MARKER}")) {
+            let after_marker = &rest[start + 1 + MARKER.len_utf8()..];
+            let region =
+                after_marker
+                    .split_once(&format!("{MARKER}'"))
+                    .and_then(|(index, after)| {
+                        Some((self.regions.get(index.parse::<usize>().ok()?)?, after))
+                    });
+            match region {
+                Some((region, after)) => {
+                    restored.push_str(&rest[..start]);
+                    region.write_indented(&mut restored);
+                    rest = after;
+                }
+                None => {
+                    restored.push_str(&rest[..start + 1]);
+                    rest = &rest[start + 1..];
+                }
+            }
+        }
+        restored.push_str(rest);
+        restored
+    }
+}
+
+impl Region {
+    fn write_indented(&self, restored: &mut String) {
+        let line = &restored[restored.rfind('\n').map(|i| i + 1).unwrap_or(0)..];
+        let indent = line[..line.len() - line.trim_start().len()].to_string();
+        let mut pos = 0;
+        for &offset in &self.indent_at {
+            restored.push_str(&self.text[pos..offset]);
+            restored.push_str(&indent);
+            pos = offset;
+        }
+        restored.push_str(&self.text[pos..]);
+    }
+}
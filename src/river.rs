@@ -0,0 +1,184 @@
+//! The river layout of `keywordAlignment: river`.
+//!
+//! Top-level clause keywords are right-aligned so their first words end at a
+//! common column, with the first line of each clause after its keyword and
+//! the rest of the clause starting just past the river:
+//!
+//! ```sql
+//! SELECT a,
+//!        b
+//!   FROM t
+//!  WHERE x = 1
+//!    AND y = 2
+//! ```
+//!
+//! sqlformat puts each of these keywords at the start of a line with its
+//! clause indented below it, which this pass rearranges. Nested queries keep
+//! their layout and move along with the clause they are in.
+
+use crate::Configuration;
+use crate::Dialect;
+use crate::KeywordAlignment;
+use crate::lexer::continued_lines;
+
+/// Words starting the lines of the clauses that take part in the river.
+const CLAUSE_KEYWORDS: &[&str] = &[
+    "cross",
+    "delete",
+    "except",
+    "fetch",
+    "from",
+    "full",
+    "group",
+    "having",
+    "inner",
+    "insert",
+    "intersect",
+    "join",
+    "left",
+    "limit",
+    "minus",
+    "natural",
+    "offset",
+    "order",
+    "qualify",
+    "returning",
+    "right",
+    "select",
+    "set",
+    "union",
+    "update",
+    "values",
+    "where",
+    "window",
+];
+
+pub(crate) fn layout(formatted: String, config: &Configuration, dialect: Dialect) -> String {
+    if config.keyword_alignment == KeywordAlignment::Left {
+        return formatted;
+    }
+    let continued = continued_lines(&formatted, dialect);
+    let lines: Vec<_> = formatted.split('\n').collect();
+    let is_clause_start =
+        |i: usize| continued.binary_search(&i).is_err() && clause_keyword(lines[i]).is_some();
+    let river = River {
+        lines: &lines,
+        continued: &continued,
+        indent: config.indent(),
+    };
+    let mut result = Vec::with_capacity(lines.len());
+    let mut i = 0;
+    while i < lines.len() {
+        if !is_clause_start(i) {
+            result.push(lines[i].to_string());
+            i += 1;
+            continue;
+        }
+        // the clauses of one statement, each a keyword line and the more
+        // indented lines after it
+        let mut clauses = Vec::new();
+        loop {
+            let start = i;
+            i += 1;
+            while i < lines.len()
+                && (continued.binary_search(&i).is_ok()
+                    || lines[i].starts_with(char::is_whitespace))
+            {
+                i += 1;
+            }
+            clauses.push(start..i);
+            if lines[i - 1].trim_end().ends_with(';') || i == lines.len() || !is_clause_start(i) {
+                break;
+            }
+        }
+        let width = clauses
+            .iter()
+            .map(|clause| clause_keyword(lines[clause.start]).unwrap().len())
+            .max()
+            .unwrap();
+        for clause in clauses {
+            river.clause(clause, width, &mut result);
+        }
+    }
+    result.join("\n")
+}
+
+struct River<'a> {
+    lines: &'a [&'a str],
+    continued: &'a [usize],
+    indent: String,
+}
+
+impl River<'_> {
+    fn clause(&self, lines: std::ops::Range<usize>, width: usize, result: &mut Vec<String>) {
+        let keyword_line = self.lines[lines.start];
+        let keyword = clause_keyword(keyword_line).unwrap();
+        let mut line = format!("{}{keyword_line}", " ".repeat(width - keyword.len()));
+        let mut body = lines.start + 1..lines.end;
+        // the first line of the clause goes after the keyword
+        if let Some(first) = body.next() {
+            match self.clause_line(first) {
+                Some(rest) if !starts_with_logical_operator(rest) => {
+                    line.push(' ');
+                    line.push_str(rest);
+                    result.push(line);
+                }
+                _ => {
+                    result.push(line);
+                    result.push(self.reindent(first, width));
+                }
+            }
+        } else {
+            result.push(line);
+        }
+        for i in body {
+            result.push(self.reindent(i, width));
+        }
+    }
+
+    /// The line at `i` without its indentation, if it's indented exactly one
+    /// level and so starts a part of the clause.
+    fn clause_line(&self, i: usize) -> Option<&str> {
+        if self.continued.binary_search(&i).is_ok() {
+            return None;
+        }
+        let rest = self.lines[i].strip_prefix(&self.indent)?;
+        (!rest.starts_with(char::is_whitespace)).then_some(rest)
+    }
+
+    fn reindent(&self, i: usize, width: usize) -> String {
+        let line = self.lines[i];
+        if self.continued.binary_search(&i).is_ok() || line.trim().is_empty() {
+            return line.to_string();
+        }
+        if let Some(rest) = self.clause_line(i)
+            && starts_with_logical_operator(rest)
+        {
+            let operator = first_word(rest);
+            return format!("{}{rest}", " ".repeat(width - operator.len().min(width)));
+        }
+        let rest = line.strip_prefix(&self.indent).unwrap_or(line.trim_start());
+        format!("{}{rest}", " ".repeat(width + 1))
+    }
+}
+
+/// The first word of `line` when it starts a clause taking part in the river.
+fn clause_keyword(line: &str) -> Option<&str> {
+    let word = first_word(line);
+    CLAUSE_KEYWORDS
+        .iter()
+        .any(|keyword| word.eq_ignore_ascii_case(keyword))
+        .then_some(word)
+}
+
+fn starts_with_logical_operator(line: &str) -> bool {
+    let word = first_word(line);
+    word.eq_ignore_ascii_case("and") || word.eq_ignore_ascii_case("or")
+}
+
+fn first_word(line: &str) -> &str {
+    let end = line
+        .find(|c: char| !(c.is_ascii_alphabetic() || c == '_'))
+        .unwrap_or(line.len());
+    &line[..end]
+}
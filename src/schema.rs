@@ -0,0 +1,162 @@
+//! The JSON schema of the plugin's configuration, generated from the doc
+//! comments and defaults of `Configuration` so that `schema.json` can't drift
+//! from the options the plugin reads. Only built with the `schema` feature,
+//! which the tests turn on, so the plugin doesn't carry schemars.
+//!
+//! A few options are read differently than they're stored, or only on the way
+//! to a `Configuration`, so they're described by the types here instead.
+//! Those types are never built, only described.
+#![allow(dead_code)]
+
+use std::collections::BTreeMap;
+
+use schemars::JsonSchema;
+use schemars::Schema;
+use schemars::generate::SchemaSettings;
+use schemars::transform::RecursiveTransform;
+use serde_json::Map;
+use serde_json::Value;
+
+use crate::Configuration;
+use crate::Preset;
+
+/// The options resolved before the others, which never make it into a
+/// `Configuration`.
+#[derive(JsonSchema)]
+#[serde(rename_all = "camelCase")]
+struct Leading {
+    /// A bundle of defaults for the layout options. Options set explicitly
+    /// take precedence.
+    #[schemars(default = "default_preset")]
+    preset: Preset,
+    /// The path of a .sqlfluff file to read the dialect, indentation, keyword
+    /// and identifier capitalisation, operator position and column aliasing
+    /// from. Options set here take precedence. A relative path is read from
+    /// the directory dprint runs in. Not available to the WebAssembly plugin,
+    /// which can't read files.
+    sqlfluff_config_path: String,
+    /// Options for the migrations of each kind, recognized by the file names
+    /// of Flyway (V1__name.sql, U1__name.sql, R__name.sql) and of tools like
+    /// sqlx and golang-migrate (1_name.up.sql, 1_name.down.sql). Overrides
+    /// take precedence.
+    frameworks: Frameworks,
+}
+
+fn default_preset() -> Preset {
+    Preset::Default
+}
+
+#[derive(JsonSchema)]
+#[schemars(deny_unknown_fields)]
+struct Frameworks {
+    /// Options for migrations applying a change.
+    #[serde(default)]
+    up: Map<String, Value>,
+    /// Options for migrations reverting a change.
+    #[serde(default)]
+    down: Map<String, Value>,
+    /// Options for Flyway repeatable migrations.
+    #[serde(default)]
+    repeatable: Map<String, Value>,
+}
+
+/// An entry of `overrides`, with the options next to the patterns.
+#[derive(JsonSchema)]
+pub(crate) struct Override {
+    /// The patterns of the files to use the options for.
+    files: Patterns,
+}
+
+#[derive(JsonSchema)]
+#[serde(untagged)]
+pub(crate) enum Patterns {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// `params` as written in the configuration.
+#[derive(JsonSchema)]
+#[serde(untagged)]
+pub(crate) enum Params {
+    Indexed(Vec<ParamValue>),
+    Named(BTreeMap<String, ParamValue>),
+}
+
+#[derive(JsonSchema)]
+#[serde(untagged)]
+pub(crate) enum ParamValue {
+    String(String),
+    Number(f64),
+    Boolean(bool),
+}
+
+/// dprint's `NewLineKind`, as written in the configuration.
+#[derive(JsonSchema)]
+pub(crate) enum NewLineKind {
+    /// For each file, uses the newline kind used by most of its lines.
+    #[serde(rename = "auto")]
+    Auto,
+    /// Uses carriage return, line feed.
+    #[serde(rename = "crlf")]
+    CarriageReturnLineFeed,
+    /// Uses line feed.
+    #[serde(rename = "lf")]
+    LineFeed,
+    /// Uses the system standard (ex. crlf on Windows).
+    #[serde(rename = "system")]
+    System,
+}
+
+/// Removes the default of the schema, for options whose default isn't one of
+/// the values they can be written as.
+pub(crate) fn without_default(schema: &mut Schema) {
+    schema.remove("default");
+}
+
+/// Joins the lines of descriptions taken from doc comments, which editors
+/// would show as written.
+fn unwrap_description(schema: &mut Schema) {
+    if let Some(Value::String(description)) = schema.get_mut("description") {
+        *description = description
+            .split("\n\n")
+            .map(|paragraph| paragraph.replace('\n', " "))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+    }
+}
+
+/// The schema published as `schema.json`.
+pub fn config_schema() -> Value {
+    let mut generator = SchemaSettings::draft07()
+        .with(|settings| {
+            settings.inline_subschemas = true;
+            settings
+                .transforms
+                .push(Box::new(RecursiveTransform(unwrap_description)));
+        })
+        .into_generator();
+    let leading = generator.root_schema_for::<Leading>();
+    let mut schema = generator.root_schema_for::<Configuration>();
+    // the leading options first, the way they're read
+    let mut properties: Map<String, Value> = leading
+        .get("properties")
+        .and_then(Value::as_object)
+        .cloned()
+        .unwrap_or_default();
+    if let Some(Value::Object(rest)) = schema.remove("properties") {
+        properties.extend(rest);
+    }
+    let mut root = Map::new();
+    root.insert(
+        "$schema".to_string(),
+        "http://json-schema.org/draft-07/schema#".into(),
+    );
+    // the version of each release is put in by CI, along with Cargo.toml's
+    root.insert(
+        "$id".to_string(),
+        "https://plugins.dprint.dev/daaku/dprint-plugin-sql/v1.42.0/schema.json".into(),
+    );
+    root.insert("type".to_string(), "object".into());
+    root.insert("properties".to_string(), properties.into());
+    root.into()
+}
@@ -0,0 +1,43 @@
+//! Adding or removing the semicolon after the last statement, per
+//! `requireSemicolons`.
+
+use std::borrow::Cow;
+
+use crate::Dialect;
+use crate::RequireSemicolons;
+use crate::lexer::TokenKind;
+use crate::lexer::tokenize;
+
+pub(crate) fn normalize(text: &str, mode: RequireSemicolons, dialect: Dialect) -> Cow<'_, str> {
+    if mode == RequireSemicolons::Preserve {
+        return Cow::Borrowed(text);
+    }
+
+    let tokens = tokenize(text, dialect);
+    let significant: Vec<_> = tokens.iter().filter(|t| !t.is_trivia()).collect();
+    let Some(last) = significant.last() else {
+        return Cow::Borrowed(text);
+    };
+    let ends_with_semicolon = last.kind == TokenKind::Semicolon;
+    match mode {
+        // rows of a `COPY` come after its semicolon
+        RequireSemicolons::Always if !ends_with_semicolon && last.kind != TokenKind::CopyData => {
+            let end = last.start + last.text.len();
+            Cow::Owned(format!("{};{}", &text[..end], &text[end..]))
+        }
+        RequireSemicolons::Never if ends_with_semicolon => {
+            let statements = significant
+                .split(|t| t.kind == TokenKind::Semicolon)
+                .filter(|statement| !statement.is_empty())
+                .count();
+            // dropping it elsewhere would join the last two statements
+            if statements == 1 {
+                let end = last.start + last.text.len();
+                Cow::Owned(format!("{}{}", &text[..last.start], &text[end..]))
+            } else {
+                Cow::Borrowed(text)
+            }
+        }
+        _ => Cow::Borrowed(text),
+    }
+}
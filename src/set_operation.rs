@@ -0,0 +1,292 @@
+//! Layout of `UNION`, `INTERSECT` and `EXCEPT`, per `linesAroundSetOperators`
+//! and `parenthesizeSetOperands`.
+//!
+//! sqlformat puts a set operator at the start of a line but leaves a
+//! parenthesized operand after it on the same line, so the operands of one
+//! query don't line up. Each operator gets a line of its own here, with the
+//! configured blank lines around it. Optionally each operand is wrapped in
+//! parentheses before formatting, which sqlformat then lays out like any
+//! other parenthesized query:
+//!
+//! ```sql
+//! (
+//!   select a from t1
+//! )
+//! union all
+//! (
+//!   select a from t2
+//! )
+//! order by 1
+//! ```
+//!
+//! Operators sqlformat kept inline, inside short subqueries, stay there.
+
+use std::collections::BTreeMap;
+
+use crate::Configuration;
+use crate::Dialect;
+use crate::lexer::Token;
+use crate::lexer::TokenKind;
+use crate::lexer::continued_lines;
+use crate::lexer::tokenize;
+use crate::trace;
+
+const SET_OPERATORS: &[&str] = &["except", "intersect", "minus", "union"];
+
+/// Keywords that apply to the result of the whole query when they follow its
+/// last operand.
+const QUERY_CLAUSES: &[&str] = &["fetch", "limit", "offset", "order"];
+
+/// Puts each operator on a line of its own, between the configured number of
+/// blank lines.
+pub(crate) fn layout(formatted: String, config: &Configuration, dialect: Dialect) -> String {
+    let formatted = outdent_operands(&formatted, dialect);
+    let tokens = tokenize(&formatted, dialect);
+    let breaks = "\n".repeat(usize::from(config.lines_around_set_operators) + 1);
+    let mut result = String::with_capacity(formatted.len());
+    let mut pos = 0;
+    for i in 0..tokens.len() {
+        if !starts_line(&tokens, i) {
+            continue;
+        }
+        trace::decided("set operator: on a line of its own");
+        let base = line_indent(&formatted, tokens[i].start);
+        result.push_str(&formatted[pos..tokens[i - 1].start]);
+        result.push_str(&breaks);
+        result.push_str(base);
+        pos = tokens[i].start;
+
+        let end = operator_end(&tokens, i);
+        let after = &tokens[end + 1..];
+        let rest_of_line = after
+            .iter()
+            .take_while(|t| !(t.kind == TokenKind::Whitespace && t.text.contains('\n')))
+            .find(|t| t.kind != TokenKind::Whitespace);
+        if rest_of_line.is_some_and(|t| t.is_trivia() || t.kind == TokenKind::Semicolon) {
+            continue;
+        }
+        let Some(next) = after.first() else {
+            continue;
+        };
+        let operator_end = tokens[end].start + tokens[end].text.len();
+        result.push_str(&formatted[pos..operator_end]);
+        result.push_str(&breaks);
+        if next.kind == TokenKind::Whitespace {
+            // the operand's indentation, when it's already on a line below
+            let indent = match next.text.rsplit_once('\n') {
+                Some((_, indent)) => indent,
+                None => base,
+            };
+            result.push_str(indent);
+            pos = next.start + next.text.len();
+        } else {
+            result.push_str(base);
+            pos = next.start;
+        }
+    }
+    result.push_str(&formatted[pos..]);
+    result
+}
+
+/// Moves operands sqlformat indented below their operator, as it does after
+/// `EXCEPT`, back to the operator's indentation.
+fn outdent_operands(formatted: &str, dialect: Dialect) -> String {
+    let tokens = tokenize(formatted, dialect);
+    let continued = continued_lines(formatted, dialect);
+    let is_continued = |i: usize| continued.binary_search(&i).is_ok();
+    // the lines holding nothing but an operator
+    let mut operator_lines = Vec::new();
+    let mut line = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        if starts_line(&tokens, i)
+            && tokens
+                .get(operator_end(&tokens, i) + 1)
+                .is_some_and(|t| t.kind == TokenKind::Whitespace && t.text.contains('\n'))
+        {
+            operator_lines.push(line);
+        }
+        line += token.text.matches('\n').count();
+    }
+    let mut lines: Vec<_> = formatted.split('\n').collect();
+    let mut i = 0;
+    while i + 1 < lines.len() {
+        if operator_lines.binary_search(&i).is_err() {
+            i += 1;
+            continue;
+        }
+        let base = indent_len(lines[i]);
+        let extra = indent_len(lines[i + 1]).saturating_sub(base);
+        i += 1;
+        if extra == 0 {
+            continue;
+        }
+        while i < lines.len()
+            && (is_continued(i) || lines[i].trim().is_empty() || indent_len(lines[i]) > base)
+        {
+            if !is_continued(i) {
+                lines[i] = &lines[i][extra.min(indent_len(lines[i]))..];
+            }
+            i += 1;
+        }
+    }
+    lines.join("\n")
+}
+
+fn indent_len(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// Wraps the operands of the queries in `text` in parentheses, before
+/// sqlformat lays them out as any other parenthesized query.
+pub(crate) fn parenthesize(text: &str, config: &Configuration, dialect: Dialect) -> String {
+    if !config.parenthesize_set_operands {
+        return text.to_string();
+    }
+    let tokens = tokenize(text, dialect);
+
+    // the operators of each query, keyed by what encloses it: the opening
+    // parenthesis or, outside of any, the statement
+    let mut queries = BTreeMap::new();
+    let mut open = Vec::new();
+    let mut statement = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        match token.kind {
+            TokenKind::OpenParen => open.push(i),
+            TokenKind::CloseParen => {
+                open.pop();
+            }
+            TokenKind::Semicolon if open.is_empty() => statement += 1,
+            _ if is_operator(&tokens, i) => {
+                queries
+                    .entry((open.last().copied(), statement))
+                    .or_insert_with(Vec::new)
+                    .push(i);
+            }
+            _ => {}
+        }
+    }
+
+    let mut parens = Vec::new();
+    for operators in queries.values() {
+        if let Some(start) = first_operand_start(&tokens, operators[0]) {
+            parens.push((start, last_before(&tokens, operators[0])));
+        }
+        for (k, &operator) in operators.iter().enumerate() {
+            let Some(start) = next_significant(&tokens, operator_end(&tokens, operator) + 1) else {
+                continue;
+            };
+            let end = match operators.get(k + 1) {
+                Some(&next) => last_before(&tokens, next),
+                None => last_before(&tokens, query_end(&tokens, start)),
+            };
+            if tokens[start].is_word("select") && end > start {
+                parens.push((start, end));
+            }
+        }
+    }
+    let mut inserts: Vec<_> = parens
+        .into_iter()
+        .flat_map(|(start, end)| {
+            let end = &tokens[end];
+            [
+                (tokens[start].start, '('),
+                (end.start + end.text.len(), ')'),
+            ]
+        })
+        .collect();
+    // closing an operand before opening the next at the same place
+    inserts.sort_by_key(|&(offset, paren)| (offset, paren == '('));
+
+    let mut result = String::with_capacity(text.len() + inserts.len());
+    let mut pos = 0;
+    for (offset, paren) in inserts {
+        result.push_str(&text[pos..offset]);
+        result.push(paren);
+        pos = offset;
+    }
+    result.push_str(&text[pos..]);
+    result
+}
+
+/// Whether `tokens[i]` is a set operator followed by a query.
+fn is_operator(tokens: &[Token], i: usize) -> bool {
+    if !SET_OPERATORS.iter().any(|w| tokens[i].is_word(w)) {
+        return false;
+    }
+    // BigQuery's `SELECT * EXCEPT (...)` leaves out columns
+    let previous = tokens[..i].iter().rev().find(|t| !t.is_trivia());
+    let next = next_significant(tokens, operator_end(tokens, i) + 1).map(|next| &tokens[next]);
+    !previous.is_some_and(|t| t.kind == TokenKind::Operator)
+        && next.is_some_and(|t| t.is_word("select") || t.kind == TokenKind::OpenParen)
+}
+
+/// Whether `tokens[i]` is a set operator at the start of a line.
+fn starts_line(tokens: &[Token], i: usize) -> bool {
+    i > 0
+        && tokens[i - 1].kind == TokenKind::Whitespace
+        && tokens[i - 1].text.contains('\n')
+        && is_operator(tokens, i)
+}
+
+/// The index of the last word of the operator at `tokens[i]`, which is its
+/// `ALL` or `DISTINCT` if it has one.
+fn operator_end(tokens: &[Token], i: usize) -> usize {
+    match next_significant(tokens, i + 1) {
+        Some(next) if tokens[next].is_word("all") || tokens[next].is_word("distinct") => next,
+        _ => i,
+    }
+}
+
+/// The `SELECT` starting the first operand of the query whose first operator
+/// is at `tokens[operator]`, unless the operand is already parenthesized.
+fn first_operand_start(tokens: &[Token], operator: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    for i in (0..operator).rev() {
+        match tokens[i].kind {
+            TokenKind::CloseParen => depth += 1,
+            TokenKind::OpenParen if depth == 0 => return None,
+            TokenKind::OpenParen => depth -= 1,
+            TokenKind::Semicolon if depth == 0 => return None,
+            _ if depth == 0 && tokens[i].is_word("select") => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The index of the token just after the last operand starting at
+/// `tokens[start]`.
+fn query_end(tokens: &[Token], start: usize) -> usize {
+    let mut depth = 0usize;
+    for (i, token) in tokens.iter().enumerate().skip(start) {
+        match token.kind {
+            TokenKind::OpenParen => depth += 1,
+            TokenKind::CloseParen if depth == 0 => return i,
+            TokenKind::CloseParen => depth -= 1,
+            TokenKind::Semicolon if depth == 0 => return i,
+            TokenKind::Word
+                if depth == 0 && i > start && QUERY_CLAUSES.iter().any(|w| token.is_word(w)) =>
+            {
+                return i;
+            }
+            _ => {}
+        }
+    }
+    tokens.len()
+}
+
+/// The index of the last significant token before `tokens[i]`.
+fn last_before(tokens: &[Token], i: usize) -> usize {
+    (0..i).rev().find(|&i| !tokens[i].is_trivia()).unwrap_or(0)
+}
+
+fn next_significant(tokens: &[Token], start: usize) -> Option<usize> {
+    (start..tokens.len()).find(|&i| !tokens[i].is_trivia())
+}
+
+/// The indentation of the line `offset` is on.
+fn line_indent(text: &str, offset: usize) -> &str {
+    let line_start = text[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let line = &text[line_start..offset];
+    &line[..line.len() - line.trim_start().len()]
+}
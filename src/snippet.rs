@@ -0,0 +1,84 @@
+//! Formatting SQL embedded in other files, which is indented along with the
+//! code around it.
+
+use crate::Configuration;
+use crate::Dialect;
+use crate::lexer::continued_lines;
+
+/// How a snippet sits in the file it was taken from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SnippetOptions {
+    /// The column the snippet's lines start at, which the formatted lines are
+    /// indented to.
+    pub indent_offset: usize,
+    /// Leave out the newline at the end of the formatted snippet.
+    pub skip_trailing_newline: bool,
+}
+
+/// Removes the indentation of spaces and tabs common to the lines of `text`,
+/// except those inside literals and comments.
+pub(crate) fn dedent(text: &str, dialect: Dialect) -> String {
+    let continued = continued_lines(text, dialect);
+    let lines: Vec<_> = text.split('\n').collect();
+    let is_code =
+        |i: usize, line: &str| continued.binary_search(&i).is_err() && !line.trim().is_empty();
+    let common = lines
+        .iter()
+        .enumerate()
+        .filter(|&(i, line)| is_code(i, line))
+        // only spaces and tabs, which the lines can be sliced after
+        .map(|(_, line)| line.len() - line.trim_start_matches([' ', '\t']).len())
+        .min()
+        .unwrap_or(0);
+    lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            if is_code(i, line) {
+                &line[common..]
+            } else if continued.binary_search(&i).is_err() {
+                line.trim_start()
+            } else {
+                line
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Indents the lines of `formatted` to `column`, except those inside
+/// literals and comments.
+pub(crate) fn indent(
+    formatted: &str,
+    column: usize,
+    config: &Configuration,
+    dialect: Dialect,
+) -> String {
+    if column == 0 {
+        return formatted.to_string();
+    }
+    let indent = if config.use_tabs {
+        let width = usize::from(config.indent_width.max(1));
+        format!(
+            "{}{}",
+            "\t".repeat(column / width),
+            " ".repeat(column % width)
+        )
+    } else {
+        " ".repeat(column)
+    };
+    let continued = continued_lines(formatted, dialect);
+    formatted
+        .split('\n')
+        .enumerate()
+        .map(|(i, line)| {
+            let line_text = line.trim_end_matches('\r');
+            if line_text.is_empty() || continued.binary_search(&i).is_ok() {
+                line.to_string()
+            } else {
+                format!("{indent}{line}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
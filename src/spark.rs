@@ -0,0 +1,77 @@
+//! Syntax of Spark SQL and HiveQL that sqlformat doesn't know.
+//!
+//! The column aliases of `LATERAL VIEW posexplode(tags) t AS pos, tag` would
+//! be broken onto lines of their own like the tables of a `FROM` list, so
+//! they're protected.
+
+use std::borrow::Cow;
+
+use crate::Dialect;
+use crate::lexer::Token;
+use crate::lexer::TokenKind;
+use crate::lexer::matching_paren;
+use crate::lexer::tokenize;
+use crate::protect::Protected;
+
+pub(crate) fn protect<'a>(
+    text: &'a str,
+    dialect: Dialect,
+    protected: &mut Protected,
+) -> Cow<'a, str> {
+    if dialect != Dialect::Spark {
+        return Cow::Borrowed(text);
+    }
+    let tokens = tokenize(text, dialect);
+    let significant: Vec<_> = (0..tokens.len())
+        .filter(|&i| !tokens[i].is_trivia())
+        .collect();
+    let mut result = String::with_capacity(text.len());
+    let mut pos = 0;
+    for (n, &i) in significant.iter().enumerate() {
+        let is_view =
+            tokens[i].is_word("view") && n > 0 && tokens[significant[n - 1]].is_word("lateral");
+        let Some((first, last)) = is_view.then(|| aliases(&tokens, i)).flatten() else {
+            continue;
+        };
+        let end = tokens[last].start + tokens[last].text.len();
+        result.push_str(&text[pos..tokens[first].start]);
+        result.push_str(&protected.insert(text[tokens[first].start..end].to_string()));
+        pos = end;
+    }
+    if pos == 0 {
+        return Cow::Borrowed(text);
+    }
+    result.push_str(&text[pos..]);
+    Cow::Owned(result)
+}
+
+/// The first and last of two or more column aliases after the `AS` of the
+/// lateral view whose `VIEW` is at `tokens[view]`.
+fn aliases(tokens: &[Token], view: usize) -> Option<(usize, usize)> {
+    let open = (view + 1..tokens.len()).find(|&i| tokens[i].kind == TokenKind::OpenParen)?;
+    let close = matching_paren(tokens, open)?;
+    let mut significant = (close + 1..tokens.len()).filter(|&i| !tokens[i].is_trivia());
+    let mut next = significant.next()?;
+    if !tokens[next].is_word("as") {
+        // the table alias
+        next = significant.next()?;
+    }
+    if !tokens[next].is_word("as") {
+        return None;
+    }
+    let first = significant.next()?;
+    let mut last = first;
+    while let Some(comma) = significant.next() {
+        if tokens[comma].kind != TokenKind::Comma {
+            break;
+        }
+        last = significant.next()?;
+    }
+    let is_name = |i: usize| {
+        matches!(
+            tokens[i].kind,
+            TokenKind::Word | TokenKind::QuotedIdentifier
+        )
+    };
+    (last != first && is_name(first) && is_name(last)).then_some((first, last))
+}
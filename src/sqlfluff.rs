@@ -0,0 +1,110 @@
+//! Reading the layout rules of a `.sqlfluff` file, for `sqlfluffConfigPath`.
+//!
+//! The file is INI, with sections like `[sqlfluff:indentation]`. Only the
+//! rules with an equivalent option here are read, mapped to the option keys
+//! so that keys set in the plugin configuration take precedence.
+
+use dprint_core::configuration::ConfigKeyMap;
+use dprint_core::configuration::ConfigKeyValue;
+
+/// The option keys and values for the rules in `text`, and descriptions of
+/// the rules that can't be followed.
+pub(crate) fn options(text: &str) -> (ConfigKeyMap, Vec<String>) {
+    let mut options = ConfigKeyMap::new();
+    let mut unsupported = Vec::new();
+    let mut section = String::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(['#', ';']) {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            section = name.trim().to_ascii_lowercase();
+            continue;
+        }
+        let Some((key, value)) = line.split_once(['=', ':']) else {
+            continue;
+        };
+        let key = key.trim().to_ascii_lowercase();
+        let value = value.trim().to_ascii_lowercase();
+        let option: Option<(&str, ConfigKeyValue)> = match (section.as_str(), key.as_str()) {
+            ("sqlfluff", "dialect") => match dialect(&value) {
+                Some(dialect) => Some(("dialect", dialect.into())),
+                None => {
+                    unsupported.push(format!("dialect {value}"));
+                    None
+                }
+            },
+            ("sqlfluff:indentation", "indent_unit") => match value.as_str() {
+                "tab" => Some(("useTabs", true.into())),
+                "space" => Some(("useTabs", false.into())),
+                _ => None,
+            },
+            ("sqlfluff:indentation", "tab_space_size") => value
+                .parse::<i32>()
+                .ok()
+                .map(|size| ("indentWidth", size.into())),
+            (
+                "sqlfluff:rules:capitalisation.keywords" | "sqlfluff:rules:l010",
+                "capitalisation_policy",
+            ) => match value.as_str() {
+                "upper" => Some(("uppercase", true.into())),
+                "lower" => Some(("uppercase", false.into())),
+                // the formatter has no way to keep whatever is most common
+                "consistent" => None,
+                _ => {
+                    unsupported.push(format!("keyword capitalisation_policy {value}"));
+                    None
+                }
+            },
+            (
+                "sqlfluff:rules:capitalisation.identifiers" | "sqlfluff:rules:l014",
+                "extended_capitalisation_policy",
+            ) => match value.as_str() {
+                "upper" => Some(("identifierCase", "upper".into())),
+                "lower" => Some(("identifierCase", "lower".into())),
+                "consistent" => None,
+                _ => {
+                    unsupported.push(format!("identifier capitalisation_policy {value}"));
+                    None
+                }
+            },
+            ("sqlfluff:layout:type:comma", "line_position") => {
+                if value == "leading" {
+                    unsupported.push("leading commas".to_string());
+                }
+                None
+            }
+            ("sqlfluff:layout:type:binary_operator", "line_position") => match value.as_str() {
+                "leading" => Some(("logicalOperatorPosition", "leadingNewline".into())),
+                "trailing" => Some(("logicalOperatorPosition", "trailingNewline".into())),
+                _ => None,
+            },
+            ("sqlfluff:rules:aliasing.column" | "sqlfluff:rules:l012", "aliasing") => {
+                match value.as_str() {
+                    "explicit" => Some(("asKeyword", "always".into())),
+                    "implicit" => Some(("asKeyword", "never".into())),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        if let Some((key, value)) = option {
+            options.insert(key.to_string(), value);
+        }
+    }
+    (options, unsupported)
+}
+
+fn dialect(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "ansi" => "generic",
+        "postgres" => "postgresql",
+        "mysql" | "mariadb" => "mysql",
+        "tsql" => "sqlserver",
+        "snowflake" => "snowflake",
+        "bigquery" => "bigquery",
+        "sparksql" | "hive" | "databricks" => "spark",
+        _ => return None,
+    })
+}
@@ -0,0 +1,48 @@
+//! Detection of output that changes again when formatted a second time.
+
+use crate::diagnostics::line_column;
+
+/// An error describing where formatting already formatted text changed it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnstableFormatError {
+    /// 1-based line number of the first difference, in the first output.
+    pub line: usize,
+    /// 1-based column number of the first difference, counted in characters.
+    pub column: usize,
+    /// The line containing the difference after formatting once.
+    pub first: String,
+    /// The same line after formatting twice.
+    pub second: String,
+}
+
+impl std::fmt::Display for UnstableFormatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Formatting is not stable at line {}, column {}:\n  once:  {}\n  twice: {}",
+            self.line, self.column, self.first, self.second
+        )
+    }
+}
+
+impl std::error::Error for UnstableFormatError {}
+
+pub(crate) fn compare(first: &str, second: &str) -> Result<(), UnstableFormatError> {
+    let Some(offset) = first
+        .char_indices()
+        .zip(second.chars())
+        .find(|((_, a), b)| a != b)
+        .map(|((i, _), _)| i)
+        .or_else(|| (first.len() != second.len()).then(|| first.len().min(second.len())))
+    else {
+        return Ok(());
+    };
+    let (line, column) = line_column(first, offset);
+    let line_at = |text: &str| text.lines().nth(line - 1).unwrap_or_default().to_string();
+    Err(UnstableFormatError {
+        line,
+        column,
+        first: line_at(first),
+        second: line_at(second),
+    })
+}
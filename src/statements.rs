@@ -0,0 +1,175 @@
+//! Splitting text into the statements it contains.
+
+use std::ops::Range;
+
+use crate::Dialect;
+use crate::delimiter;
+use crate::lexer::Token;
+use crate::lexer::TokenKind;
+use crate::lexer::ends_statement;
+use crate::lexer::tokenize;
+
+/// A statement found by `split_statements`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatementSpan {
+    /// The byte range of the statement in the text, including its terminator
+    /// and any comments before it or on the same line after it.
+    pub range: Range<usize>,
+    pub kind: StatementKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatementKind {
+    /// Defines or changes the schema, like `CREATE TABLE` or `DROP INDEX`.
+    Ddl,
+    /// Queries or changes data, like `SELECT` or `INSERT`.
+    Dml,
+    /// Nothing but comments.
+    CommentOnly,
+    /// Anything else, like `SET` or `BEGIN`.
+    Other,
+}
+
+const DDL: &[&str] = &["alter", "comment", "create", "drop", "rename", "truncate"];
+
+const DML: &[&str] = &[
+    "delete", "insert", "merge", "replace", "select", "table", "update", "upsert", "values", "with",
+];
+
+pub(crate) struct Statement<'a> {
+    /// The statement including its `;` and any comment on the same line after
+    /// it, without surrounding blank lines.
+    pub text: &'a str,
+    /// The number of blank lines between this statement and the previous one.
+    pub blank_lines_before: usize,
+}
+
+pub(crate) fn split(text: &str, dialect: Dialect) -> Vec<Statement<'_>> {
+    let tokens = tokenize(text, dialect);
+    let mut statements = Vec::new();
+    let mut start = 0;
+    let mut blank_lines_before = 0;
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = &tokens[i];
+        i += 1;
+        if token.kind == TokenKind::Whitespace && start == token.start {
+            // leading whitespace belongs to no statement
+            blank_lines_before = token.text.matches('\n').count().saturating_sub(1);
+            start += token.text.len();
+            continue;
+        }
+        if !ends_statement(&tokens, i - 1) {
+            continue;
+        }
+        // keep comments on the same line with the statement they follow
+        let mut end = token.start + token.text.len();
+        for next in tokens[i..]
+            .iter()
+            .take_while(|t| t.is_trivia() && !t.text.contains('\n'))
+        {
+            if next.kind != TokenKind::Whitespace {
+                end = next.start + next.text.len();
+            }
+        }
+        statements.push(Statement {
+            text: &text[start..end],
+            blank_lines_before,
+        });
+        start = end;
+        blank_lines_before = 0;
+        i = tokens.partition_point(|t| t.start < end);
+    }
+    if !text[start..].trim().is_empty() {
+        statements.push(Statement {
+            text: text[start..].trim_end(),
+            blank_lines_before,
+        });
+    }
+    statements
+}
+
+/// Splits `text` after each `;` and the comments on the same line after it,
+/// the way sqlformat does, so formatting the pieces separately and joining
+/// them gives what formatting the whole would.
+pub(crate) fn pieces(text: &str, dialect: Dialect) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    let tokens = tokenize(text, dialect);
+    for (i, token) in tokens.iter().enumerate() {
+        if token.start < start || !ends_statement(&tokens, i) {
+            continue;
+        }
+        let mut end = token.start + token.text.len();
+        for next in tokens[i + 1..]
+            .iter()
+            .take_while(|t| t.is_trivia() && !t.text.contains('\n'))
+        {
+            if next.kind != TokenKind::Whitespace {
+                end = next.start + next.text.len();
+            }
+        }
+        pieces.push(&text[start..end]);
+        start = end;
+    }
+    if !text[start..].trim().is_empty() {
+        pieces.push(&text[start..]);
+    }
+    pieces
+}
+
+pub(crate) fn spans(text: &str, dialect: Dialect) -> Vec<StatementSpan> {
+    let Some(chunks) = delimiter::split(text, dialect) else {
+        return split(text, dialect)
+            .iter()
+            .map(|statement| span(text, statement.text, 0, dialect))
+            .collect();
+    };
+    let mut spans = Vec::new();
+    for chunk in chunks {
+        match chunk {
+            delimiter::Chunk::Directive(_) => {}
+            delimiter::Chunk::Statements(statements) => {
+                for statement in split(statements, Dialect::MySql) {
+                    spans.push(span(text, statement.text, 0, Dialect::MySql));
+                }
+            }
+            delimiter::Chunk::Statement {
+                text: statement,
+                delimiter,
+            } => {
+                let statement = statement.trim_start();
+                let len = delimiter.map_or(0, str::len);
+                spans.push(span(text, statement, len, Dialect::MySql));
+            }
+        }
+    }
+    spans
+}
+
+/// The span of `statement`, a slice of `text` followed by a delimiter of
+/// `delimiter_len` bytes.
+fn span(text: &str, statement: &str, delimiter_len: usize, dialect: Dialect) -> StatementSpan {
+    let start = statement.as_ptr() as usize - text.as_ptr() as usize;
+    let tokens = tokenize(statement, dialect);
+    StatementSpan {
+        range: start..start + statement.len() + delimiter_len,
+        kind: kind(&tokens),
+    }
+}
+
+fn kind(tokens: &[Token]) -> StatementKind {
+    let Some(first) = tokens
+        .iter()
+        .find(|t| !t.is_trivia() && t.kind != TokenKind::OpenParen)
+    else {
+        return StatementKind::CommentOnly;
+    };
+    if DDL.iter().any(|word| first.is_word(word)) {
+        StatementKind::Ddl
+    } else if DML.iter().any(|word| first.is_word(word)) {
+        StatementKind::Dml
+    } else {
+        StatementKind::Other
+    }
+}
@@ -0,0 +1,215 @@
+//! Formatting statements as they are read, for files too large to hold in
+//! memory as a whole.
+
+use std::collections::VecDeque;
+use std::io::BufRead;
+
+use anyhow::Result;
+use dprint_core::plugins::NullCancellationToken;
+
+use crate::Configuration;
+use crate::RequireSemicolons;
+use crate::format_checked;
+use crate::lexer::TokenKind;
+use crate::lexer::reads_copy_data;
+use crate::lexer::tokenize;
+use crate::statements;
+
+/// An iterator over the formatted statements of a reader, returned by
+/// `format_statements`.
+///
+/// Each item is a statement with its trailing newline, preceded by the blank
+/// lines separating it from the previous one, or a row of a `COPY ... FROM
+/// stdin`, so writing the items in order gives the formatted file.
+pub struct FormattedStatements<R> {
+    reader: R,
+    config: Configuration,
+    /// The configuration for every statement but the last, which leaves the
+    /// semicolons between statements alone.
+    inner_config: Configuration,
+    /// Text read but not yet formatted, starting after the last complete
+    /// statement.
+    buffer: String,
+    /// The delimiter the buffer has to read before it can end another
+    /// statement, when it ends inside a string or comment.
+    missing_delimiter: Option<String>,
+    /// Whether the lines read are the rows of a `COPY`, which are written as
+    /// they are read.
+    copy_rows: bool,
+    formatted: VecDeque<Result<String>>,
+    first: bool,
+    done: bool,
+}
+
+impl<R: BufRead> FormattedStatements<R> {
+    pub(crate) fn new(reader: R, config: &Configuration) -> Self {
+        let config = Configuration {
+            overrides: Vec::new(),
+            ..config.clone()
+        };
+        let inner_config = Configuration {
+            require_semicolons: RequireSemicolons::Preserve,
+            insert_final_newline: true,
+            ..config.clone()
+        };
+        Self {
+            reader,
+            config,
+            inner_config,
+            buffer: String::new(),
+            missing_delimiter: None,
+            copy_rows: false,
+            formatted: VecDeque::new(),
+            first: true,
+            done: false,
+        }
+    }
+
+    /// Reads lines until at least one statement is complete or the reader
+    /// runs out.
+    fn read_statements(&mut self) {
+        loop {
+            let start = self.buffer.len();
+            match self.reader.read_line(&mut self.buffer) {
+                Ok(0) => {
+                    self.done = true;
+                    self.format_rest();
+                    return;
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    self.done = true;
+                    self.formatted.push_back(Err(err.into()));
+                    return;
+                }
+            }
+            if self.copy_rows {
+                let row = std::mem::take(&mut self.buffer);
+                self.copy_rows = row.trim_end_matches(['\r', '\n']) != "\\.";
+                self.formatted.push_back(Ok(row));
+                return;
+            }
+            let line = &self.buffer[start..];
+            if self
+                .missing_delimiter
+                .as_ref()
+                .is_some_and(|delimiter| line.contains(delimiter.as_str()))
+            {
+                self.missing_delimiter = None;
+            }
+            // only a line with a semicolon can complete a statement, and only
+            // once the string or comment the buffer ended inside is closed
+            if self.missing_delimiter.is_none() && line.contains(';') && self.format_complete() {
+                return;
+            }
+        }
+    }
+
+    /// Formats the complete statements at the start of the buffer, returning
+    /// false if there are none.
+    fn format_complete(&mut self) -> bool {
+        let dialect = self.config.dialect;
+        let statements = statements::split(&self.buffer, dialect);
+        let complete = statements
+            .iter()
+            .take_while(|statement| {
+                let tokens = tokenize(statement.text, dialect);
+                tokens
+                    .iter()
+                    .rfind(|t| !t.is_trivia())
+                    .is_some_and(|t| t.kind == TokenKind::Semicolon)
+            })
+            .count();
+        let Some(last) = statements[..complete].last() else {
+            self.find_missing_delimiter();
+            return false;
+        };
+        let end = last.text.as_ptr() as usize - self.buffer.as_ptr() as usize + last.text.len();
+        // the rows of a `COPY` start on the line after its semicolon
+        self.copy_rows =
+            self.buffer[end..].trim().is_empty() && reads_copy_data(&tokenize(last.text, dialect));
+        let formatted: Vec<_> = statements[..complete]
+            .iter()
+            .map(|statement| {
+                let formatted =
+                    format_checked(statement.text, &self.inner_config, &NullCancellationToken);
+                (formatted, statement.blank_lines_before)
+            })
+            .collect();
+        for (formatted, blank_lines_before) in formatted {
+            self.push(formatted, blank_lines_before);
+        }
+        if self.copy_rows {
+            self.buffer.clear();
+        } else {
+            self.buffer.drain(..end);
+            self.find_missing_delimiter();
+        }
+        true
+    }
+
+    /// Finds the delimiter of the string or comment the buffer ends inside,
+    /// so that lines are only split again once it's read.
+    fn find_missing_delimiter(&mut self) {
+        self.missing_delimiter = tokenize(&self.buffer, self.config.dialect)
+            .last()
+            .and_then(|t| t.missing_delimiter())
+            .map(str::to_string);
+    }
+
+    fn format_rest(&mut self) {
+        let buffer = std::mem::take(&mut self.buffer);
+        let statements = statements::split(&buffer, self.config.dialect);
+        let Some((last, inner)) = statements.split_last() else {
+            return;
+        };
+        for statement in inner {
+            let formatted =
+                format_checked(statement.text, &self.inner_config, &NullCancellationToken);
+            self.push(formatted, statement.blank_lines_before);
+        }
+        // `never` only drops the semicolon of a file's only statement
+        let only_statement = self.first && inner.is_empty();
+        let config = Configuration {
+            require_semicolons: match self.config.require_semicolons {
+                RequireSemicolons::Never if !only_statement => RequireSemicolons::Preserve,
+                mode => mode,
+            },
+            insert_final_newline: self.config.insert_final_newline || buffer.ends_with('\n'),
+            ..self.inner_config.clone()
+        };
+        let formatted = format_checked(last.text, &config, &NullCancellationToken);
+        self.push(formatted, last.blank_lines_before);
+    }
+
+    fn push(&mut self, formatted: Result<String>, blank_lines_before: usize) {
+        let formatted = formatted.map(|text| {
+            if std::mem::take(&mut self.first) {
+                return text;
+            }
+            let blank_lines = if self.config.preserve_blank_lines {
+                blank_lines_before.min(self.config.max_blank_lines.into())
+            } else {
+                usize::from(self.config.lines_between_queries).saturating_sub(1)
+            };
+            "\n".repeat(blank_lines) + &text
+        });
+        self.formatted.push_back(formatted);
+    }
+}
+
+impl<R: BufRead> Iterator for FormattedStatements<R> {
+    type Item = Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(formatted) = self.formatted.pop_front() {
+                return Some(formatted);
+            }
+            if self.done {
+                return None;
+            }
+            self.read_statements();
+        }
+    }
+}
@@ -0,0 +1,82 @@
+//! Layout of parenthesized subqueries, per `subqueryStyle`.
+//!
+//! sqlformat puts a subquery that starts a clause on its own line below the
+//! clause keyword. The indented style moves the opening parenthesis up onto
+//! the keyword's line and outdents the rest by one level, so the closing
+//! parenthesis lines up with the keyword.
+
+use crate::Configuration;
+use crate::Dialect;
+use crate::SubqueryStyle;
+use crate::keywords::is_keyword;
+use crate::lexer::Token;
+use crate::lexer::TokenKind;
+use crate::lexer::matching_paren;
+use crate::lexer::tokenize;
+use crate::trace;
+
+pub(crate) fn layout(formatted: String, config: &Configuration, dialect: Dialect) -> String {
+    if config.subquery_style == SubqueryStyle::Packed {
+        return formatted;
+    }
+    let indent = config.indent();
+
+    let tokens = tokenize(&formatted, dialect);
+    let mut joined = vec![false; tokens.len()];
+    let mut outdent = vec![0; tokens.len()];
+    for (i, token) in tokens.iter().enumerate() {
+        if token.kind == TokenKind::OpenParen && starts_clause_subquery(&tokens, i) {
+            let Some(close) = matching_paren(&tokens, i) else {
+                continue;
+            };
+            trace::decided("subquery: opened on the clause line");
+            joined[i - 1] = true;
+            for level in &mut outdent[i + 1..close] {
+                *level += 1;
+            }
+        }
+    }
+
+    let mut result = String::with_capacity(formatted.len());
+    for (i, token) in tokens.iter().enumerate() {
+        if joined[i] {
+            result.push(' ');
+        } else if outdent[i] > 0 && is_line_break(token) {
+            let line_start = token.text.rfind('\n').unwrap() + 1;
+            let mut text = token.text;
+            for _ in 0..outdent[i] {
+                text = text
+                    .strip_suffix(indent.as_str())
+                    .filter(|t| t.len() >= line_start)
+                    .unwrap_or(text);
+            }
+            result.push_str(text);
+        } else {
+            result.push_str(token.text);
+        }
+    }
+    result
+}
+
+/// Whether `tokens[i]` is a parenthesis alone on its line that opens a
+/// subquery right below a line holding only a clause keyword.
+fn starts_clause_subquery(tokens: &[Token], i: usize) -> bool {
+    let alone = i >= 2
+        && is_line_break(&tokens[i - 1])
+        && tokens.get(i + 1).is_some_and(is_line_break)
+        && tokens
+            .get(i + 2)
+            .is_some_and(|t| t.is_word("select") || t.is_word("with"));
+    alone && {
+        let keyword = &tokens[i - 2];
+        keyword.kind == TokenKind::Word
+            && is_keyword(keyword.text)
+            // the rest of a select list stays indented below it
+            && !keyword.is_word("select")
+            && (i == 2 || is_line_break(&tokens[i - 3]))
+    }
+}
+
+fn is_line_break(token: &Token) -> bool {
+    token.kind == TokenKind::Whitespace && token.text.contains('\n')
+}
@@ -0,0 +1,173 @@
+//! dprint-core tracing of formatting, for debugging specs.
+//!
+//! sqlformat lays out the text on its own, so there's no printer making
+//! decisions to trace. Instead the layout passes note what they decide with
+//! `decided`, and the output is rebuilt as print items: an info item at the
+//! start of each statement followed by one for each decision made formatting
+//! it, and the indentation of each line as indent signals. The trace analyzer
+//! then shows what was decided for each statement and how deep every line was
+//! indented.
+
+use std::cell::RefCell;
+
+#[cfg(feature = "tracing")]
+use dprint_core::formatting::LineNumber;
+#[cfg(feature = "tracing")]
+use dprint_core::formatting::PrintItems;
+#[cfg(feature = "tracing")]
+use dprint_core::formatting::PrintOptions;
+#[cfg(feature = "tracing")]
+use dprint_core::formatting::Signal;
+#[cfg(feature = "tracing")]
+use dprint_core::formatting::TracingResult;
+#[cfg(feature = "tracing")]
+use dprint_core::formatting::trace_printing;
+#[cfg(feature = "tracing")]
+use dprint_core::plugins::NullCancellationToken;
+
+#[cfg(feature = "tracing")]
+use crate::Configuration;
+#[cfg(feature = "tracing")]
+use crate::Dialect;
+#[cfg(feature = "tracing")]
+use crate::lexer::continued_lines;
+#[cfg(feature = "tracing")]
+use crate::statements;
+
+thread_local! {
+    /// The decisions noted on this thread, while a trace collects them.
+    static DECISIONS: RefCell<Option<Vec<&'static str>>> = const { RefCell::new(None) };
+}
+
+/// Notes that a layout pass decided `decision` for the statement being
+/// formatted, which the trace shows at its start.
+pub(crate) fn decided(decision: &'static str) {
+    DECISIONS.with_borrow_mut(|decisions| {
+        if let Some(decisions) = decisions {
+            decisions.push(decision);
+        }
+    });
+}
+
+/// The decisions noted while `format` runs.
+#[cfg(feature = "tracing")]
+fn collect(format: impl FnOnce()) -> Vec<&'static str> {
+    DECISIONS.set(Some(Vec::new()));
+    format();
+    DECISIONS.take().unwrap_or_default()
+}
+
+/// Traces printing `formatted`, the output of formatting `text`.
+#[cfg(feature = "tracing")]
+pub(crate) fn trace(
+    text: &str,
+    formatted: &str,
+    config: &Configuration,
+    dialect: Dialect,
+) -> TracingResult {
+    // each statement is formatted again on its own, so that the decisions
+    // are told apart by statement
+    let decisions: Vec<_> = statements::split(text, dialect)
+        .iter()
+        .map(|statement| {
+            collect(|| {
+                let _ = crate::format_checked(statement.text, config, &NullCancellationToken);
+            })
+        })
+        .collect();
+    let new_line_text = if formatted.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let formatted = formatted.replace("\r\n", "\n");
+    let options = PrintOptions {
+        max_width: u32::MAX,
+        indent_width: config.indent_width,
+        use_tabs: config.use_tabs,
+        new_line_text,
+    };
+    trace_printing(
+        || print_items(&formatted, &decisions, config, dialect),
+        options,
+    )
+}
+
+#[cfg(feature = "tracing")]
+fn print_items(
+    formatted: &str,
+    decisions: &[Vec<&'static str>],
+    config: &Configuration,
+    dialect: Dialect,
+) -> PrintItems {
+    let mut items = PrintItems::new();
+    let mut depth = 0;
+    let statements = statements::split(formatted, dialect);
+    let count = statements.len();
+    for (index, statement) in statements.into_iter().enumerate() {
+        if index > 0 {
+            for _ in 0..=statement.blank_lines_before {
+                items.push_signal(Signal::NewLine);
+            }
+        }
+        items.push_info(LineNumber::new("statement_start"));
+        // those of statements the output has fewer of go with the last
+        let decided = if index + 1 == count {
+            decisions.get(index..)
+        } else {
+            decisions.get(index..=index)
+        };
+        for &decision in decided.unwrap_or_default().iter().flatten() {
+            items.push_info(LineNumber::new(decision));
+        }
+        let continued = continued_lines(statement.text, dialect);
+        for (i, line) in statement.text.split('\n').enumerate() {
+            if i > 0 {
+                items.push_signal(Signal::NewLine);
+            }
+            // inside a literal or comment, where indentation is content
+            if continued.contains(&i) {
+                items.push_signal(Signal::StartIgnoringIndent);
+                items.push_string(line.to_string());
+                items.push_signal(Signal::FinishIgnoringIndent);
+                continue;
+            }
+            let content = line.trim_start();
+            let line_depth = indent_depth(&line[..line.len() - content.len()], config);
+            while depth < line_depth {
+                items.push_signal(Signal::StartIndent);
+                depth += 1;
+            }
+            while depth > line_depth {
+                items.push_signal(Signal::FinishIndent);
+                depth -= 1;
+            }
+            if !content.is_empty() {
+                items.push_string(content.to_string());
+            }
+        }
+    }
+    for _ in 0..depth {
+        items.push_signal(Signal::FinishIndent);
+    }
+    if formatted.ends_with('\n') {
+        items.push_signal(Signal::NewLine);
+    }
+    items
+}
+
+/// The number of indentation levels `indent` makes up, rounded down.
+#[cfg(feature = "tracing")]
+fn indent_depth(indent: &str, config: &Configuration) -> usize {
+    let columns: usize = indent
+        .chars()
+        .map(|c| {
+            if c == '\t' {
+                usize::from(config.indent_width)
+            } else {
+                1
+            }
+        })
+        .sum();
+    columns / usize::from(config.indent_width.max(1))
+}
@@ -0,0 +1,28 @@
+//! Layout of the `ON CONFLICT` clause of Postgres upserts.
+//!
+//! sqlformat reads `DO NOTHING` as a clause whose contents go on the lines
+//! after it, so when it ends a statement, the `;` is moved onto a line of its
+//! own. The `;` is put back after it.
+
+use crate::Dialect;
+use crate::lexer::TokenKind;
+use crate::lexer::tokenize;
+
+pub(crate) fn layout(formatted: String, dialect: Dialect) -> String {
+    let tokens = tokenize(&formatted, dialect);
+    let mut result = String::with_capacity(formatted.len());
+    for (i, token) in tokens.iter().enumerate() {
+        let before_semicolon = tokens
+            .get(i + 1)
+            .is_some_and(|t| t.kind == TokenKind::Semicolon);
+        let after_do_nothing = i >= 3
+            && tokens[i - 1].is_word("nothing")
+            && tokens[i - 2].kind == TokenKind::Whitespace
+            && tokens[i - 3].is_word("do");
+        if token.kind == TokenKind::Whitespace && before_semicolon && after_do_nothing {
+            continue;
+        }
+        result.push_str(token.text);
+    }
+    result
+}
@@ -0,0 +1,187 @@
+//! The `validateOutput` check that formatting only changed the whitespace and
+//! case of the input.
+//!
+//! Both are tokenized and their significant tokens compared, after taking out
+//! what the configuration deliberately changes: the quotes of identifiers,
+//! optional `AS` keywords, semicolons, the parentheses around set operands and
+//! the contents of formatted function bodies.
+
+use crate::AsKeyword;
+use crate::Configuration;
+use crate::Dialect;
+use crate::QuoteIdentifiers;
+use crate::RequireSemicolons;
+use crate::delimiter;
+use crate::delimiter::Chunk;
+use crate::diagnostics::line_column;
+use crate::lexer::Token;
+use crate::lexer::TokenKind;
+use crate::lexer::tokenize;
+use crate::literal_case::is_hex_string;
+
+/// An error describing the first token formatting would have changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangedTokensError {
+    /// 1-based line number of the token in the input.
+    pub line: usize,
+    /// 1-based column number of the token, counted in characters.
+    pub column: usize,
+    /// The token as written, if the input has one there.
+    pub original: Option<String>,
+    /// The token in the formatted output, if it's there at all.
+    pub formatted: Option<String>,
+}
+
+impl std::fmt::Display for ChangedTokensError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Formatting would change the SQL at line {}, column {}:\n  input:  {}\n  output: {}",
+            self.line,
+            self.column,
+            self.original.as_deref().unwrap_or("(missing)"),
+            self.formatted.as_deref().unwrap_or("(missing)")
+        )
+    }
+}
+
+impl std::error::Error for ChangedTokensError {}
+
+/// A token reduced to what formatting must keep, along with where it starts
+/// and its text.
+struct Compared<'a> {
+    key: String,
+    start: usize,
+    text: &'a str,
+}
+
+pub(crate) fn compare(
+    input: &str,
+    output: &str,
+    config: &Configuration,
+    dialect: Dialect,
+) -> Result<(), ChangedTokensError> {
+    let before = compared(input, config, dialect);
+    let after = compared(output, config, dialect);
+    let mut after = after.into_iter();
+    for token in &before {
+        match after.next() {
+            Some(formatted) if formatted.key == token.key => {}
+            formatted => return Err(error(input, token.start, Some(token.text), formatted)),
+        }
+    }
+    match after.next() {
+        None => Ok(()),
+        formatted => Err(error(input, input.len(), None, formatted)),
+    }
+}
+
+fn error(
+    input: &str,
+    offset: usize,
+    original: Option<&str>,
+    formatted: Option<Compared>,
+) -> ChangedTokensError {
+    let (line, column) = line_column(input, offset);
+    ChangedTokensError {
+        line,
+        column,
+        original: original.map(str::to_string),
+        formatted: formatted.map(|t| t.text.to_string()),
+    }
+}
+
+fn compared<'a>(text: &'a str, config: &Configuration, dialect: Dialect) -> Vec<Compared<'a>> {
+    // custom delimiters end up stuck to the token before them
+    let delimiters: Vec<_> = delimiter::split(text, dialect)
+        .into_iter()
+        .flatten()
+        .filter_map(|chunk| match chunk {
+            Chunk::Directive(line) => line.split_whitespace().nth(1),
+            _ => None,
+        })
+        .filter(|&delimiter| delimiter != ";")
+        .collect();
+    let mut tokens: Vec<Compared> = Vec::new();
+    let mut after_operator = false;
+    for token in tokenize(text, dialect) {
+        let delimiter = delimiters.iter().find(|&&delimiter| {
+            !token.is_trivia()
+                && token.text.len() > delimiter.len()
+                && token.text.ends_with(delimiter)
+        });
+        let token = match delimiter {
+            Some(delimiter) => Token {
+                text: &token.text[..token.text.len() - delimiter.len()],
+                ..token
+            },
+            None => token,
+        };
+        let Some(key) = key(&token, config) else {
+            continue;
+        };
+        // sqlformat may add or remove spaces in a run of operators, like `=-1`
+        let is_operator = token.kind == TokenKind::Operator;
+        if is_operator
+            && after_operator
+            && let Some(last) = tokens.last_mut()
+        {
+            last.key.push_str(&key);
+            last.text = &text[last.start..token.start + token.text.len()];
+            continue;
+        }
+        after_operator = is_operator;
+        tokens.push(Compared {
+            key,
+            start: token.start,
+            text: token.text,
+        });
+        if let Some(delimiter) = delimiter {
+            tokens.push(Compared {
+                key: delimiter.to_string(),
+                start: token.start + token.text.len(),
+                text: delimiter,
+            });
+            after_operator = false;
+        }
+    }
+    tokens
+}
+
+/// What must stay the same of `token`, or `None` if it may come and go.
+fn key(token: &Token, config: &Configuration) -> Option<String> {
+    match token.kind {
+        TokenKind::Whitespace => None,
+        TokenKind::Semicolon if config.require_semicolons != RequireSemicolons::Preserve => None,
+        TokenKind::OpenParen | TokenKind::CloseParen if config.parenthesize_set_operands => None,
+        TokenKind::Word if token.is_word("as") && config.as_keyword != AsKeyword::Preserve => None,
+        TokenKind::Word => Some(token.text.to_lowercase()),
+        TokenKind::QuotedIdentifier if config.quote_identifiers != QuoteIdentifiers::Preserve => {
+            let quote = token.text.chars().last()?;
+            let unquoted = token
+                .text
+                .get(1..token.text.len() - quote.len_utf8())
+                .unwrap_or_default();
+            let unescaped = unquoted.replace(&format!("{quote}{quote}"), &quote.to_string());
+            Some(unescaped.to_lowercase())
+        }
+        TokenKind::String if config.format_function_bodies && token.text.starts_with('$') => {
+            Some("$".to_string())
+        }
+        TokenKind::String if is_hex_string(token) => Some(format!("x{}", &token.text[1..])),
+        // exponents and hexadecimal digits are read in any case
+        TokenKind::Number => Some(token.text.to_lowercase()),
+        // sqlformat drops the spaces at their end
+        TokenKind::LineComment => Some(token.text.trim_end().to_string()),
+        // and indents their lines along with the query
+        TokenKind::BlockComment => Some(
+            token
+                .text
+                .lines()
+                .map(str::trim)
+                .collect::<Vec<_>>()
+                .join("\n"),
+        ),
+        _ => Some(token.text.to_string()),
+    }
+}
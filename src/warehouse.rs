@@ -0,0 +1,142 @@
+//! Syntax of the Snowflake and BigQuery dialects that sqlformat doesn't know.
+//!
+//! Type parameters (`ARRAY<INT64>`), Snowflake's semi-structured paths
+//! (`payload:items.id`) and BigQuery's hyphenated project names
+//! (`my-project.dataset.table`) would get spaces around their operators and
+//! BigQuery's `arr[OFFSET(0)]` one before its parenthesis, so they're
+//! protected.
+
+use std::borrow::Cow;
+
+use crate::Dialect;
+use crate::lexer::Token;
+use crate::lexer::TokenKind;
+use crate::lexer::matching_paren;
+use crate::lexer::tokenize;
+use crate::protect::Protected;
+
+pub(crate) fn protect<'a>(
+    text: &'a str,
+    dialect: Dialect,
+    protected: &mut Protected,
+) -> Cow<'a, str> {
+    let tokens = tokenize(text, dialect);
+    let mut result = String::with_capacity(text.len());
+    let mut pos = 0;
+    let mut i = 0;
+    while i < tokens.len() {
+        let end = type_parameters_end(&tokens, i)
+            .or_else(|| subscript_end(&tokens, i))
+            .or_else(|| (dialect == Dialect::Snowflake).then(|| path_end(&tokens, i))?)
+            .or_else(|| (dialect == Dialect::BigQuery).then(|| project_end(&tokens, i))?);
+        let Some(end) = end else {
+            i += 1;
+            continue;
+        };
+        let start = tokens[i].start;
+        let last = &tokens[end - 1];
+        result.push_str(&text[pos..start]);
+        result.push_str(&protected.insert(text[start..last.start + last.text.len()].to_string()));
+        pos = last.start + last.text.len();
+        i = end;
+    }
+    if pos == 0 {
+        return Cow::Borrowed(text);
+    }
+    result.push_str(&text[pos..]);
+    Cow::Owned(result)
+}
+
+/// The end of `ARRAY<...>`, `STRUCT<...>` or `MAP<...>` starting at
+/// `tokens[start]`.
+fn type_parameters_end(tokens: &[Token], start: usize) -> Option<usize> {
+    let is_type = ["array", "struct", "map"]
+        .iter()
+        .any(|word| tokens[start].is_word(word));
+    let open = tokens.get(start + 1)?;
+    if !is_type || open.kind != TokenKind::Operator || !open.text.starts_with('<') {
+        return None;
+    }
+    let mut depth = 0isize;
+    for (i, token) in tokens.iter().enumerate().skip(start + 1) {
+        match token.kind {
+            TokenKind::Operator => {
+                depth += token.text.matches('<').count() as isize;
+                depth -= token.text.matches('>').count() as isize;
+                if depth <= 0 {
+                    return (depth == 0).then_some(i + 1);
+                }
+            }
+            TokenKind::Semicolon => return None,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The end of the `OFFSET(...)` or `ORDINAL(...)` of an array subscript
+/// starting at `tokens[start]`.
+fn subscript_end(tokens: &[Token], start: usize) -> Option<usize> {
+    let is_subscript = start > 0
+        && tokens[start - 1].text == "["
+        && (tokens[start].is_word("offset") || tokens[start].is_word("ordinal"))
+        && tokens
+            .get(start + 1)
+            .is_some_and(|t| t.kind == TokenKind::OpenParen);
+    if !is_subscript {
+        return None;
+    }
+    matching_paren(tokens, start + 1).map(|close| close + 1)
+}
+
+/// The end of a semi-structured path like `payload:items.id` starting at
+/// `tokens[start]`.
+fn path_end(tokens: &[Token], start: usize) -> Option<usize> {
+    let is_name = |i: usize| {
+        tokens
+            .get(i)
+            .is_some_and(|t| matches!(t.kind, TokenKind::Word | TokenKind::QuotedIdentifier))
+    };
+    let separator = tokens.get(start + 1)?;
+    if !is_name(start) || separator.text != ":" || !is_name(start + 2) {
+        return None;
+    }
+    let mut end = start + 3;
+    while tokens.get(end).is_some_and(|t| t.kind == TokenKind::Dot) && is_name(end + 1) {
+        end += 2;
+    }
+    Some(end)
+}
+
+/// The end of a hyphenated project name like `my-project` starting at
+/// `tokens[start]`, which is only one when followed by a dataset.
+fn project_end(tokens: &[Token], start: usize) -> Option<usize> {
+    if tokens[start].kind != TokenKind::Word {
+        return None;
+    }
+    let mut end = start + 1;
+    while tokens.get(end).is_some_and(|t| t.text == "-")
+        && tokens
+            .get(end + 1)
+            .is_some_and(|t| matches!(t.kind, TokenKind::Word | TokenKind::Number))
+    {
+        end += 2;
+    }
+    let followed_by_dataset = tokens.get(end).is_some_and(|t| t.kind == TokenKind::Dot);
+    (end > start + 1 && followed_by_dataset).then_some(end)
+}
+
+/// Removes the space sqlformat puts after `::` when the type is a keyword, as
+/// in `payload::string`.
+pub(crate) fn tighten_casts(formatted: String, dialect: Dialect) -> String {
+    let tokens = tokenize(&formatted, dialect);
+    let mut result = String::with_capacity(formatted.len());
+    for (i, token) in tokens.iter().enumerate() {
+        let after_cast = i > 0 && tokens[i - 1].text == "::";
+        if after_cast && token.kind == TokenKind::Whitespace && !token.text.contains('\n') {
+            continue;
+        }
+        result.push_str(token.text);
+    }
+    result
+}
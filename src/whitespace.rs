@@ -0,0 +1,58 @@
+//! The `normalizeWhitespace` pass over the final text, which strips the
+//! spaces and tabs at the end of lines and replaces tabs with the configured
+//! indent.
+//!
+//! It runs on everything but string literals, quoted identifiers and `COPY`
+//! rows, whose whitespace is part of their value, so that comments and text
+//! otherwise kept as written get cleaned up too.
+
+use crate::Configuration;
+use crate::Dialect;
+use crate::lexer::TokenKind;
+use crate::lexer::tokenize;
+
+pub(crate) fn normalize(text: &str, config: &Configuration, dialect: Dialect) -> String {
+    if !config.normalize_whitespace {
+        return text.to_string();
+    }
+    // tabs are the configured indent when indenting with tabs
+    let tab = if config.use_tabs {
+        "\t".to_string()
+    } else {
+        config.indent()
+    };
+    let tokens = tokenize(text, dialect);
+    let mut result = String::with_capacity(text.len());
+    for (i, token) in tokens.iter().enumerate() {
+        let is_value = matches!(
+            token.kind,
+            TokenKind::String | TokenKind::QuotedIdentifier | TokenKind::CopyData
+        );
+        if is_value {
+            result.push_str(token.text);
+            continue;
+        }
+        let ends_line = tokens
+            .get(i + 1)
+            .is_none_or(|next| next.text.starts_with(['\r', '\n']));
+        let mut lines = token.text.split('\n').peekable();
+        while let Some(line) = lines.next() {
+            let (line, cr) = match line.strip_suffix('\r') {
+                Some(line) => (line, "\r"),
+                None => (line, ""),
+            };
+            // the last line of the token may go on in the next one
+            let line = if lines.peek().is_some() || ends_line {
+                line.trim_end_matches([' ', '\t'])
+            } else {
+                line
+            };
+            result.push_str(&line.replace('\t', &tab));
+            result.push_str(cr);
+            if lines.peek().is_some() {
+                result.push('\n');
+            }
+        }
+    }
+    result
+}
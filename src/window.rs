@@ -0,0 +1,113 @@
+//! Layout of window specifications (`OVER (...)` and `WINDOW w AS (...)`),
+//! per `windowStyle` and `windowInlineWidth`.
+//!
+//! sqlformat breaks every window specification the way it breaks a query,
+//! with each clause keyword and each of its items on a line of their own.
+//! This pass joins short specifications onto one line and, with the
+//! clause-per-line style, gives each of `PARTITION BY`, `ORDER BY` and the
+//! frame clause a single line.
+
+use crate::Configuration;
+use crate::Dialect;
+use crate::WindowStyle;
+use crate::lexer::Token;
+use crate::lexer::TokenKind;
+use crate::lexer::joined;
+use crate::lexer::matching_paren;
+use crate::lexer::tokenize;
+use crate::trace;
+
+pub(crate) fn layout(formatted: String, config: &Configuration, dialect: Dialect) -> String {
+    if config.window_style == WindowStyle::Default && config.window_inline_width == 0 {
+        return formatted;
+    }
+    let tokens = tokenize(&formatted, dialect);
+    let indent = config.indent();
+    let mut result = String::with_capacity(formatted.len());
+    let mut i = 0;
+    while i < tokens.len() {
+        let close = tokens[i].kind == TokenKind::OpenParen && is_window_spec(&tokens, i);
+        let Some(close) = close.then(|| matching_paren(&tokens, i)).flatten() else {
+            result.push_str(tokens[i].text);
+            i += 1;
+            continue;
+        };
+        let spec = &tokens[i + 1..close];
+        // joining lines would comment out the rest of the specification
+        if spec.iter().any(|t| t.kind == TokenKind::LineComment) {
+            trace::decided("window: kept as it is");
+            result.push_str(tokens[i].text);
+            i += 1;
+            continue;
+        }
+
+        let line_start = result.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let line = &result[line_start..];
+        let inline = format!("({})", joined(spec));
+        let width = config.window_inline_width;
+        if width > 0 && line.chars().count() + inline.chars().count() <= width {
+            trace::decided("window: inline");
+            result.push_str(&inline);
+        } else if config.window_style == WindowStyle::ClausePerLine {
+            trace::decided("window: clause per line");
+            let base = line[..line.len() - line.trim_start().len()].to_string();
+            result.push('(');
+            for clause in clauses(spec) {
+                result.push('\n');
+                result.push_str(&base);
+                result.push_str(&indent);
+                result.push_str(&joined(clause));
+            }
+            result.push('\n');
+            result.push_str(&base);
+            result.push(')');
+        } else {
+            trace::decided("window: too wide to inline");
+            for token in &tokens[i..=close] {
+                result.push_str(token.text);
+            }
+        }
+        i = close + 1;
+    }
+    result
+}
+
+/// Whether the parenthesis at `tokens[open]` starts a window specification.
+fn is_window_spec(tokens: &[Token], open: usize) -> bool {
+    let previous = tokens[..open].iter().rev().find(|t| !t.is_trivia());
+    if previous.is_some_and(|t| t.is_word("over")) {
+        return true;
+    }
+    // named windows, told apart from CTEs by what they start with
+    let first = tokens[open + 1..].iter().find(|t| !t.is_trivia());
+    previous.is_some_and(|t| t.is_word("as")) && first.is_some_and(starts_clause)
+}
+
+fn starts_clause(token: &Token) -> bool {
+    ["partition", "order", "rows", "range", "groups"]
+        .iter()
+        .any(|word| token.is_word(word))
+}
+
+/// Splits a window specification at its top level clause keywords.
+fn clauses<'a>(spec: &'a [Token<'a>]) -> Vec<&'a [Token<'a>]> {
+    let mut clauses = Vec::new();
+    let mut start = 0;
+    let mut depth = 0usize;
+    for (i, token) in spec.iter().enumerate() {
+        match token.kind {
+            TokenKind::OpenParen => depth += 1,
+            TokenKind::CloseParen => depth = depth.saturating_sub(1),
+            _ if depth == 0
+                && starts_clause(token)
+                && spec[start..i].iter().any(|t| !t.is_trivia()) =>
+            {
+                clauses.push(&spec[start..i]);
+                start = i;
+            }
+            _ => {}
+        }
+    }
+    clauses.push(&spec[start..]);
+    clauses
+}
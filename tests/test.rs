@@ -1,18 +1,59 @@
+use daaku_dprint_plugin_sql::AlteredLiteralError;
+use daaku_dprint_plugin_sql::AsKeyword;
+use daaku_dprint_plugin_sql::CancelledError;
+use daaku_dprint_plugin_sql::ChangedTokensError;
 use daaku_dprint_plugin_sql::Configuration;
+use daaku_dprint_plugin_sql::Dialect;
+use daaku_dprint_plugin_sql::Encoding;
+use daaku_dprint_plugin_sql::FileError;
+use daaku_dprint_plugin_sql::IdentifierCase;
+use daaku_dprint_plugin_sql::LintError;
+use daaku_dprint_plugin_sql::LintLevel;
+use daaku_dprint_plugin_sql::LintRule;
+use daaku_dprint_plugin_sql::LogicalOperatorPosition;
+use daaku_dprint_plugin_sql::ParseError;
+use daaku_dprint_plugin_sql::QueryParams;
+use daaku_dprint_plugin_sql::QuoteIdentifiers;
+use daaku_dprint_plugin_sql::SnippetOptions;
 use daaku_dprint_plugin_sql::SqlPluginHandler;
+use daaku_dprint_plugin_sql::StatementKind;
+use daaku_dprint_plugin_sql::UnstableFormatError;
+use daaku_dprint_plugin_sql::config_schema;
+use daaku_dprint_plugin_sql::fix_corpus;
+use daaku_dprint_plugin_sql::format_bytes;
+use daaku_dprint_plugin_sql::format_file;
+use daaku_dprint_plugin_sql::format_snippet;
+use daaku_dprint_plugin_sql::format_statements;
 use daaku_dprint_plugin_sql::format_text;
+use daaku_dprint_plugin_sql::format_text_cancellable;
+use daaku_dprint_plugin_sql::format_text_with_lints;
+use daaku_dprint_plugin_sql::format_text_with_params;
+use daaku_dprint_plugin_sql::format_text_with_validation;
+use daaku_dprint_plugin_sql::lint_text;
+use daaku_dprint_plugin_sql::run_corpus;
+use daaku_dprint_plugin_sql::split_statements;
+use daaku_dprint_plugin_sql::sqlfluff_options;
+#[cfg(feature = "tracing")]
+use daaku_dprint_plugin_sql::trace_file;
+use daaku_dprint_plugin_sql::validate_output;
+use daaku_dprint_plugin_sql::verify_stable;
 use dprint_core::configuration::ConfigKeyMap;
+use dprint_core::configuration::ConfigKeyValue;
 use dprint_core::configuration::NewLineKind;
 use dprint_core::configuration::resolve_global_config;
+use dprint_core::plugins::CancellationToken;
+use dprint_core::plugins::NullCancellationToken;
 use dprint_core::plugins::SyncPluginHandler;
 use dprint_development::ParseSpecOptions;
 use dprint_development::RunSpecsOptions;
 use dprint_development::ensure_no_diagnostics;
 use dprint_development::run_specs;
+use std::path::Path;
 use std::path::PathBuf;
 use std::sync::Arc;
 
 #[test]
+#[allow(clippy::bool_assert_comparison)]
 fn handle_global_config() {
     let mut global_config = ConfigKeyMap::new();
     global_config.insert(String::from("newLineKind"), "crlf".into());
@@ -33,6 +74,106 @@ fn use_defaults_when_global_not_set() {
     assert_eq!(config.new_line_kind, NewLineKind::LineFeed);
 }
 
+#[test]
+fn explicit_null_overrides_preset() {
+    let mut config = ConfigKeyMap::new();
+    config.insert(String::from("preset"), "compact".into());
+    config.insert(String::from("maxInlineArguments"), ConfigKeyValue::Null);
+    let result = SqlPluginHandler::new().resolve_config(config, &Default::default());
+    ensure_no_diagnostics(&result.diagnostics);
+    assert_eq!(result.config.max_inline_arguments, None);
+    assert_eq!(result.config.max_inline_top_level, Some(100));
+}
+
+#[test]
+fn case_exceptions_keep_words_as_written() {
+    let mut config = ConfigKeyMap::new();
+    config.insert(String::from("uppercase"), true.into());
+    config.insert(
+        String::from("caseExceptions"),
+        ConfigKeyValue::Array(vec!["iif".into(), "KEY".into()]),
+    );
+    let result = SqlPluginHandler::new().resolve_config(config, &Default::default());
+    ensure_no_diagnostics(&result.diagnostics);
+    assert_eq!(
+        format_text(
+            "select iif(a > 1, 1, 0), Key from t group by key",
+            &result.config
+        )
+        .unwrap()
+        .unwrap(),
+        "SELECT\n  iif(a > 1, 1, 0),\n  Key\nFROM\n  t\nGROUP BY\n  key\n",
+    );
+}
+
+#[test]
+fn overrides_apply_to_matching_paths() {
+    let override_for = |files: ConfigKeyValue, uppercase: bool| {
+        let mut fragment = ConfigKeyMap::new();
+        fragment.insert(String::from("files"), files);
+        fragment.insert(String::from("uppercase"), uppercase.into());
+        ConfigKeyValue::Object(fragment)
+    };
+    let mut config = ConfigKeyMap::new();
+    config.insert(String::from("indentWidth"), 4.into());
+    config.insert(
+        String::from("overrides"),
+        ConfigKeyValue::Array(vec![
+            override_for("migrations/**".into(), true),
+            override_for(
+                ConfigKeyValue::Array(vec!["seeds/**/*.sql".into(), "*.seed.sql".into()]),
+                false,
+            ),
+        ]),
+    );
+    let result = SqlPluginHandler::new().resolve_config(config, &Default::default());
+    ensure_no_diagnostics(&result.diagnostics);
+    let config = result.config;
+
+    let migration = config.for_path(Path::new("/repo/db/migrations/2024/01_init.sql"));
+    assert!(migration.uppercase);
+    assert_eq!(migration.indent_width, 4);
+    assert!(
+        !config
+            .for_path(Path::new("/repo/seeds/users.sql"))
+            .uppercase
+    );
+    assert!(
+        !config
+            .for_path(Path::new("/repo/migrations/users.seed.sql"))
+            .uppercase
+    );
+    assert!(
+        !config
+            .for_path(Path::new("/repo/queries/users.sql"))
+            .uppercase
+    );
+    assert!(
+        !config
+            .for_path(Path::new("/repo/old_migrations/a.sql"))
+            .uppercase
+    );
+}
+
+#[test]
+fn overrides_report_their_own_diagnostics() {
+    let mut fragment = ConfigKeyMap::new();
+    fragment.insert(String::from("files"), "*.sql".into());
+    fragment.insert(String::from("uppercas"), true.into());
+    let mut config = ConfigKeyMap::new();
+    config.insert(
+        String::from("overrides"),
+        ConfigKeyValue::Array(vec![ConfigKeyValue::Object(fragment), 1.into()]),
+    );
+    let result = SqlPluginHandler::new().resolve_config(config, &Default::default());
+    let names: Vec<_> = result
+        .diagnostics
+        .iter()
+        .map(|d| d.property_name.as_str())
+        .collect();
+    assert_eq!(names, ["overrides[0].uppercas", "overrides[1]"]);
+}
+
 #[test]
 fn test_specs() {
     let global_config = resolve_global_config(&mut Default::default()).config;
@@ -57,7 +198,18 @@ fn test_specs() {
             })
         },
         Arc::new(move |_file_path, _file_text, _spec_config| {
-            panic!("Plugin does not support dprint-core tracing.")
+            #[cfg(feature = "tracing")]
+            {
+                let spec_config: ConfigKeyMap =
+                    serde_json::from_value(_spec_config.clone().into()).unwrap();
+                let mut sph = SqlPluginHandler::new();
+                let config_result = sph.resolve_config(spec_config, &global_config);
+                ensure_no_diagnostics(&config_result.diagnostics);
+                let trace = trace_file(_file_text, &config_result.config).unwrap();
+                serde_json::to_string(&trace).unwrap()
+            }
+            #[cfg(not(feature = "tracing"))]
+            panic!("Run the tests with `--features tracing` to trace specs.")
         }),
     )
 }
@@ -72,3 +224,760 @@ fn should_handle_windows_newlines() {
         "select\n  *\nfrom\n  dbo.Test\n",
     );
 }
+
+#[test]
+fn should_keep_line_endings_inside_strings() {
+    let config = Configuration::default();
+    assert_eq!(
+        format_text("SELECT 'a\r\nb' FROM t\r\n", &config)
+            .unwrap()
+            .unwrap(),
+        "select\n  'a\r\nb'\nfrom\n  t\n",
+    );
+    let config = Configuration {
+        new_line_kind: NewLineKind::CarriageReturnLineFeed,
+        ..Default::default()
+    };
+    assert_eq!(
+        format_text("SELECT 'a\nb' FROM t\n", &config)
+            .unwrap()
+            .unwrap(),
+        "select\r\n  'a\nb'\r\nfrom\r\n  t\r\n",
+    );
+}
+
+#[test]
+fn altered_literal_error_shows_both_literals() {
+    let err = AlteredLiteralError {
+        line: 2,
+        column: 7,
+        original: "e'a\\nb'".to_string(),
+        formatted: None,
+    };
+    assert_eq!(
+        err.to_string(),
+        "Formatting would change the literal at line 2, column 7:\n  input:  e'a\\nb'\n  output: (missing)"
+    );
+}
+
+#[test]
+fn auto_new_line_kind_uses_the_dominant_line_ending() {
+    let config = Configuration {
+        new_line_kind: NewLineKind::Auto,
+        ..Default::default()
+    };
+    assert_eq!(
+        format_text("SELECT a\r\nFROM t\r\nWHERE b = 1\n", &config)
+            .unwrap()
+            .unwrap(),
+        "select\r\n  a\r\nfrom\r\n  t\r\nwhere\r\n  b = 1\r\n",
+    );
+    assert_eq!(
+        format_text("SELECT a\nFROM t\nWHERE b = 1\r\n", &config)
+            .unwrap()
+            .unwrap(),
+        "select\n  a\nfrom\n  t\nwhere\n  b = 1\n",
+    );
+}
+
+#[test]
+fn insert_final_newline_false_keeps_missing_newline() {
+    let config = Configuration {
+        insert_final_newline: false,
+        ..Default::default()
+    };
+    assert_eq!(
+        format_text("SELECT a FROM t", &config).unwrap().unwrap(),
+        "select\n  a\nfrom\n  t",
+    );
+    assert_eq!(
+        format_text("SELECT a FROM t\n", &config).unwrap().unwrap(),
+        "select\n  a\nfrom\n  t\n",
+    );
+}
+
+#[test]
+fn strict_mode_reports_parse_errors() {
+    let config = Configuration {
+        strict: true,
+        ..Default::default()
+    };
+    let err = format_text("SELECT *\nFROM t\nWHERE name = 'abc\n", &config).unwrap_err();
+    let err = err.downcast::<ParseError>().unwrap();
+    assert_eq!(err.line, 3);
+    assert_eq!(err.column, 14);
+    assert_eq!(err.token, "'abc...");
+    assert_eq!(
+        err.to_string(),
+        "Unterminated string literal at line 3, column 14: `'abc...`"
+    );
+
+    let err = format_text("SELECT count(*)) FROM t\n", &config).unwrap_err();
+    let err = err.downcast::<ParseError>().unwrap();
+    assert_eq!(err.message, "Unexpected closing parenthesis");
+    assert_eq!((err.line, err.column), (1, 16));
+
+    let err = format_text("SELECT coalesce(a, (b) FROM t\n", &config).unwrap_err();
+    let err = err.downcast::<ParseError>().unwrap();
+    assert_eq!(err.message, "Unclosed parenthesis");
+    assert_eq!(err.token, "(");
+    assert_eq!((err.line, err.column), (1, 16));
+}
+
+#[test]
+fn non_strict_mode_formats_unparseable_sql() {
+    let config = Configuration::default();
+    assert!(format_text("SELECT 'abc\n", &config).is_ok());
+}
+
+#[test]
+fn format_file_errors_name_the_file_and_statement() {
+    let config = Configuration {
+        strict: true,
+        ..Default::default()
+    };
+    let path = std::path::Path::new("migrations/002_users.sql");
+    let text = "SELECT 1;\n-- the users\nSELECT name\nFROM users WHERE name = 'abc;\n";
+    let err = format_file(path, text.as_bytes(), &config, &NullCancellationToken).unwrap_err();
+    let err = err.downcast::<FileError>().unwrap();
+    assert_eq!(err.path, path);
+    let location = err.location.as_ref().unwrap();
+    assert_eq!((location.line, location.column), (4, 25));
+    assert_eq!(&text[location.offset..location.offset + 4], "'abc");
+    assert_eq!(location.statement, "SELECT name...");
+    assert_eq!(
+        err.to_string(),
+        "migrations/002_users.sql:4:25: Unterminated string literal at line 4, column 25: `'abc;...`\n  in statement: SELECT name..."
+    );
+    assert!(err.error.downcast_ref::<ParseError>().is_some());
+
+    let err = format_file(path, &[0xFF, 0xFE, 0x00], &config, &NullCancellationToken).unwrap_err();
+    let err = err.downcast::<FileError>().unwrap();
+    assert!(err.location.is_none());
+    assert!(err.to_string().starts_with("migrations/002_users.sql: "));
+}
+
+#[test]
+fn file_errors_locate_changed_tokens() {
+    let config = Configuration {
+        validate_output: true,
+        ..Default::default()
+    };
+    let path = std::path::Path::new("queries/vars.sql");
+    // sqlformat splits `@@x` into `@` and `@x`
+    let text = "SELECT 1;
+SELECT a
+FROM t WHERE b = @@x;
+";
+    let (formatted, err) = format_text_with_validation(text, &config).unwrap();
+    assert_eq!(formatted, None);
+    let err = FileError::new(path, Some(text), config.dialect, err.unwrap().into());
+    let location = err.location.as_ref().unwrap();
+    assert_eq!((location.line, location.column), (3, 19));
+    assert_eq!(&text[location.offset..location.offset + 2], "@x");
+    assert_eq!(location.statement, "SELECT a...");
+}
+
+/// A token cancelled once it's been checked `after` times.
+#[derive(Debug)]
+struct CancelAfter {
+    after: usize,
+    checks: std::sync::atomic::AtomicUsize,
+}
+
+impl CancellationToken for CancelAfter {
+    fn is_cancelled(&self) -> bool {
+        let checks = self
+            .checks
+            .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        checks >= self.after
+    }
+}
+
+#[test]
+fn cancelled_formats_stop_between_statements() {
+    let config = Configuration::default();
+    let text = "SELECT a FROM t;\n".repeat(200);
+    let token = CancelAfter {
+        after: 10,
+        checks: Default::default(),
+    };
+    let err = format_text_cancellable(&text, &config, &token).unwrap_err();
+    assert!(err.is::<CancelledError>());
+
+    let token = CancelAfter {
+        after: 0,
+        checks: Default::default(),
+    };
+    let err = format_file(
+        std::path::Path::new("a.sql"),
+        b"SELECT 1;\n",
+        &config,
+        &token,
+    )
+    .unwrap_err();
+    assert!(err.is::<CancelledError>());
+}
+
+/// The expected outputs of the corpus are written by running this test with
+/// `FIX_CORPUS=1` set.
+#[test]
+fn corpus_formats_to_expected_output() {
+    let path = PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/corpus"));
+    let config = Configuration::default();
+    if std::env::var_os("FIX_CORPUS").is_some() {
+        fix_corpus(&path, &config).unwrap();
+    }
+    let report = run_corpus(&path, &config).unwrap();
+    assert!(report.passed > 0);
+    let failures: Vec<_> = report.failures.iter().map(ToString::to_string).collect();
+    assert!(
+        failures.is_empty(),
+        "{}\nupdate the expected output with `FIX_CORPUS=1 cargo test corpus`",
+        failures.join("\n")
+    );
+}
+
+/// `schema.json` is generated from `Configuration`, and written by running
+/// this test with `UPDATE_SCHEMA=1` set.
+#[test]
+fn schema_json_is_generated_from_configuration() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/schema.json");
+    let generated = serde_json::to_string_pretty(&config_schema()).unwrap() + "\n";
+    if std::env::var_os("UPDATE_SCHEMA").is_some() {
+        std::fs::write(path, &generated).unwrap();
+    }
+    assert!(
+        std::fs::read_to_string(path).unwrap() == generated,
+        "schema.json is out of date, update it with `UPDATE_SCHEMA=1 cargo test schema_json`"
+    );
+}
+
+#[test]
+fn lint_reports_each_rule() {
+    let config = Configuration::default();
+    let findings = |text| {
+        lint_text(text, &config)
+            .into_iter()
+            .map(|d| (d.rule, d.line, d.column))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        findings("SELECT *\nFROM a, b\nWHERE EXISTS (SELECT * FROM c);\n"),
+        vec![
+            (LintRule::SelectStar, 1, 8),
+            (LintRule::ImplicitCrossJoin, 2, 7),
+        ]
+    );
+    assert_eq!(
+        findings("DELETE FROM a;\nUPDATE b SET x = 1 WHERE id = 2;\n"),
+        vec![(LintRule::MissingWhere, 1, 1)]
+    );
+    assert_eq!(
+        findings("SELECT a.x AS x, count(*) n FROM a\n"),
+        vec![(LintRule::InconsistentAlias, 1, 27)]
+    );
+    assert!(findings("SELECT a.x AS x FROM a JOIN b AS bb ON a.id = bb.id\n").is_empty());
+}
+
+#[test]
+fn lint_option_warns_by_default() {
+    let config = Configuration {
+        lint: true,
+        ..Default::default()
+    };
+    let (formatted, findings) = format_text_with_lints("SELECT * FROM t\n", &config).unwrap();
+    assert_eq!(formatted.unwrap(), "select\n  *\nfrom\n  t\n");
+    assert_eq!(
+        findings
+            .iter()
+            .map(|d| (d.rule, d.line, d.column))
+            .collect::<Vec<_>>(),
+        vec![(LintRule::SelectStar, 1, 8)]
+    );
+    assert!(format_text("SELECT * FROM t\n", &config).is_ok());
+}
+
+#[test]
+fn lint_level_error_fails_formatting() {
+    let config = Configuration {
+        lint: true,
+        lint_level: LintLevel::Error,
+        ..Default::default()
+    };
+    let err = format_text("SELECT * FROM t\n", &config).unwrap_err();
+    let err = err.downcast::<LintError>().unwrap();
+    assert_eq!(
+        err.to_string(),
+        "Found 1 lint problem(s):\n  1:8: Avoid `SELECT *`, list the columns explicitly (select-star)"
+    );
+    assert!(format_text("SELECT id FROM t\n", &config).is_ok());
+}
+
+#[test]
+fn verify_stable_reports_first_divergence() {
+    let config = Configuration::default();
+    assert!(verify_stable("SELECT a FROM t\n", &config).is_ok());
+
+    // sqlformat treats ADD as a keyword and keeps moving its arguments
+    let text = "SELECT a FROM t WHERE x = add(1,2)\n";
+    let err = verify_stable(text, &config).unwrap_err();
+    let err = err.downcast::<UnstableFormatError>().unwrap();
+    assert_eq!((err.line, err.column), (8, 1));
+    assert_eq!(err.first, "(1, 2)");
+    assert_eq!(err.second, "  (1, 2)");
+
+    let config = Configuration {
+        verify_stable: true,
+        ..config
+    };
+    let err = format_text(text, &config).unwrap_err();
+    assert!(err.downcast_ref::<UnstableFormatError>().is_some());
+}
+
+#[test]
+fn validate_output_reports_changed_tokens() {
+    let config = Configuration {
+        validate_output: true,
+        as_keyword: AsKeyword::Always,
+        quote_identifiers: QuoteIdentifiers::Always,
+        ..Configuration::default()
+    };
+    let text = "SELECT a b, `say \"hi\"` FROM t WHERE x=-1;\n";
+    assert!(validate_output(text, &config).is_ok());
+    assert_eq!(
+        format_text(text, &config).unwrap().unwrap(),
+        "select\n  \"a\" as \"b\",\n  \"say \"\"hi\"\"\"\nfrom\n  \"t\"\nwhere\n  \"x\" =- 1;\n",
+    );
+
+    // sqlformat splits `@@x` into `@` and `@x`
+    let text = "SELECT a FROM t WHERE b = @@x;\n";
+    assert_eq!(format_text(text, &config).unwrap(), None);
+    let (formatted, err) = format_text_with_validation(text, &config).unwrap();
+    assert_eq!(formatted, None);
+    let err = err.unwrap();
+    assert_eq!((err.line, err.column), (1, 28));
+    assert_eq!(err.original.as_deref(), Some("@x"));
+    assert_eq!(err.formatted.as_deref(), Some("@"));
+
+    // parameter values take the place of the parameters
+    let config = Configuration {
+        params: QueryParams::Indexed(vec!["42".to_string()]),
+        ..config
+    };
+    let err = validate_output("SELECT a FROM t WHERE id = $1\n", &config).unwrap_err();
+    let err = err.downcast::<ChangedTokensError>().unwrap();
+    assert_eq!((err.line, err.column), (1, 28));
+    assert_eq!(err.original.as_deref(), Some("$1"));
+    assert_eq!(err.formatted.as_deref(), Some("42"));
+}
+
+#[test]
+fn should_preserve_utf8_bom() {
+    let config = Configuration::default();
+    assert_eq!(
+        format_bytes(b"\xEF\xBB\xBFSELECT 1", &config)
+            .unwrap()
+            .unwrap(),
+        b"\xEF\xBB\xBFselect\n  1\n",
+    );
+    assert_eq!(
+        format_bytes(b"\xEF\xBB\xBFselect\n  1\n", &config).unwrap(),
+        None
+    );
+}
+
+#[test]
+fn should_round_trip_utf16_and_latin1() {
+    let config = Configuration::default();
+    let utf16 = |bom: &[u8], text: &str, to_bytes: fn(u16) -> [u8; 2]| {
+        let mut bytes = bom.to_vec();
+        bytes.extend(text.encode_utf16().flat_map(to_bytes));
+        bytes
+    };
+    assert_eq!(
+        format_bytes(&utf16(&[0xFF, 0xFE], "SELECT 1", u16::to_le_bytes), &config)
+            .unwrap()
+            .unwrap(),
+        utf16(&[0xFF, 0xFE], "select\n  1\n", u16::to_le_bytes),
+    );
+    assert_eq!(
+        format_bytes(&utf16(&[0xFE, 0xFF], "SELECT 1", u16::to_be_bytes), &config)
+            .unwrap()
+            .unwrap(),
+        utf16(&[0xFE, 0xFF], "select\n  1\n", u16::to_be_bytes),
+    );
+    // no byte order mark, as written by some exports
+    assert_eq!(
+        format_bytes(&utf16(&[], "SELECT 1", u16::to_le_bytes), &config)
+            .unwrap()
+            .unwrap(),
+        utf16(&[], "select\n  1\n", u16::to_le_bytes),
+    );
+    assert_eq!(
+        format_bytes(b"SELECT 'caf\xE9'", &config).unwrap().unwrap(),
+        b"select\n  'caf\xE9'\n",
+    );
+}
+
+#[test]
+fn should_reencode_with_configured_encoding() {
+    let config = Configuration {
+        encoding: Encoding::Utf8,
+        ..Default::default()
+    };
+    assert_eq!(
+        format_bytes(b"SELECT 'caf\xE9'", &config).unwrap().unwrap(),
+        "select\n  'caf\u{e9}'\n".as_bytes(),
+    );
+    let config = Configuration {
+        encoding: Encoding::Latin1,
+        ..Default::default()
+    };
+    assert!(format_bytes("SELECT '\u{2603}'".as_bytes(), &config).is_err());
+}
+
+#[test]
+fn split_statements_returns_ranges_and_kinds() {
+    let text = "-- users\nCREATE TABLE users (id int);\n\nINSERT INTO users VALUES (1); -- one\nSET search_path = app;\n-- the end\n";
+    let spans = split_statements(text, Dialect::PostgreSql);
+    let found: Vec<_> = spans
+        .iter()
+        .map(|s| (&text[s.range.clone()], s.kind))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            ("-- users\nCREATE TABLE users (id int);", StatementKind::Ddl),
+            ("INSERT INTO users VALUES (1); -- one", StatementKind::Dml),
+            ("SET search_path = app;", StatementKind::Other),
+            ("-- the end", StatementKind::CommentOnly),
+        ]
+    );
+}
+
+#[test]
+fn split_statements_follows_delimiter_directives() {
+    let text = "DROP PROCEDURE IF EXISTS p;\nDELIMITER //\nCREATE PROCEDURE p() BEGIN SELECT 1; END//\nDELIMITER ;\nCALL p();\n";
+    let spans = split_statements(text, Dialect::MySql);
+    let found: Vec<_> = spans
+        .iter()
+        .map(|s| (&text[s.range.clone()], s.kind))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            ("DROP PROCEDURE IF EXISTS p;", StatementKind::Ddl),
+            (
+                "CREATE PROCEDURE p() BEGIN SELECT 1; END//",
+                StatementKind::Ddl
+            ),
+            ("CALL p();", StatementKind::Other),
+        ]
+    );
+}
+
+#[test]
+fn format_statements_matches_format_text() {
+    let text = "CREATE TABLE t (id int);\n\n-- rows\nINSERT INTO t VALUES (1), (2);\nSELECT 'a;b' FROM t; SELECT id\nFROM t\nWHERE id = 1\n";
+    for config in [
+        Configuration::default(),
+        Configuration {
+            lines_between_queries: 2,
+            ..Default::default()
+        },
+        Configuration {
+            preserve_blank_lines: true,
+            ..Default::default()
+        },
+    ] {
+        let statements = format_statements(text.as_bytes(), &config)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(statements.len(), 4);
+        assert_eq!(
+            statements.concat(),
+            format_text(text, &config).unwrap().unwrap()
+        );
+    }
+}
+
+#[test]
+fn format_statements_writes_copy_rows_as_read() {
+    let text = "COPY t (id, name) FROM stdin;\n1\ta;b\n2\tc\n\\.\nSELECT 1;\n";
+    let config = Configuration::default();
+    let statements = format_statements(text.as_bytes(), &config)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(
+        statements,
+        [
+            "COPY t (id, name)\nfrom\n  stdin;\n",
+            "1\ta;b\n",
+            "2\tc\n",
+            "\\.\n",
+            "select\n  1;\n"
+        ]
+    );
+    assert_eq!(
+        statements.concat(),
+        format_text(text, &config).unwrap().unwrap()
+    );
+}
+
+#[test]
+fn format_statements_waits_for_the_end_of_strings() {
+    let text = "CREATE FUNCTION f() RETURNS int AS $$\nSELECT 1;\nSELECT 2;\n$$ LANGUAGE sql; SELECT 'a;\nb;';\n/* c;\n*/ SELECT 3;\n";
+    let config = Configuration::default();
+    let statements = format_statements(text.as_bytes(), &config)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(statements.len(), 3);
+    assert_eq!(
+        statements.concat(),
+        format_text(text, &config).unwrap().unwrap()
+    );
+}
+
+#[test]
+fn format_statements_reports_errors_per_statement() {
+    let config = Configuration {
+        strict: true,
+        ..Default::default()
+    };
+    let results: Vec<_> =
+        format_statements("SELECT 1;\nSELECT (2;\nSELECT 3;\n".as_bytes(), &config)
+            .map(|result| result.is_ok())
+            .collect();
+    assert_eq!(results, [true, false, true]);
+}
+
+#[test]
+fn many_statements_keep_their_order() {
+    let text: String = (0..200)
+        .map(|i| format!("SELECT a, b FROM t{i} WHERE id = {i}; -- row {i}\n"))
+        .collect();
+    let expected: String = (0..200)
+        .map(|i| format!("select\n  a,\n  b\nfrom\n  t{i}\nwhere\n  id = {i};\n-- row {i}\n"))
+        .collect();
+    for preserve_blank_lines in [false, true] {
+        let config = Configuration {
+            preserve_blank_lines,
+            ..Default::default()
+        };
+        assert_eq!(format_text(&text, &config).unwrap().unwrap(), expected);
+    }
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn trace_prints_the_formatted_text() {
+    let config = Configuration::default();
+    let text = "SELECT a, 'x\n  y' FROM t;\n\nUPDATE t SET a = 1;\n";
+    let trace = trace_file(text, &config).unwrap();
+    let printed: String = trace
+        .writer_nodes
+        .iter()
+        .map(|node| node.text.as_str())
+        .collect();
+    assert_eq!(printed, format_text(text, &config).unwrap().unwrap());
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn trace_shows_the_decisions_of_each_statement() {
+    use dprint_core::formatting::TraceInfo;
+    use dprint_core::formatting::TracePrintItem;
+
+    let config = Configuration {
+        case_inline_width: 40,
+        window_inline_width: 40,
+        ..Default::default()
+    };
+    let text = "SELECT CASE WHEN a THEN 1 END FROM t;
+SELECT rank() OVER (ORDER BY a) FROM t;
+";
+    let trace = trace_file(text, &config).unwrap();
+    let infos: Vec<_> = trace
+        .print_nodes
+        .iter()
+        .filter_map(|node| match &node.print_item {
+            TracePrintItem::Info(TraceInfo::LineNumber(info)) => Some(info.name.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        infos,
+        [
+            "statement_start",
+            "case: inline",
+            "statement_start",
+            "window: inline",
+        ]
+    );
+}
+
+#[test]
+fn sqlfluff_config_provides_defaults() {
+    let path = std::env::temp_dir().join("dprint-plugin-sql-test.sqlfluff");
+    std::fs::write(
+        &path,
+        "[sqlfluff]\ndialect = postgres\n\n[sqlfluff:indentation]\nindent_unit = space\ntab_space_size = 4\n\n[sqlfluff:rules:capitalisation.keywords]\ncapitalisation_policy = upper\n\n[sqlfluff:rules:capitalisation.identifiers]\nextended_capitalisation_policy = lower\n\n[sqlfluff:layout:type:comma]\nline_position = leading\n\n[sqlfluff:layout:type:binary_operator]\nline_position = trailing\n",
+    )
+    .unwrap();
+    let mut config = ConfigKeyMap::new();
+    config.insert(
+        String::from("sqlfluffConfigPath"),
+        path.to_string_lossy().as_ref().into(),
+    );
+    config.insert(String::from("indentWidth"), 2.into());
+    let result = SqlPluginHandler::new().resolve_config(config, &Default::default());
+    std::fs::remove_file(&path).unwrap();
+    let messages: Vec<_> = result
+        .diagnostics
+        .iter()
+        .map(|d| (d.property_name.as_str(), d.message.as_str()))
+        .collect();
+    assert_eq!(
+        messages,
+        [(
+            "sqlfluffConfigPath",
+            "The sqlfluff setting for leading commas is not supported."
+        )]
+    );
+    let config = result.config;
+    assert_eq!(config.dialect, Dialect::PostgreSql);
+    assert!(config.uppercase);
+    assert_eq!(config.identifier_case, IdentifierCase::Lower);
+    assert!(!config.use_tabs);
+    assert_eq!(config.indent_width, 2);
+    assert_eq!(
+        config.logical_operator_position,
+        LogicalOperatorPosition::TrailingNewline
+    );
+
+    // for hosts that read the file themselves
+    let (options, unsupported) = sqlfluff_options("[sqlfluff]\ndialect = postgres\n");
+    assert_eq!(options.get("dialect"), Some(&"postgresql".into()));
+    assert!(unsupported.is_empty());
+}
+
+#[test]
+fn format_text_with_params_inlines_values() {
+    let config = Configuration::default();
+    assert_eq!(
+        format_text_with_params(
+            "SELECT * FROM users WHERE id = ? AND name = ?",
+            QueryParams::Indexed(vec!["42".to_string(), "'bob'".to_string()]),
+            &config,
+        )
+        .unwrap()
+        .unwrap(),
+        "select\n  *\nfrom\n  users\nwhere\n  id = 42\n  and name = 'bob'\n",
+    );
+
+    let mut params = ConfigKeyMap::new();
+    params.insert(String::from("id"), 7.into());
+    let mut config = ConfigKeyMap::new();
+    config.insert(String::from("params"), ConfigKeyValue::Object(params));
+    let result = SqlPluginHandler::new().resolve_config(config, &Default::default());
+    ensure_no_diagnostics(&result.diagnostics);
+    assert_eq!(
+        format_text(
+            "SELECT a FROM t WHERE id = :id;\nSELECT :id;\n",
+            &result.config
+        )
+        .unwrap()
+        .unwrap(),
+        "select\n  a\nfrom\n  t\nwhere\n  id = 7;\nselect\n  7;\n",
+    );
+}
+
+#[test]
+fn frameworks_configure_migrations_by_kind() {
+    let options = |key: &str| {
+        let mut fragment = ConfigKeyMap::new();
+        fragment.insert(String::from(key), true.into());
+        ConfigKeyValue::Object(fragment)
+    };
+    let mut frameworks = ConfigKeyMap::new();
+    frameworks.insert(String::from("up"), options("strict"));
+    frameworks.insert(String::from("down"), options("skip"));
+    frameworks.insert(String::from("sideways"), options("skip"));
+    let mut config = ConfigKeyMap::new();
+    config.insert(
+        String::from("frameworks"),
+        ConfigKeyValue::Object(frameworks),
+    );
+    let mut fragment = ConfigKeyMap::new();
+    fragment.insert(String::from("files"), "legacy/**".into());
+    fragment.insert(String::from("strict"), false.into());
+    config.insert(
+        String::from("overrides"),
+        ConfigKeyValue::Array(vec![ConfigKeyValue::Object(fragment)]),
+    );
+    let result = SqlPluginHandler::new().resolve_config(config, &Default::default());
+    let names: Vec<_> = result
+        .diagnostics
+        .iter()
+        .map(|d| d.property_name.as_str())
+        .collect();
+    assert_eq!(names, ["frameworks.sideways"]);
+    let config = result.config;
+
+    let up = config.for_path(Path::new("/repo/db/V001__create_users.sql"));
+    assert!(up.strict && !up.skip);
+    assert!(config.for_path(Path::new("/repo/1_init.up.sql")).strict);
+    assert!(config.for_path(Path::new("/repo/1_init.down.sql")).skip);
+    assert!(config.for_path(Path::new("/repo/U001__drop.sql")).skip);
+    assert!(!config.for_path(Path::new("/repo/legacy/2_a.up.sql")).strict);
+    let other = config.for_path(Path::new("/repo/queries/users.sql"));
+    assert!(!other.strict && !other.skip);
+}
+
+#[test]
+fn format_snippet_keeps_the_indentation_around_it() {
+    let config = Configuration::default();
+    let options = SnippetOptions {
+        indent_offset: 4,
+        skip_trailing_newline: true,
+    };
+    let text = "\n      SELECT a, 'x\n  y' FROM t\n      WHERE b = 1\n    ";
+    let formatted = format_snippet(text, &config, options).unwrap().unwrap();
+    assert_eq!(
+        formatted,
+        "    select\n      a,\n      'x\n  y'\n    from\n      t\n    where\n      b = 1",
+    );
+    assert_eq!(format_snippet(&formatted, &config, options).unwrap(), None);
+
+    let config = Configuration {
+        use_tabs: true,
+        ..config
+    };
+    assert_eq!(
+        format_snippet(
+            "SELECT 1",
+            &config,
+            SnippetOptions {
+                indent_offset: 5,
+                skip_trailing_newline: false,
+            }
+        )
+        .unwrap()
+        .unwrap(),
+        "\t\t select\n\t\t \t1\n",
+    );
+
+    // other whitespace is no indentation
+    assert_eq!(
+        format_snippet(
+            " select a\n\u{a0}from t\n",
+            &config,
+            SnippetOptions::default()
+        )
+        .unwrap()
+        .unwrap(),
+        "select\n\ta\nfrom\n\tt\n",
+    );
+}